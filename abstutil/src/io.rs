@@ -21,8 +21,8 @@ pub fn to_json<T: Serialize>(obj: &T) -> String {
 
 // TODO Idea: Have a wrapper type DotJSON(...) and DotBin(...) to distinguish raw path strings
 fn maybe_write_json<T: Serialize>(path: &str, obj: &T) -> Result<(), Error> {
-    if !path.ends_with(".json") {
-        panic!("write_json needs {} to end with .json", path);
+    if !path.ends_with(".json") && !path.ends_with(".geojson") {
+        panic!("write_json needs {} to end with .json or .geojson", path);
     }
     std::fs::create_dir_all(std::path::Path::new(path).parent().unwrap())
         .expect("Creating parent dir failed");
@@ -112,6 +112,13 @@ pub fn path_all_ab_test_saves(map_name: &str, test_name: &str) -> String {
     format!("../data/player/ab_test_saves/{}/{}", map_name, test_name)
 }
 
+pub fn path_ab_test_results(map_name: &str, test_name: &str) -> String {
+    format!(
+        "../data/player/ab_test_results/{}/{}.json",
+        map_name, test_name
+    )
+}
+
 pub fn path_camera_state(map_name: &str) -> String {
     format!("../data/player/camera_state/{}.json", map_name)
 }
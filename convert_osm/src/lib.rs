@@ -1,14 +1,17 @@
 mod clip;
-mod neighborhoods;
+pub mod neighborhoods;
+mod osm_change;
 mod osm_reader;
 mod split_ways;
 mod srtm;
 
+pub use self::osm_change::OsmChange;
+
 use abstutil::Timer;
 use geom::{Distance, FindClosest, Line, PolyLine, Pt2D};
 use kml::ExtraShapes;
 use map_model::raw::{DrivingSide, OriginalBuilding, OriginalRoad, RawMap};
-use map_model::{osm, LaneID, OffstreetParking, Position};
+use map_model::{osm, LaneID, OffstreetParking, ParkingRules, Position};
 
 // Just used for matching hints to different sides of a road.
 const DIRECTED_ROAD_THICKNESS: Distance = Distance::const_meters(2.5);
@@ -27,16 +30,16 @@ pub struct Flags {
 }
 
 pub fn convert(flags: &Flags, timer: &mut abstutil::Timer) -> RawMap {
-    let (mut map, amenities) = split_ways::split_up_roads(
-        osm_reader::extract_osm(&flags.osm, &flags.clip, timer),
-        timer,
-    );
-    clip::clip_map(&mut map, timer);
-    map.driving_side = if flags.drive_on_right {
+    let driving_side = if flags.drive_on_right {
         DrivingSide::Right
     } else {
         DrivingSide::Left
     };
+    let (mut map, amenities) = split_ways::split_up_roads(
+        osm_reader::extract_osm(&flags.osm, &flags.clip, driving_side, timer),
+        timer,
+    );
+    clip::clip_map(&mut map, timer);
 
     // Need to do a first pass of removing cul-de-sacs here, or we wind up with loop PolyLines when
     // doing the parking hint matching.
@@ -56,6 +59,10 @@ pub fn convert(flags: &Flags, timer: &mut abstutil::Timer) -> RawMap {
     if let Some(ref path) = flags.gtfs {
         timer.start("load GTFS");
         map.bus_routes = gtfs::load(path);
+        // A whole city's feed covers way more than one map
+        let gps_bounds = &map.gps_bounds;
+        map.bus_routes
+            .retain(|r| r.stops.iter().any(|pt| gps_bounds.contains(*pt)));
         timer.stop("load GTFS");
     }
     if let Some(ref path) = flags.elevation {
@@ -145,11 +152,7 @@ fn use_parking_hints(map: &mut RawMap, path: String, timer: &mut Timer) {
             }
 
             tags.insert(
-                if fwds {
-                    osm::PARKING_RIGHT.to_string()
-                } else {
-                    osm::PARKING_LEFT.to_string()
-                },
+                format!("parking:lane:{}", map.driving_side.osm_side(fwds)),
                 if has_parking {
                     "parallel".to_string()
                 } else {
@@ -208,6 +211,7 @@ fn use_offstreet_parking(map: &mut RawMap, path: String, timer: &mut Timer) {
             // Temporary values, populate later
             driveway_line: Line::new(Pt2D::new(0.0, 0.0), Pt2D::new(1.0, 1.0)),
             driving_pos: Position::new(LaneID(0), Distance::ZERO),
+            rules: ParkingRules::free(),
         });
         None
     });
@@ -278,18 +282,12 @@ fn use_sidewalk_hints(map: &mut RawMap, path: String, timer: &mut Timer) {
                     continue;
                 }
 
-                if fwds {
-                    if osm_tags.get(osm::SIDEWALK) == Some(&"left".to_string()) {
-                        osm_tags.insert(osm::SIDEWALK.to_string(), "both".to_string());
-                    } else {
-                        osm_tags.insert(osm::SIDEWALK.to_string(), "right".to_string());
-                    }
+                let side = map.driving_side.osm_side(fwds);
+                let other_side = map.driving_side.osm_side(!fwds);
+                if osm_tags.get(osm::SIDEWALK) == Some(&other_side.to_string()) {
+                    osm_tags.insert(osm::SIDEWALK.to_string(), "both".to_string());
                 } else {
-                    if osm_tags.get(osm::SIDEWALK) == Some(&"right".to_string()) {
-                        osm_tags.insert(osm::SIDEWALK.to_string(), "both".to_string());
-                    } else {
-                        osm_tags.insert(osm::SIDEWALK.to_string(), "left".to_string());
-                    }
+                    osm_tags.insert(osm::SIDEWALK.to_string(), side.to_string());
                 }
             }
         }
@@ -52,6 +52,10 @@ fn convert_polygon(input: PolygonType, name: String, map_name: String, gps_bound
             return;
         }
     }
+    // GeoJSON repeats the first point at the end; NeighborhoodBuilder doesn't.
+    if points.len() > 1 && points[0] == *points.last().unwrap() {
+        points.pop();
+    }
     NeighborhoodBuilder {
         map_name,
         name,
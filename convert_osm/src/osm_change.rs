@@ -0,0 +1,739 @@
+use abstutil::{prettyprint_usize, FileWithProgress, Timer};
+use geom::{Line, Pt2D};
+use map_model::raw::{
+    MapFixes, OriginalIntersection, OriginalRoad, RawMap, RawRoad, RestrictionType,
+};
+use map_model::{
+    get_osm_lane_tags, EditIntersection, IntersectionType, LaneID, Map, RoadID, TurnType,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
+use xmltree::Element;
+
+// Proposed changes to OSM, gathered from map_editor fixes to a RawMap and from MapEdits, then
+// written as an osmChange file that can be reviewed in JOSM and shared with the OSM community or
+// city GIS staff. Everything is expressed relative to what was imported, then applied to the
+// elements of a local OSM extract, so tags that somebody changed upstream since the import aren't
+// reverted.
+//
+// This covers lanes (through the lanes, cycleway, parking:lane, and sidewalk tags), other road
+// tags edited in map_editor, traffic signals, and turn restrictions. Creating or deleting ways,
+// and splitting a way when only part of it changed, aren't handled yet.
+pub struct OsmChange {
+    // Per OSM way and node, the tags to set, or remove if None
+    ways: BTreeMap<i64, BTreeMap<String, Option<String>>>,
+    nodes: BTreeMap<i64, BTreeMap<String, Option<String>>>,
+    // (from way, via node, to way) to the value of the restriction tag
+    new_restrictions: BTreeMap<(i64, i64, i64), String>,
+    deleted_restrictions: BTreeSet<(i64, i64, i64)>,
+}
+
+impl OsmChange {
+    pub fn new() -> OsmChange {
+        OsmChange {
+            ways: BTreeMap::new(),
+            nodes: BTreeMap::new(),
+            new_restrictions: BTreeMap::new(),
+            deleted_restrictions: BTreeSet::new(),
+        }
+    }
+
+    // raw must be the RawMap before any fixes were applied.
+    pub fn add_fixes(&mut self, raw: &RawMap, fixes: &MapFixes, timer: &mut Timer) {
+        let mut fixed = raw.roads.clone();
+        for r in &fixes.delete_roads {
+            fixed.remove(r);
+        }
+        for (id, r) in &fixes.override_roads {
+            fixed.insert(*id, r.clone());
+        }
+
+        // Group the pieces of each OSM way. The tags of every piece are the same in the original.
+        let mut orig_tags = BTreeMap::new();
+        for (id, r) in &raw.roads {
+            orig_tags.insert(id.osm_way_id, &r.osm_tags);
+        }
+        // None if some piece can't be exported
+        let mut changed_ways: BTreeMap<i64, Option<Vec<BTreeMap<String, Option<String>>>>> =
+            BTreeMap::new();
+        for id in fixes.override_roads.keys() {
+            changed_ways.insert(id.osm_way_id, Some(Vec::new()));
+        }
+        let mut new_ways = BTreeSet::new();
+        for (id, r) in &fixed {
+            if let Some(Some(diffs)) = changed_ways.get_mut(&id.osm_way_id) {
+                if id.osm_way_id < 0 || r.synthetic() || !orig_tags.contains_key(&id.osm_way_id) {
+                    new_ways.insert(id.osm_way_id);
+                    continue;
+                }
+                let tags = if r.osm_tags.contains_key(map_model::osm::SYNTHETIC_LANES) {
+                    let spec = r.get_spec(raw.driving_side);
+                    let tags =
+                        get_osm_lane_tags(&r.osm_tags, &spec.fwd, &spec.back, raw.driving_side);
+                    match tags {
+                        Ok(tags) => tags,
+                        Err(err) => {
+                            timer.warn(format!("Can't export lanes of {}: {}", id, err));
+                            changed_ways.insert(id.osm_way_id, None);
+                            continue;
+                        }
+                    }
+                } else {
+                    r.osm_tags.clone()
+                };
+                diffs.push(diff_tags(orig_tags[&id.osm_way_id], &tags));
+            }
+        }
+        if !new_ways.is_empty() {
+            timer.warn(format!(
+                "Skipping {} new roads; creating ways isn't supported yet",
+                prettyprint_usize(new_ways.len())
+            ));
+        }
+        for (way, diffs) in changed_ways {
+            if let Some(diffs) = diffs {
+                self.change_way(way, diffs, timer);
+            }
+        }
+
+        for (id, i) in &fixes.override_intersections {
+            if let Some(orig) = raw.intersections.get(id) {
+                if orig.intersection_type != i.intersection_type {
+                    self.change_control(id.osm_node_id, i.intersection_type);
+                }
+            }
+        }
+
+        let restrictions = |roads: &BTreeMap<OriginalRoad, RawRoad>| {
+            let mut results = BTreeSet::new();
+            for (from, r) in roads {
+                for (rt, to) in &r.turn_restrictions {
+                    if fixed.contains_key(from) && fixed.contains_key(to) {
+                        results.insert((*rt, *from, *to));
+                    }
+                }
+            }
+            results
+        };
+        let before = restrictions(&raw.roads);
+        let after = restrictions(&fixed);
+        for (rt, from, to) in after.difference(&before) {
+            let via = match shared_intersection(*from, *to) {
+                Some(i) => i,
+                None => {
+                    timer.warn(format!(
+                        "Turn restriction from {} to {} doesn't share an intersection",
+                        from, to
+                    ));
+                    continue;
+                }
+            };
+            let turn_type = match (
+                line_into(&fixed[from].center_points, from.i2 == via),
+                line_into(&fixed[to].center_points, to.i2 == via),
+            ) {
+                (Some(l1), Some(l2)) => TurnType::from_angles(l1.angle(), l2.angle().opposite()),
+                _ => TurnType::Straight,
+            };
+            self.new_restrictions.insert(
+                (from.osm_way_id, via.osm_node_id, to.osm_way_id),
+                restriction_value(*rt, turn_type, from.osm_way_id == to.osm_way_id),
+            );
+        }
+        for (_, from, to) in before.difference(&after) {
+            if let Some(via) = shared_intersection(*from, *to) {
+                self.deleted_restrictions
+                    .insert((from.osm_way_id, via.osm_node_id, to.osm_way_id));
+            }
+        }
+        // TODO Deleted roads and intersections might be mistakes in OSM, or just things we don't
+        // want to simulate.
+    }
+
+    // map must have the edits applied.
+    pub fn add_edits(&mut self, map: &Map, timer: &mut Timer) {
+        let edits = map.get_edits();
+
+        let mut changed_roads: BTreeSet<RoadID> = BTreeSet::new();
+        for l in edits.original_lts.keys().chain(edits.reversed_lanes.iter()) {
+            if let Some(lane) = map.maybe_get_l(*l) {
+                changed_roads.insert(lane.parent);
+            }
+        }
+        changed_roads.extend(edits.original_road_lanes.keys().cloned());
+
+        let mut roads_per_way: BTreeMap<i64, Vec<RoadID>> = BTreeMap::new();
+        for r in map.all_roads() {
+            roads_per_way
+                .entry(r.orig_id.osm_way_id)
+                .or_insert_with(Vec::new)
+                .push(r.id);
+        }
+        let changed_ways: BTreeSet<i64> = changed_roads
+            .into_iter()
+            .map(|r| map.get_r(r).orig_id.osm_way_id)
+            .filter(|way| *way > 0)
+            .collect();
+        for way in changed_ways {
+            let mut diffs = Vec::new();
+            for id in &roads_per_way[&way] {
+                let r = map.get_r(*id);
+                let (fwd, back) = r.get_lane_types();
+                match get_osm_lane_tags(&r.osm_tags, &fwd, &back, map.get_driving_side()) {
+                    Ok(tags) => {
+                        diffs.push(diff_tags(&r.osm_tags, &tags));
+                    }
+                    Err(err) => {
+                        timer.warn(format!("Can't export lanes of {}: {}", r.id, err));
+                    }
+                }
+            }
+            if diffs.len() == roads_per_way[&way].len() {
+                self.change_way(way, diffs, timer);
+            }
+        }
+
+        for (id, orig) in &edits.original_intersections {
+            let i = map.get_i(*id);
+            let orig_type = match orig {
+                EditIntersection::StopSign(_) => IntersectionType::StopSign,
+                EditIntersection::TrafficSignal(_) => IntersectionType::TrafficSignal,
+                EditIntersection::Roundabout(_) => IntersectionType::Roundabout,
+                EditIntersection::Closed => IntersectionType::Construction,
+            };
+            if orig_type != i.intersection_type && i.orig_id.osm_node_id > 0 {
+                self.change_control(i.orig_id.osm_node_id, i.intersection_type);
+            }
+        }
+
+        // A turn restriction between two roads only makes sense when every turn between them is
+        // banned.
+        for id in edits.original_banned_turns.keys() {
+            let i = map.get_i(*id);
+            let mut banned: BTreeMap<(RoadID, RoadID), (LaneID, LaneID)> = BTreeMap::new();
+            for t in &i.banned_turns {
+                if map.get_l(t.src).is_sidewalk() {
+                    continue;
+                }
+                banned.insert(
+                    (map.get_l(t.src).parent, map.get_l(t.dst).parent),
+                    (t.src, t.dst),
+                );
+            }
+            for ((r1, r2), (l1, l2)) in banned {
+                if i.turns.iter().any(|t| {
+                    !map.get_l(t.src).is_sidewalk()
+                        && map.get_l(t.src).parent == r1
+                        && map.get_l(t.dst).parent == r2
+                }) {
+                    timer.warn(format!(
+                        "Only some turns from {} to {} are banned; OSM can't express that",
+                        r1, r2
+                    ));
+                    continue;
+                }
+                let from = map.get_r(r1).orig_id.osm_way_id;
+                let to = map.get_r(r2).orig_id.osm_way_id;
+                if from < 0 || to < 0 || i.orig_id.osm_node_id < 0 {
+                    continue;
+                }
+                let turn_type = TurnType::from_angles(
+                    map.get_l(l1).last_line().angle(),
+                    map.get_l(l2).first_line().angle(),
+                );
+                // TODO The via node has to be at the end of both ways, or OSM needs to split them.
+                self.new_restrictions.insert(
+                    (from, i.orig_id.osm_node_id, to),
+                    restriction_value(RestrictionType::BanTurns, turn_type, from == to),
+                );
+            }
+        }
+    }
+
+    // Apply the changes to the elements in a local OSM extract and write an osmChange file.
+    pub fn write(&self, osm_path: &str, path: &str, timer: &mut Timer) {
+        let (reader, done) = FileWithProgress::new(osm_path).unwrap();
+        let doc = Element::parse(reader).expect("OSM parsing failed");
+        done(timer);
+
+        let mut modify = Vec::new();
+        let mut delete = Vec::new();
+        let mut existing_restrictions = BTreeSet::new();
+        let mut found_ways = BTreeSet::new();
+        let mut found_nodes = BTreeSet::new();
+        timer.start_iter("find changed OSM elements", doc.children.len());
+        for elem in &doc.children {
+            timer.next();
+            let id = match elem
+                .attributes
+                .get("id")
+                .and_then(|id| id.parse::<i64>().ok())
+            {
+                Some(id) => id,
+                None => {
+                    continue;
+                }
+            };
+            let changes = match elem.name.as_str() {
+                "node" => {
+                    found_nodes.insert(id);
+                    self.nodes.get(&id)
+                }
+                "way" => {
+                    found_ways.insert(id);
+                    self.ways.get(&id)
+                }
+                "relation" => {
+                    if let Some(key) = restriction_members(elem) {
+                        existing_restrictions.insert(key);
+                        if self.deleted_restrictions.contains(&key) {
+                            delete.push(to_xml(strip_metadata(elem.clone())));
+                        }
+                    }
+                    None
+                }
+                _ => None,
+            };
+            if let Some(changes) = changes {
+                let mut elem = strip_metadata(elem.clone());
+                if retag(&mut elem, changes) {
+                    modify.push(to_xml(elem));
+                }
+            }
+        }
+        for way in self.ways.keys() {
+            if !found_ways.contains(way) {
+                timer.warn(format!("Way {} isn't in {}", way, osm_path));
+            }
+        }
+        for node in self.nodes.keys() {
+            if !found_nodes.contains(node) {
+                timer.warn(format!("Node {} isn't in {}", node, osm_path));
+            }
+        }
+
+        let mut create = Vec::new();
+        for ((from, via, to), value) in &self.new_restrictions {
+            if existing_restrictions.contains(&(*from, *via, *to)) {
+                continue;
+            }
+            let mut rel = Element::new("relation");
+            rel.attributes
+                .insert("id".to_string(), (-1 - create.len() as i64).to_string());
+            for (member_type, id, role) in &[
+                ("way", from, "from"),
+                ("node", via, "via"),
+                ("way", to, "to"),
+            ] {
+                let mut member = Element::new("member");
+                member
+                    .attributes
+                    .insert("type".to_string(), member_type.to_string());
+                member.attributes.insert("ref".to_string(), id.to_string());
+                member
+                    .attributes
+                    .insert("role".to_string(), role.to_string());
+                rel.children.push(member);
+            }
+            let mut tags = BTreeMap::new();
+            tags.insert("type".to_string(), Some("restriction".to_string()));
+            tags.insert("restriction".to_string(), Some(value.clone()));
+            retag(&mut rel, &tags);
+            create.push(to_xml(rel));
+        }
+
+        timer.note(format!(
+            "{} elements to create, {} to modify, {} to delete",
+            prettyprint_usize(create.len()),
+            prettyprint_usize(modify.len()),
+            prettyprint_usize(delete.len())
+        ));
+        // Even with no changes, overwrite the file, so an old osmChange isn't left behind.
+        let mut f = File::create(path).unwrap();
+        writeln!(f, "<osmChange version=\"0.6\" generator=\"abst\">").unwrap();
+        for (action, elems) in &[("create", create), ("modify", modify), ("delete", delete)] {
+            if elems.is_empty() {
+                continue;
+            }
+            writeln!(f, "<{}>", action).unwrap();
+            for elem in elems {
+                writeln!(f, "  {}", elem).unwrap();
+            }
+            writeln!(f, "</{}>", action).unwrap();
+        }
+        writeln!(f, "</osmChange>").unwrap();
+        println!("Wrote {}", path);
+    }
+
+    // Every piece of a way has to change the same way, since the tags belong to the whole way.
+    fn change_way(
+        &mut self,
+        way: i64,
+        diffs: Vec<BTreeMap<String, Option<String>>>,
+        timer: &mut Timer,
+    ) {
+        if diffs.iter().all(|d| d.is_empty()) {
+            return;
+        }
+        if diffs.iter().any(|d| d != &diffs[0]) {
+            timer.warn(format!(
+                "Only part of way {} changed; splitting ways isn't supported yet",
+                way
+            ));
+            return;
+        }
+        self.ways
+            .entry(way)
+            .or_insert_with(BTreeMap::new)
+            .extend(diffs.into_iter().next().unwrap());
+    }
+
+    fn change_control(&mut self, node: i64, intersection_type: IntersectionType) {
+        let value = match intersection_type {
+            IntersectionType::TrafficSignal => "traffic_signals",
+            IntersectionType::StopSign => "stop",
+            IntersectionType::Roundabout => "mini_roundabout",
+            IntersectionType::Border | IntersectionType::Construction => {
+                return;
+            }
+        };
+        self.nodes
+            .entry(node)
+            .or_insert_with(BTreeMap::new)
+            .insert(map_model::osm::HIGHWAY.to_string(), Some(value.to_string()));
+    }
+}
+
+// Tags that differ, skipping the ones we add during import
+fn diff_tags(
+    orig: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+) -> BTreeMap<String, Option<String>> {
+    let mut diff = BTreeMap::new();
+    for (k, v) in new {
+        if !k.starts_with("abst:") && orig.get(k) != Some(v) {
+            diff.insert(k.clone(), Some(v.clone()));
+        }
+    }
+    for k in orig.keys() {
+        if !k.starts_with("abst:") && !new.contains_key(k) {
+            diff.insert(k.clone(), None);
+        }
+    }
+    diff
+}
+
+fn shared_intersection(r1: OriginalRoad, r2: OriginalRoad) -> Option<OriginalIntersection> {
+    if r1.i1 == r2.i1 || r1.i1 == r2.i2 {
+        Some(r1.i1)
+    } else if r1.i2 == r2.i1 || r1.i2 == r2.i2 {
+        Some(r1.i2)
+    } else {
+        None
+    }
+}
+
+// The line heading along a road into the intersection at its end, or at its start if !at_end
+fn line_into(pts: &Vec<Pt2D>, at_end: bool) -> Option<Line> {
+    if pts.len() < 2 {
+        return None;
+    }
+    let n = pts.len();
+    if at_end {
+        Line::maybe_new(pts[n - 2], pts[n - 1])
+    } else {
+        Line::maybe_new(pts[1], pts[0])
+    }
+}
+
+fn restriction_value(rt: RestrictionType, turn_type: TurnType, same_way: bool) -> String {
+    match (rt, turn_type) {
+        (RestrictionType::BanTurns, _) if same_way => "no_u_turn",
+        (RestrictionType::BanTurns, TurnType::Left) => "no_left_turn",
+        (RestrictionType::BanTurns, TurnType::Right) => "no_right_turn",
+        (RestrictionType::BanTurns, _) => "no_straight_on",
+        (RestrictionType::OnlyAllowTurns, TurnType::Left) => "only_left_turn",
+        (RestrictionType::OnlyAllowTurns, TurnType::Right) => "only_right_turn",
+        (RestrictionType::OnlyAllowTurns, _) => "only_straight_on",
+    }
+    .to_string()
+}
+
+// (from way, via node, to way) of a turn restriction
+fn restriction_members(rel: &Element) -> Option<(i64, i64, i64)> {
+    if !rel.children.iter().any(|c| {
+        c.name == "tag"
+            && c.attributes.get("k") == Some(&"type".to_string())
+            && c.attributes.get("v") == Some(&"restriction".to_string())
+    }) {
+        return None;
+    }
+    let member = |role: &str, member_type: &str| {
+        rel.children
+            .iter()
+            .find(|c| {
+                c.name == "member"
+                    && c.attributes.get("role") == Some(&role.to_string())
+                    && c.attributes.get("type") == Some(&member_type.to_string())
+            })
+            .and_then(|c| c.attributes.get("ref"))
+            .and_then(|id| id.parse::<i64>().ok())
+    };
+    Some((
+        member("from", "way")?,
+        member("via", "node")?,
+        member("to", "way")?,
+    ))
+}
+
+// Returns true if any tag changed.
+fn retag(elem: &mut Element, changes: &BTreeMap<String, Option<String>>) -> bool {
+    let mut tags = BTreeMap::new();
+    let mut other_children = Vec::new();
+    for child in elem.children.drain(..) {
+        if child.name == "tag" {
+            tags.insert(child.attributes["k"].clone(), child.attributes["v"].clone());
+        } else {
+            other_children.push(child);
+        }
+    }
+
+    let mut changed = false;
+    for (k, v) in changes {
+        if let Some(v) = v {
+            if tags.get(k) != Some(v) {
+                tags.insert(k.clone(), v.clone());
+                changed = true;
+            }
+        } else if tags.remove(k).is_some() {
+            changed = true;
+        }
+    }
+
+    elem.children = other_children;
+    for (k, v) in tags {
+        let mut tag = Element::new("tag");
+        tag.attributes.insert("k".to_string(), k);
+        tag.attributes.insert("v".to_string(), v);
+        elem.children.push(tag);
+    }
+    changed
+}
+
+// The version stays, so uploading catches conflicts with changes made since the extract.
+fn strip_metadata(mut elem: Element) -> Element {
+    for key in &["timestamp", "changeset", "user", "uid", "visible"] {
+        elem.attributes.remove(*key);
+    }
+    elem
+}
+
+fn to_xml(elem: Element) -> String {
+    let mut bytes: Vec<u8> = Vec::new();
+    elem.write(&mut bytes).unwrap();
+    let out = String::from_utf8(bytes).unwrap();
+    out.trim_start_matches("<?xml version=\"1.0\" encoding=\"UTF-8\"?>")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two ways meeting at node 2, with a turn restriction between them
+    const FIXTURE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" version="1" lat="47.0" lon="-122.0" user="someone" uid="7"/>
+  <node id="2" version="3" lat="47.001" lon="-122.0"/>
+  <node id="3" version="1" lat="47.001" lon="-122.001"/>
+  <way id="10" version="2" timestamp="2020-01-01T00:00:00Z">
+    <nd ref="1"/>
+    <nd ref="2"/>
+    <tag k="highway" v="residential"/>
+    <tag k="lanes" v="2"/>
+    <tag k="parking:lane:both" v="parallel"/>
+  </way>
+  <way id="11" version="1">
+    <nd ref="2"/>
+    <nd ref="3"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <relation id="20" version="1">
+    <member type="way" ref="10" role="from"/>
+    <member type="node" ref="2" role="via"/>
+    <member type="way" ref="11" role="to"/>
+    <tag k="restriction" v="no_left_turn"/>
+    <tag k="type" v="restriction"/>
+  </relation>
+</osm>
+"#;
+
+    fn tags(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn changes(pairs: &[(&str, Option<&str>)]) -> BTreeMap<String, Option<String>> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.map(|v| v.to_string())))
+            .collect()
+    }
+
+    fn write_fixture(name: &str) -> String {
+        let path = std::env::temp_dir()
+            .join(name)
+            .to_str()
+            .unwrap()
+            .to_string();
+        std::fs::write(&path, FIXTURE).unwrap();
+        path
+    }
+
+    fn parse_fixture() -> Element {
+        Element::parse(FIXTURE.as_bytes()).unwrap()
+    }
+
+    fn find<'a>(doc: &'a Element, name: &str, id: &str) -> &'a Element {
+        doc.children
+            .iter()
+            .find(|e| e.name == name && e.attributes.get("id") == Some(&id.to_string()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_diff_tags() {
+        let orig = tags(&[
+            ("highway", "residential"),
+            ("lanes", "2"),
+            ("parking:lane:both", "parallel"),
+            ("abst:endpt_fwd", "true"),
+        ]);
+        let new = tags(&[
+            ("highway", "residential"),
+            ("lanes", "3"),
+            ("cycleway", "lane"),
+            ("abst:synthetic_lanes", "true"),
+        ]);
+        assert_eq!(
+            diff_tags(&orig, &new),
+            changes(&[
+                ("cycleway", Some("lane")),
+                ("lanes", Some("3")),
+                ("parking:lane:both", None),
+            ])
+        );
+        assert!(diff_tags(&orig, &orig).is_empty());
+    }
+
+    #[test]
+    fn test_retag() {
+        let doc = parse_fixture();
+        let mut way = find(&doc, "way", "10").clone();
+        let diff = changes(&[
+            ("lanes", Some("3")),
+            ("parking:lane:both", None),
+            ("oneway", None),
+        ]);
+        assert!(retag(&mut way, &diff));
+
+        let mut result = BTreeMap::new();
+        for child in &way.children {
+            if child.name == "tag" {
+                result.insert(child.attributes["k"].clone(), child.attributes["v"].clone());
+            }
+        }
+        assert_eq!(result, tags(&[("highway", "residential"), ("lanes", "3")]));
+        // The node references are untouched
+        assert_eq!(way.children.iter().filter(|c| c.name == "nd").count(), 2);
+
+        // Applying the same changes again, or removing a tag that isn't there, changes nothing.
+        assert!(!retag(&mut way, &diff));
+        assert!(!retag(&mut way, &changes(&[("oneway", None)])));
+    }
+
+    #[test]
+    fn test_restriction_value() {
+        let ban = RestrictionType::BanTurns;
+        let only = RestrictionType::OnlyAllowTurns;
+        assert_eq!(restriction_value(ban, TurnType::Left, false), "no_left_turn");
+        assert_eq!(restriction_value(ban, TurnType::Right, false), "no_right_turn");
+        assert_eq!(restriction_value(ban, TurnType::Straight, false), "no_straight_on");
+        assert_eq!(restriction_value(ban, TurnType::Left, true), "no_u_turn");
+        assert_eq!(restriction_value(only, TurnType::Left, false), "only_left_turn");
+        assert_eq!(restriction_value(only, TurnType::Right, false), "only_right_turn");
+        assert_eq!(restriction_value(only, TurnType::Straight, true), "only_straight_on");
+
+        let doc = parse_fixture();
+        assert_eq!(
+            restriction_members(find(&doc, "relation", "20")),
+            Some((10, 2, 11))
+        );
+        assert_eq!(restriction_members(find(&doc, "way", "10")), None);
+    }
+
+    #[test]
+    fn test_change_way_refuses_partial_ways() {
+        let mut timer = Timer::throwaway();
+        let mut change = OsmChange::new();
+        change.change_way(
+            10,
+            vec![changes(&[("lanes", Some("3"))]), BTreeMap::new()],
+            &mut timer,
+        );
+        assert!(change.ways.is_empty());
+
+        // Every piece has to change the same way
+        change.change_way(
+            10,
+            vec![
+                changes(&[("lanes", Some("3"))]),
+                changes(&[("lanes", Some("3"))]),
+            ],
+            &mut timer,
+        );
+        assert_eq!(change.ways[&10], changes(&[("lanes", Some("3"))]));
+
+        // Nothing changed at all
+        change.change_way(11, vec![BTreeMap::new(), BTreeMap::new()], &mut timer);
+        assert!(!change.ways.contains_key(&11));
+    }
+
+    #[test]
+    fn test_write() {
+        let osm_path = write_fixture("test_osm_change_input.osm");
+        let out_path = std::env::temp_dir()
+            .join("test_osm_change.osc")
+            .to_str()
+            .unwrap()
+            .to_string();
+        let mut timer = Timer::throwaway();
+
+        let mut change = OsmChange::new();
+        change
+            .ways
+            .insert(10, changes(&[("lanes", Some("3"))]));
+        change.deleted_restrictions.insert((10, 2, 11));
+        change.write(&osm_path, &out_path, &mut timer);
+        let doc = Element::parse(File::open(&out_path).unwrap()).unwrap();
+        assert_eq!(doc.name, "osmChange");
+        let modify = doc.get_child("modify").unwrap();
+        assert_eq!(modify.children.len(), 1);
+        let way = find(modify, "way", "10");
+        assert_eq!(way.attributes["version"], "2");
+        assert!(!way.attributes.contains_key("timestamp"));
+        assert!(find(doc.get_child("delete").unwrap(), "relation", "20")
+            .attributes
+            .contains_key("version"));
+        assert!(doc.get_child("create").is_none());
+
+        // With nothing to change, the old file is replaced by an empty osmChange.
+        OsmChange::new().write(&osm_path, &out_path, &mut timer);
+        let doc = Element::parse(File::open(&out_path).unwrap()).unwrap();
+        assert_eq!(doc.name, "osmChange");
+        assert!(doc.children.is_empty());
+    }
+}
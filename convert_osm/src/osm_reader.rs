@@ -1,22 +1,25 @@
 use abstutil::{FileWithProgress, Timer};
 use geom::{GPSBounds, HashablePt2D, LonLat, PolyLine, Polygon, Pt2D, Ring};
-use map_model::raw::{OriginalBuilding, RawArea, RawBuilding, RawMap, RawRoad, RestrictionType};
-use map_model::{osm, AreaType};
+use map_model::raw::{
+    DrivingSide, OriginalBuilding, RawArea, RawBuilding, RawMap, RawRoad, RestrictionType,
+};
+use map_model::{osm, AreaType, IntersectionType};
 use osm_xml;
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
 pub fn extract_osm(
     osm_path: &str,
     maybe_clip_path: &Option<String>,
+    driving_side: DrivingSide,
     timer: &mut Timer,
 ) -> (
     RawMap,
     // Un-split roads
     Vec<(i64, RawRoad)>,
-    // Traffic signals
-    HashSet<HashablePt2D>,
+    // Traffic signals and mini-roundabouts; every other intersection gets a stop sign
+    HashMap<HashablePt2D, IntersectionType>,
     // OSM Node IDs
     HashMap<HashablePt2D, i64>,
     // Turn restrictions: (restriction type, from way ID, via node ID, to way ID)
@@ -44,10 +47,11 @@ pub fn extract_osm(
         m.boundary_polygon = m.gps_bounds.to_bounds().get_rectangle();
         m
     };
+    map.driving_side = driving_side;
 
     let mut id_to_way: HashMap<i64, Vec<Pt2D>> = HashMap::new();
     let mut roads: Vec<(i64, RawRoad)> = Vec::new();
-    let mut traffic_signals: HashSet<HashablePt2D> = HashSet::new();
+    let mut intersection_types: HashMap<HashablePt2D, IntersectionType> = HashMap::new();
     let mut osm_node_ids = HashMap::new();
     let mut amenities = Vec::new();
 
@@ -59,7 +63,10 @@ pub fn extract_osm(
 
         let tags = tags_to_map(&node.tags);
         if tags.get(osm::HIGHWAY) == Some(&"traffic_signals".to_string()) {
-            traffic_signals.insert(pt.to_hashable());
+            intersection_types.insert(pt.to_hashable(), IntersectionType::TrafficSignal);
+        }
+        if tags.get(osm::HIGHWAY) == Some(&"mini_roundabout".to_string()) {
+            intersection_types.insert(pt.to_hashable(), IntersectionType::Roundabout);
         }
         if let Some(amenity) = tags.get("amenity") {
             if let Some(name) = tags.get("name") {
@@ -117,7 +124,10 @@ pub fn extract_osm(
                 {
                     tags.insert(osm::SIDEWALK.to_string(), "none".to_string());
                 } else if tags.get("oneway") == Some(&"yes".to_string()) {
-                    tags.insert(osm::SIDEWALK.to_string(), "right".to_string());
+                    tags.insert(
+                        osm::SIDEWALK.to_string(),
+                        driving_side.osm_side(true).to_string(),
+                    );
                     if tags.get(osm::HIGHWAY) == Some(&"residential".to_string()) {
                         tags.insert(osm::SIDEWALK.to_string(), "both".to_string());
                     }
@@ -264,7 +274,7 @@ pub fn extract_osm(
     (
         map,
         roads,
-        traffic_signals,
+        intersection_types,
         osm_node_ids,
         turn_restrictions,
         amenities,
@@ -4,13 +4,13 @@ use map_model::raw::{
     OriginalIntersection, OriginalRoad, RawIntersection, RawMap, RawRoad, RestrictionType,
 };
 use map_model::{osm, IntersectionType};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 pub fn split_up_roads(
-    (mut map, roads, traffic_signals, osm_node_ids, turn_restrictions, amenities): (
+    (mut map, roads, intersection_types, osm_node_ids, turn_restrictions, amenities): (
         RawMap,
         Vec<(i64, RawRoad)>,
-        HashSet<HashablePt2D>,
+        HashMap<HashablePt2D, IntersectionType>,
         HashMap<HashablePt2D, i64>,
         Vec<(RestrictionType, i64, i64, i64)>,
         Vec<(Pt2D, String, String)>,
@@ -43,11 +43,10 @@ pub fn split_up_roads(
             *id,
             RawIntersection {
                 point: pt.to_pt2d(),
-                intersection_type: if traffic_signals.contains(pt) {
-                    IntersectionType::TrafficSignal
-                } else {
-                    IntersectionType::StopSign
-                },
+                intersection_type: intersection_types
+                    .get(pt)
+                    .cloned()
+                    .unwrap_or(IntersectionType::StopSign),
                 // Filled out later
                 elevation: Distance::ZERO,
             },
@@ -22,10 +22,11 @@ impl Elevation {
         println!("Reading elevation data from {}", path);
         let mut f = File::open(path).unwrap();
 
+        let (lon_offset, lat_offset) = southwest_corner(&abstutil::basename(path))
+            .unwrap_or_else(|| panic!("{} isn't named like N47W122.hgt", path));
         let mut e = Elevation {
-            // TODO dont hardcode
-            lon_offset: -122.0,
-            lat_offset: 47.0,
+            lon_offset,
+            lat_offset,
             data: Vec::with_capacity(GRID_DIM.pow(2)),
         };
         // TODO off by one?
@@ -45,3 +46,27 @@ impl Elevation {
         Distance::meters(f64::from(self.data[i]))
     }
 }
+
+// SRTM tiles are named after the (longitude, latitude) of their southwest corner, like N47W122.
+fn southwest_corner(name: &str) -> Option<(f64, f64)> {
+    if name.len() != 7 || !name.is_ascii() {
+        return None;
+    }
+    let lat = name[1..3].parse::<f64>().ok()?;
+    let lon = name[4..7].parse::<f64>().ok()?;
+    let lat = match &name[0..1] {
+        "N" => lat,
+        "S" => -lat,
+        _ => {
+            return None;
+        }
+    };
+    let lon = match &name[3..4] {
+        "E" => lon,
+        "W" => -lon,
+        _ => {
+            return None;
+        }
+    };
+    Some((lon, lat))
+}
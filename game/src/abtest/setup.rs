@@ -121,7 +121,8 @@ fn launch_test(test: &ABTest, app: &mut App, ctx: &mut EventCtx) -> ABTestMode {
                 apply_map_edits(
                     ctx,
                     app,
-                    MapEdits::load(&test.map_name, &test.edits1_name, &mut timer),
+                    MapEdits::load(&app.primary.map, &test.edits1_name, &mut timer)
+                        .unwrap_or_else(|err| panic!("{}", err)),
                 );
                 app.primary
                     .map
@@ -167,6 +168,12 @@ fn launch_test(test: &ABTest, app: &mut App, ctx: &mut EventCtx) -> ABTestMode {
                                     .opts
                                     .clear_laggy_head_early,
                                 enable_pandemic_model: None,
+                                pandemic_config: current_flags
+                                    .sim_flags
+                                    .opts
+                                    .pandemic_config
+                                    .clone(),
+                                reroute_every: current_flags.sim_flags.opts.reroute_every,
                             },
                         },
                         ..current_flags.clone()
@@ -180,7 +187,8 @@ fn launch_test(test: &ABTest, app: &mut App, ctx: &mut EventCtx) -> ABTestMode {
                 apply_map_edits(
                     ctx,
                     app,
-                    MapEdits::load(&test.map_name, &test.edits2_name, &mut timer),
+                    MapEdits::load(&app.primary.map, &test.edits2_name, &mut timer)
+                        .unwrap_or_else(|err| panic!("{}", err)),
                 );
                 std::mem::swap(&mut app.primary, &mut secondary);
                 secondary
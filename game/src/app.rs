@@ -9,7 +9,7 @@ use crate::sandbox::TutorialState;
 use abstutil::{MeasureMemory, Timer};
 use ezgui::{EventCtx, GfxCtx, Prerender};
 use geom::{Bounds, Circle, Distance, Pt2D};
-use map_model::{Map, Traversable};
+use map_model::{LaneType, Map, Traversable};
 use rand::seq::SliceRandom;
 use sim::{Analytics, GetDrawAgents, Sim, SimFlags};
 
@@ -333,6 +333,13 @@ impl App {
             match id {
                 ID::Area(id) => areas.push(draw_map.get_a(id)),
                 ID::Lane(id) => {
+                    // Lanes removed by map edits linger in the quadtree
+                    let lane = map.get_l(id);
+                    if lane.lane_type == LaneType::Construction
+                        && !map.get_parent(id).all_lanes().contains(&id)
+                    {
+                        continue;
+                    }
                     lanes.push(draw_map.get_l(id));
                     agents_on.push(Traversable::Lane(id));
                     for bs in &map.get_l(id).bus_stops {
@@ -72,6 +72,7 @@ pub struct ColorScheme {
     pub normal_intersection: Color,
     pub stop_sign: Color,
     pub stop_sign_pole: Color,
+    pub roundabout_island: Color,
     pub signal_protected_turn: Color,
     pub signal_permitted_turn: Color,
     pub signal_permitted_turn_outline: Color,
@@ -170,6 +171,7 @@ impl ColorScheme {
             normal_intersection: Color::grey(0.2),
             stop_sign: Color::RED,
             stop_sign_pole: Color::grey(0.5),
+            roundabout_island: Color::grey(0.5),
             signal_protected_turn: hex("#72CE36"),
             signal_permitted_turn: Color::rgba(76, 167, 233, 0.3),
             signal_permitted_turn_outline: hex("#4CA7E9"),
@@ -8,7 +8,11 @@ use ezgui::{
     hotkey, Btn, Choice, Color, Composite, EventCtx, GfxCtx, HorizontalAlignment, Key, Outcome,
     RewriteColor, TextExt, VerticalAlignment, Widget,
 };
-use map_model::{EditCmd, LaneID, LaneType, Map, RoadID};
+use geom::{Distance, Speed};
+use map_model::{
+    EditCmd, EditLane, LaneID, LaneType, Map, PathConstraints, RoadID, NORMAL_LANE_THICKNESS,
+    SIDEWALK_THICKNESS,
+};
 use std::collections::BTreeSet;
 
 pub struct LaneEditor {
@@ -79,6 +83,33 @@ impl LaneEditor {
             .draw_text(ctx)
             .centered_horiz(),
             Widget::row(row).centered(),
+            format!(
+                "Speed limit: {}",
+                app.primary.map.get_parent(l).get_speed_limit()
+            )
+            .draw_text(ctx),
+            Widget::row(vec![
+                Btn::text_fg("change speed limit")
+                    .build_def(ctx, hotkey(Key::S))
+                    .margin(5),
+                Btn::text_fg("restrict through-traffic")
+                    .build_def(ctx, hotkey(Key::A))
+                    .margin(5),
+            ])
+            .centered(),
+            format!("Lane width: {}", app.primary.map.get_l(l).width).draw_text(ctx),
+            Widget::row(vec![
+                Btn::text_fg("add a lane")
+                    .build_def(ctx, hotkey(Key::N))
+                    .margin(5),
+                Btn::text_fg("remove this lane")
+                    .build_def(ctx, hotkey(Key::X))
+                    .margin(5),
+                Btn::text_fg("change lane width")
+                    .build_def(ctx, hotkey(Key::W))
+                    .margin(5),
+            ])
+            .centered(),
             Btn::text_fg("Finish").build_def(ctx, hotkey(Key::Escape)),
             // TODO Not ready for general use
             if app.opts.dev {
@@ -143,6 +174,29 @@ impl State for LaneEditor {
                     "Finish" => {
                         return Transition::Pop;
                     }
+                    "change speed limit" => {
+                        return Transition::Push(edit_speed_limit(self.l, app));
+                    }
+                    "restrict through-traffic" => {
+                        return Transition::Push(edit_access_restrictions(self.l, app));
+                    }
+                    "add a lane" => {
+                        return Transition::Push(add_lane(self.l, app));
+                    }
+                    "remove this lane" => match try_remove_lane(self.l, map) {
+                        Ok(cmd) => {
+                            let mut edits = app.primary.map.get_edits().clone();
+                            edits.commands.push(cmd);
+                            apply_map_edits(ctx, app, edits);
+                            return Transition::Pop;
+                        }
+                        Err(err) => {
+                            return Transition::Push(msg("Error", vec![err]));
+                        }
+                    },
+                    "change lane width" => {
+                        return Transition::Push(edit_lane_width(self.l, app));
+                    }
                     "Edit entire road" => {
                         return Transition::Replace(make_bulk_edit_lanes(map.get_l(self.l).parent));
                     }
@@ -267,6 +321,214 @@ fn try_reverse(l: LaneID, map: &Map) -> Result<EditCmd, String> {
     }
 }
 
+fn edit_speed_limit(l: LaneID, app: &App) -> Box<dyn State> {
+    let r = app.primary.map.get_parent(l);
+    let id = r.id;
+    let name = r.get_name();
+    let old = r.get_speed_limit();
+
+    WizardState::new(Box::new(move |wiz, ctx, app| {
+        let mph = wiz.wrap(ctx).input_usize_prefilled(
+            &format!("What should the speed limit of {} be (mph)?", name),
+            format!("{}", (old.inner_meters_per_second() * 2.23694).round()),
+        )?;
+        if mph == 0 {
+            return Some(Transition::Replace(msg(
+                "Error",
+                vec!["The speed limit can't be zero; close the lanes instead"],
+            )));
+        }
+
+        let mut edits = app.primary.map.get_edits().clone();
+        edits.commands.push(EditCmd::ChangeSpeedLimit {
+            id,
+            new: Speed::miles_per_hour(mph as f64),
+            old,
+        });
+        apply_map_edits(ctx, app, edits);
+        Some(Transition::PopThenReplace(Box::new(LaneEditor::new(
+            l, ctx, app,
+        ))))
+    }))
+}
+
+fn edit_access_restrictions(l: LaneID, app: &App) -> Box<dyn State> {
+    let r = app.primary.map.get_parent(l);
+    let id = r.id;
+    let old = r.no_through_traffic.clone();
+
+    WizardState::new(Box::new(move |wiz, ctx, app| {
+        let (_, constraints) = wiz
+            .wrap(ctx)
+            .choose("Change access for which vehicles?", || {
+                vec![
+                    ("cars", PathConstraints::Car),
+                    ("bikes", PathConstraints::Bike),
+                    ("buses", PathConstraints::Bus),
+                ]
+                .into_iter()
+                .map(|(name, constraints)| {
+                    let label = if old.contains(&constraints) {
+                        format!("allow through-traffic for {}", name)
+                    } else {
+                        format!("only allow {} to start or end trips here", name)
+                    };
+                    Choice::new(label, constraints)
+                })
+                .collect()
+            })?;
+
+        let mut new = old.clone();
+        if !new.remove(&constraints) {
+            new.insert(constraints);
+        }
+        let mut edits = app.primary.map.get_edits().clone();
+        edits.commands.push(EditCmd::ChangeAccessRestrictions {
+            id,
+            new,
+            old: old.clone(),
+        });
+        apply_map_edits(ctx, app, edits);
+        Some(Transition::PopThenReplace(Box::new(LaneEditor::new(
+            l, ctx, app,
+        ))))
+    }))
+}
+
+fn add_lane(l: LaneID, app: &App) -> Box<dyn State> {
+    let r = app.primary.map.get_l(l).parent;
+    let old = app.primary.map.get_r_edit(r);
+
+    WizardState::new(Box::new(move |wiz, ctx, app| {
+        let mut wizard = wiz.wrap(ctx);
+        let (_, lt) = wizard.choose("Add what type of lane?", || {
+            vec![
+                Choice::new("driving", LaneType::Driving),
+                Choice::new("parking", LaneType::Parking),
+                Choice::new("biking", LaneType::Biking),
+                Choice::new("bus", LaneType::Bus),
+                Choice::new("sidewalk", LaneType::Sidewalk),
+            ]
+        })?;
+        let outside = "next to this lane, further from the center of the road";
+        let inside = "next to this lane, closer to the center of the road";
+        let further = wizard
+            .choose_string("Where should the new lane go?", || vec![outside, inside])?
+            == outside;
+
+        let idx = old.iter().position(|spec| spec.id == l).unwrap();
+        let fwd = old[idx].fwd;
+        let mut new = old.clone();
+        new.insert(
+            if further { idx + 1 } else { idx },
+            EditLane {
+                // LaneIDs are never reused, so this one is brand new
+                id: LaneID(app.primary.map.all_lanes().len()),
+                lane_type: lt,
+                fwd,
+                width: if lt == LaneType::Sidewalk {
+                    SIDEWALK_THICKNESS
+                } else {
+                    NORMAL_LANE_THICKNESS
+                },
+            },
+        );
+        if lt == LaneType::Parking
+            && new
+                .iter()
+                .filter(|spec| spec.fwd == fwd && spec.lane_type == LaneType::Parking)
+                .count()
+                > 1
+        {
+            return Some(Transition::Replace(msg(
+                "Error",
+                vec!["You can only have one parking lane on the same side of the road"],
+            )));
+        }
+
+        let mut edits = app.primary.map.get_edits().clone();
+        edits.commands.push(EditCmd::ChangeRoadLanes {
+            r,
+            new,
+            old: old.clone(),
+        });
+        apply_map_edits(ctx, app, edits);
+        Some(Transition::PopThenReplace(Box::new(LaneEditor::new(
+            l, ctx, app,
+        ))))
+    }))
+}
+
+fn try_remove_lane(l: LaneID, map: &Map) -> Result<EditCmd, String> {
+    let r = map.get_parent(l);
+    if r.all_lanes().len() == 1 {
+        return Err(format!(
+            "A road needs at least one lane; close this one for construction instead"
+        ));
+    }
+    if map.all_buildings().iter().any(|b| {
+        b.parking
+            .as_ref()
+            .map(|p| p.driving_pos.lane() == l)
+            .unwrap_or(false)
+    }) {
+        return Err(format!(
+            "Some buildings have driveways connecting to this lane"
+        ));
+    }
+    let lane = map.get_l(l);
+    if !lane.building_paths.is_empty() {
+        return Err(format!(
+            "Some buildings have paths connecting to this sidewalk"
+        ));
+    }
+    if !lane.bus_stops.is_empty() {
+        return Err(format!("You can't remove a sidewalk with a bus stop"));
+    }
+    // Removing the lane has to leave the road in a state that closing it would.
+    if let Some(err) = can_change_lane_type(l, LaneType::Construction, map) {
+        return Err(err);
+    }
+
+    let old = map.get_r_edit(r.id);
+    Ok(EditCmd::ChangeRoadLanes {
+        r: r.id,
+        new: old.iter().filter(|spec| spec.id != l).cloned().collect(),
+        old,
+    })
+}
+
+fn edit_lane_width(l: LaneID, app: &App) -> Box<dyn State> {
+    let r = app.primary.map.get_l(l).parent;
+    let old = app.primary.map.get_r_edit(r);
+    let current = app.primary.map.get_l(l).width;
+
+    WizardState::new(Box::new(move |wiz, ctx, app| {
+        let meters = wiz.wrap(ctx).input_something(
+            "How wide should this lane be (meters, between 1 and 10)?",
+            Some(format!("{}", current.inner_meters())),
+            Box::new(|line| line.parse::<f64>().ok().filter(|m| *m >= 1.0 && *m <= 10.0)),
+        )?;
+
+        let mut new = old.clone();
+        for spec in new.iter_mut() {
+            if spec.id == l {
+                spec.width = Distance::meters(meters);
+            }
+        }
+        let mut edits = app.primary.map.get_edits().clone();
+        edits.commands.push(EditCmd::ChangeRoadLanes {
+            r,
+            new,
+            old: old.clone(),
+        });
+        apply_map_edits(ctx, app, edits);
+        Some(Transition::PopThenReplace(Box::new(LaneEditor::new(
+            l, ctx, app,
+        ))))
+    }))
+}
+
 fn make_bulk_edit_lanes(road: RoadID) -> Box<dyn State> {
     WizardState::new(Box::new(move |wiz, ctx, app| {
         let mut wizard = wiz.wrap(ctx);
@@ -305,6 +567,10 @@ fn make_bulk_edit_lanes(road: RoadID) -> Box<dyn State> {
                 if orig_lt != from || app.primary.map.get_parent(l).get_name() != road_name {
                     continue;
                 }
+                // Skip lanes removed by edits
+                if !app.primary.map.get_parent(l).all_lanes().contains(&l) {
+                    continue;
+                }
                 if can_change_lane_type(l, to, &app.primary.map).is_none() {
                     let mut edits = app.primary.map.get_edits().clone();
                     edits.commands.push(EditCmd::ChangeLaneType {
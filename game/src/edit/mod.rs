@@ -1,10 +1,14 @@
 mod lanes;
+mod roundabouts;
 mod stop_signs;
 mod traffic_signals;
+mod turn_restrictions;
 
 pub use self::lanes::LaneEditor;
+pub use self::roundabouts::RoundaboutEditor;
 pub use self::stop_signs::StopSignEditor;
 pub use self::traffic_signals::TrafficSignalEditor;
+use self::turn_restrictions::edit_turn_restrictions;
 use crate::app::{App, ShowEverything};
 use crate::common::{tool_panel, Colorer, CommonState, Warping};
 use crate::debug::DebugMode;
@@ -144,11 +148,7 @@ impl State for EditMode {
                 }
                 "undo" => {
                     let mut edits = app.primary.map.get_edits().clone();
-                    let id = match edits.commands.pop().unwrap() {
-                        EditCmd::ChangeLaneType { id, .. } => ID::Lane(id),
-                        EditCmd::ReverseLane { l, .. } => ID::Lane(l),
-                        EditCmd::ChangeIntersection { i, .. } => ID::Intersection(i),
-                    };
+                    let id = cmd_to_id(&edits.commands.pop().unwrap());
                     apply_map_edits(ctx, app, edits);
                     return Transition::Push(Warping::new(
                         ctx,
@@ -198,6 +198,16 @@ impl State for EditMode {
                         self.suspended_sim.clone(),
                     )));
                 }
+                if app.primary.map.maybe_get_roundabout(id).is_some()
+                    && app.per_obj.left_click(ctx, "edit roundabout")
+                {
+                    return Transition::Push(Box::new(RoundaboutEditor::new(
+                        id,
+                        ctx,
+                        app,
+                        self.suspended_sim.clone(),
+                    )));
+                }
                 if app.primary.map.get_i(id).is_closed()
                     && app.per_obj.left_click(ctx, "re-open closed intersection")
                 {
@@ -314,14 +324,23 @@ fn make_load_edits(btn: ScreenRectangle, mode: GameplayMode) -> Box<dyn State> {
             ),
             None,
             || {
-                let mut list = Choice::from(
-                    abstutil::load_all_objects(abstutil::path_all_edits(&map_name))
-                        .into_iter()
-                        .filter(|(_, edits)| {
-                            mode.allows(edits) && edits.edits_name != current_edits_name
-                        })
-                        .collect(),
-                );
+                let mut timer = Timer::new("load all edits");
+                let mut list = Vec::new();
+                for name in abstutil::list_all_objects(abstutil::path_all_edits(&map_name)) {
+                    if name == current_edits_name {
+                        continue;
+                    }
+                    match MapEdits::load(&app.primary.map, &name, &mut timer) {
+                        Ok(edits) => {
+                            if mode.allows(&edits) {
+                                list.push(Choice::new(name, edits));
+                            }
+                        }
+                        Err(err) => {
+                            timer.warn(err);
+                        }
+                    }
+                }
                 list.push(Choice::new(
                     "start over with blank edits",
                     MapEdits::new(map_name),
@@ -390,6 +409,23 @@ pub fn apply_map_edits(ctx: &mut EventCtx, app: &mut App, edits: MapEdits) {
     let (roads_changed, turns_deleted, turns_added, mut modified_intersections) =
         app.primary.map.apply_edits(edits, &mut timer);
 
+    // Edits might've added lanes. Their IDs are never reused, so just append.
+    while app.primary.draw_map.lanes.len() < app.primary.map.all_lanes().len() {
+        let lane = app
+            .primary
+            .map
+            .get_l(LaneID(app.primary.draw_map.lanes.len()));
+        let draw = DrawLane::new(
+            lane,
+            &app.primary.map,
+            app.primary.current_flags.draw_lane_markings,
+            &app.cs,
+            &mut timer,
+        )
+        .finish(ctx.prerender, &app.cs, lane);
+        app.primary.draw_map.add_lane(draw, &app.primary.map);
+    }
+
     for r in roads_changed {
         let road = app.primary.map.get_r(r);
         app.primary.draw_map.roads[r.0] =
@@ -447,11 +483,31 @@ pub fn close_intersection(
     i: IntersectionID,
     pop_once: bool,
 ) -> Transition {
+    let mut ids = BTreeSet::new();
+    ids.insert(i);
+    close_intersections(ctx, app, ids, pop_once)
+}
+
+// Closes all of the intersections in one edit, like every part of a roundabout.
+pub fn close_intersections(
+    ctx: &mut EventCtx,
+    app: &mut App,
+    ids: BTreeSet<IntersectionID>,
+    pop_once: bool,
+) -> Transition {
+    let mut cmds: Vec<EditCmd> = ids
+        .iter()
+        .map(|i| EditCmd::ChangeIntersection {
+            i: *i,
+            old: app.primary.map.get_i_edit(*i),
+            new: EditIntersection::Closed,
+        })
+        .collect();
     let mut edits = app.primary.map.get_edits().clone();
-    edits.commands.push(EditCmd::ChangeIntersection {
-        i,
-        old: app.primary.map.get_i_edit(i),
-        new: EditIntersection::Closed,
+    edits.commands.push(if cmds.len() == 1 {
+        cmds.pop().unwrap()
+    } else {
+        EditCmd::Batch(cmds)
     });
     apply_map_edits(ctx, app, edits);
 
@@ -472,7 +528,12 @@ pub fn close_intersection(
     let mut err_state = msg(
         "Error",
         vec![format!(
-            "Can't close this intersection; {} sidewalks disconnected",
+            "Can't close {}; {} sidewalks disconnected",
+            if ids.len() == 1 {
+                "this intersection"
+            } else {
+                "these intersections"
+            },
             disconnected.len()
         )],
     );
@@ -490,3 +551,19 @@ pub fn close_intersection(
         Transition::Replace(err_state)
     }
 }
+
+fn cmd_to_id(cmd: &EditCmd) -> ID {
+    match cmd {
+        EditCmd::ChangeLaneType { id, .. } => ID::Lane(*id),
+        EditCmd::ReverseLane { l, .. } => ID::Lane(*l),
+        EditCmd::ChangeIntersection { i, .. } => ID::Intersection(*i),
+        EditCmd::ChangeBannedTurns { i, .. } => ID::Intersection(*i),
+        EditCmd::ChangeSpeedLimit { id, .. } | EditCmd::ChangeAccessRestrictions { id, .. } => {
+            ID::Road(*id)
+        }
+        EditCmd::ChangeRoadLanes { r, .. } => ID::Road(*r),
+        EditCmd::ChangeParkingRules { id, .. } => ID::Road(*id),
+        EditCmd::ChangeOffstreetParkingRules { b, .. } => ID::Building(*b),
+        EditCmd::Batch(ref cmds) => cmd_to_id(&cmds[0]),
+    }
+}
@@ -0,0 +1,157 @@
+use crate::app::App;
+use crate::common::CommonState;
+use crate::edit::{
+    apply_map_edits, close_intersections, edit_turn_restrictions, StopSignEditor,
+    TrafficSignalEditor,
+};
+use crate::game::{State, Transition};
+use abstutil::Timer;
+use ezgui::{
+    hotkey, Btn, Composite, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Outcome,
+    TextExt, VerticalAlignment, Widget,
+};
+use map_model::{ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID};
+use sim::Sim;
+use std::collections::BTreeSet;
+
+// There's nothing to tune about a roundabout, but it can be turned back into something else. All
+// intersections of the same roundabout are converted together.
+pub struct RoundaboutEditor {
+    composite: Composite,
+    id: IntersectionID,
+    ring: BTreeSet<IntersectionID>,
+
+    suspended_sim: Sim,
+}
+
+impl RoundaboutEditor {
+    pub fn new(
+        id: IntersectionID,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        suspended_sim: Sim,
+    ) -> RoundaboutEditor {
+        app.primary.current_selection = None;
+        let roundabout = app.primary.map.get_roundabout(id);
+        let ring = roundabout.ring(&app.primary.map);
+
+        let composite = Composite::new(
+            Widget::col(vec![
+                "Roundabout editor".draw_text(ctx),
+                if ring.len() > 1 {
+                    format!("{} intersections belong to this roundabout", ring.len()).draw_text(ctx)
+                } else if roundabout.circulating.is_empty() {
+                    // TODO Vehicles should yield to the approach on their left (right when
+                    // driving on the left).
+                    "There's no ring road, so everybody entering yields, like at an all-way stop"
+                        .draw_text(ctx)
+                } else {
+                    "Vehicles entering yield to the ones already circulating".draw_text(ctx)
+                },
+                Btn::text_fg("close intersection for construction").build_def(ctx, None),
+                Btn::text_fg("ban or allow turns").build_def(ctx, hotkey(Key::T)),
+                Btn::text_fg("convert to stop signs").build_def(ctx, None),
+                Btn::text_fg("convert to traffic signal").build_def(ctx, None),
+                Btn::text_fg("Finish").build_def(ctx, hotkey(Key::Escape)),
+            ])
+            .bg(app.cs.panel_bg)
+            .padding(10),
+        )
+        .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+        .build(ctx);
+
+        RoundaboutEditor {
+            composite,
+            id,
+            ring,
+            suspended_sim,
+        }
+    }
+
+    // Replaces every intersection of the roundabout at once.
+    fn convert(
+        &self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        new: &dyn Fn(&App, IntersectionID) -> EditIntersection,
+    ) {
+        let mut cmds = Vec::new();
+        for i in &self.ring {
+            cmds.push(EditCmd::ChangeIntersection {
+                i: *i,
+                old: app.primary.map.get_i_edit(*i),
+                new: new(app, *i),
+            });
+        }
+        let mut edits = app.primary.map.get_edits().clone();
+        edits.commands.push(EditCmd::Batch(cmds));
+        apply_map_edits(ctx, app, edits);
+    }
+}
+
+impl State for RoundaboutEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.composite.event(ctx) {
+            Some(Outcome::Clicked(x)) => match x.as_ref() {
+                "Finish" => {
+                    return Transition::Pop;
+                }
+                "close intersection for construction" => {
+                    return close_intersections(ctx, app, self.ring.clone(), true);
+                }
+                "ban or allow turns" => {
+                    return Transition::Push(edit_turn_restrictions(
+                        app,
+                        self.id,
+                        self.suspended_sim.clone(),
+                    ));
+                }
+                "convert to stop signs" => {
+                    self.convert(ctx, app, &|app: &App, i| {
+                        EditIntersection::StopSign(ControlStopSign::new(&app.primary.map, i))
+                    });
+                    return Transition::Replace(Box::new(StopSignEditor::new(
+                        self.id,
+                        ctx,
+                        app,
+                        self.suspended_sim.clone(),
+                    )));
+                }
+                "convert to traffic signal" => {
+                    self.convert(ctx, app, &|app: &App, i| {
+                        EditIntersection::TrafficSignal(ControlTrafficSignal::new(
+                            &app.primary.map,
+                            i,
+                            &mut Timer::throwaway(),
+                        ))
+                    });
+                    return Transition::Replace(Box::new(TrafficSignalEditor::new(
+                        self.id,
+                        ctx,
+                        app,
+                        self.suspended_sim.clone(),
+                    )));
+                }
+                _ => unreachable!(),
+            },
+            None => {}
+        }
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        let mut batch = GeomBatch::new();
+        for i in &self.ring {
+            batch.push(
+                app.cs.perma_selected_object.alpha(0.5),
+                app.primary.map.get_i(*i).polygon.clone(),
+            );
+        }
+        batch.draw(g);
+
+        self.composite.draw(g);
+        CommonState::draw_osd(g, app, &None);
+    }
+}
@@ -1,6 +1,9 @@
 use crate::app::App;
 use crate::common::CommonState;
-use crate::edit::{apply_map_edits, close_intersection, TrafficSignalEditor};
+use crate::edit::{
+    apply_map_edits, close_intersection, edit_turn_restrictions, RoundaboutEditor,
+    TrafficSignalEditor,
+};
 use crate::game::{State, Transition};
 use crate::render::DrawIntersection;
 use abstutil::Timer;
@@ -10,13 +13,12 @@ use ezgui::{
 };
 use geom::Polygon;
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, RoadID,
+    ControlRoundabout, ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection,
+    IntersectionID, RoadID,
 };
 use sim::Sim;
 use std::collections::HashMap;
 
-// TODO For now, individual turns can't be manipulated. Banning turns could be useful, but I'm not
-// sure what to do about the player orphaning a section of the map.
 pub struct StopSignEditor {
     composite: Composite,
     id: IntersectionID,
@@ -59,7 +61,9 @@ impl StopSignEditor {
                     Btn::text_fg("reset to default").inactive(ctx)
                 },
                 Btn::text_fg("close intersection for construction").build_def(ctx, None),
+                Btn::text_fg("ban or allow turns").build_def(ctx, hotkey(Key::T)),
                 Btn::text_fg("convert to traffic signal").build_def(ctx, None),
+                Btn::text_fg("convert to roundabout").build_def(ctx, None),
                 Btn::text_fg("Finish").build_def(ctx, hotkey(Key::Escape)),
             ])
             .bg(app.cs.panel_bg)
@@ -146,6 +150,13 @@ impl State for StopSignEditor {
                 "close intersection for construction" => {
                     return close_intersection(ctx, app, self.id, true);
                 }
+                "ban or allow turns" => {
+                    return Transition::Push(edit_turn_restrictions(
+                        app,
+                        self.id,
+                        self.suspended_sim.clone(),
+                    ));
+                }
                 "convert to traffic signal" => {
                     let mut edits = app.primary.map.get_edits().clone();
                     edits.commands.push(EditCmd::ChangeIntersection {
@@ -165,6 +176,24 @@ impl State for StopSignEditor {
                         self.suspended_sim.clone(),
                     )));
                 }
+                "convert to roundabout" => {
+                    let mut edits = app.primary.map.get_edits().clone();
+                    edits.commands.push(EditCmd::ChangeIntersection {
+                        i: self.id,
+                        old: app.primary.map.get_i_edit(self.id),
+                        new: EditIntersection::Roundabout(ControlRoundabout::new(
+                            &app.primary.map,
+                            self.id,
+                        )),
+                    });
+                    apply_map_edits(ctx, app, edits);
+                    return Transition::Replace(Box::new(RoundaboutEditor::new(
+                        self.id,
+                        ctx,
+                        app,
+                        self.suspended_sim.clone(),
+                    )));
+                }
                 _ => unreachable!(),
             },
             None => {}
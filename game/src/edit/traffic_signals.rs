@@ -1,6 +1,8 @@
 use crate::app::{App, ShowEverything};
 use crate::common::CommonState;
-use crate::edit::{apply_map_edits, close_intersection, StopSignEditor};
+use crate::edit::{
+    apply_map_edits, close_intersection, edit_turn_restrictions, RoundaboutEditor, StopSignEditor,
+};
 use crate::game::{msg, DrawBaselayer, State, Transition, WizardState};
 use crate::render::{
     draw_signal_phase, make_signal_diagram, DrawOptions, DrawTurnGroup, BIG_ARROW_THICKNESS,
@@ -14,8 +16,8 @@ use ezgui::{
 };
 use geom::Duration;
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, Phase,
-    TurnGroupID, TurnPriority,
+    ControlRoundabout, ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection,
+    IntersectionID, Phase, PhaseType, SignalCorridor, TurnGroupID, TurnPriority,
 };
 use sim::Sim;
 use std::collections::BTreeSet;
@@ -370,11 +372,24 @@ fn edit_entire_signal(app: &App, i: IntersectionID, suspended_sim: Sim) -> Box<d
         let use_template = "use template";
         let all_walk = "add an all-walk phase at the end";
         let stop_sign = "convert to stop signs";
+        let roundabout = "convert to a roundabout";
         let close = "close intersection for construction";
         let offset = "edit signal offset";
+        let green_wave = "coordinate a green wave towards another intersection";
+        let turns = "ban or allow turns";
         let reset = "reset to default";
 
-        let mut choices = vec![use_template, all_walk, stop_sign, close, offset, reset];
+        let mut choices = vec![
+            use_template,
+            all_walk,
+            stop_sign,
+            roundabout,
+            close,
+            offset,
+            green_wave,
+            turns,
+            reset,
+        ];
         if !has_sidewalks {
             choices.remove(1);
         }
@@ -429,6 +444,21 @@ fn edit_entire_signal(app: &App, i: IntersectionID, suspended_sim: Sim) -> Box<d
                     suspended_sim.clone(),
                 ))))
             }
+            x if x == roundabout => {
+                let mut edits = app.primary.map.get_edits().clone();
+                edits.commands.push(EditCmd::ChangeIntersection {
+                    i,
+                    old: app.primary.map.get_i_edit(i),
+                    new: EditIntersection::Roundabout(ControlRoundabout::new(&app.primary.map, i)),
+                });
+                apply_map_edits(ctx, app, edits);
+                Some(Transition::PopThenReplace(Box::new(RoundaboutEditor::new(
+                    i,
+                    ctx,
+                    app,
+                    suspended_sim.clone(),
+                ))))
+            }
             x if x == close => Some(close_intersection(ctx, app, i, false)),
             x if x == offset => {
                 let new_duration = wizard.input_usize_prefilled(
@@ -446,6 +476,45 @@ fn edit_entire_signal(app: &App, i: IntersectionID, suspended_sim: Sim) -> Box<d
                     editor.change_phase(editor.current_phase, app, ctx);
                 })))
             }
+            x if x == green_wave => {
+                let to =
+                    IntersectionID(wizard.input_usize("Coordinate up to which intersection ID?")?);
+                let map = &app.primary.map;
+                if to.0 >= map.all_intersections().len() {
+                    return Some(Transition::Replace(msg(
+                        "Error",
+                        vec![format!("{} doesn't exist", to)],
+                    )));
+                }
+                let cmd = match SignalCorridor::between(map, i, to) {
+                    Some(corridor) => corridor.green_wave(map),
+                    None => {
+                        return Some(Transition::Replace(msg(
+                            "Error",
+                            vec![format!("Cars can't drive from {} to {}", i, to)],
+                        )));
+                    }
+                };
+                if let Some(cmd) = cmd {
+                    let mut edits = map.get_edits().clone();
+                    edits.commands.push(cmd);
+                    apply_map_edits(ctx, app, edits);
+                    Some(Transition::Pop)
+                } else {
+                    Some(Transition::Replace(msg(
+                        "Error",
+                        vec![format!(
+                            "There's no other traffic signal between {} and {}",
+                            i, to
+                        )],
+                    )))
+                }
+            }
+            x if x == turns => Some(Transition::Replace(edit_turn_restrictions(
+                app,
+                i,
+                suspended_sim.clone(),
+            ))),
             x if x == reset => {
                 Some(Transition::PopWithData(Box::new(move |state, app, ctx| {
                     let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
@@ -472,17 +541,26 @@ fn edit_entire_signal(app: &App, i: IntersectionID, suspended_sim: Sim) -> Box<d
 fn edit_phase(app: &App, i: IntersectionID, idx: usize) -> Box<dyn State> {
     let signal = app.primary.map.get_traffic_signal(i);
     let num_phases = signal.phases.len();
-    let current_duration = signal.phases[idx].duration;
+    let current_type = signal.phases[idx].phase_type.clone();
 
     WizardState::new(Box::new(move |wiz, ctx, _| {
         let change_duration = "change phase duration";
+        let make_actuated = "make this phase actuated";
+        let change_actuated = "change actuated timing";
+        let toggle_skip = "toggle skipping this phase when nobody's waiting";
+        let make_fixed = "make this phase fixed-time";
         let new_before = "add new phase before";
         let new_after = "add new phase after";
         let move_up = "move this phase up";
         let move_down = "move this phase down";
         let delete = "delete this phase";
 
-        let mut choices = vec![change_duration, new_before, new_after];
+        let mut choices = if current_type.is_actuated() {
+            vec![change_actuated, toggle_skip, make_fixed]
+        } else {
+            vec![change_duration, make_actuated]
+        };
+        choices.extend(vec![new_before, new_after]);
         if idx != 0 {
             choices.push(move_up);
         }
@@ -499,25 +577,81 @@ fn edit_phase(app: &App, i: IntersectionID, idx: usize) -> Box<dyn State> {
             x if x == change_duration => {
                 let new_duration = wizard.input_something(
                     "How long should this phase be (seconds)?",
-                    Some(format!("{}", current_duration.inner_seconds() as usize)),
-                    Box::new(|line| {
-                        line.parse::<usize>()
-                            .ok()
-                            .and_then(|n| if n != 0 { Some(n) } else { None })
-                    }),
+                    Some(format!(
+                        "{}",
+                        current_type.simple_duration().inner_seconds() as usize
+                    )),
+                    Box::new(parse_nonzero_secs),
                 )?;
-                Some(Transition::PopWithData(Box::new(move |state, app, ctx| {
-                    let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
-                    let orig_signal = app.primary.map.get_traffic_signal(editor.i);
-
-                    let mut new_signal = orig_signal.clone();
-                    new_signal.phases[idx].duration = Duration::seconds(new_duration as f64);
-                    editor.command_stack.push(orig_signal.clone());
-                    editor.redo_stack.clear();
-                    editor.top_panel = make_top_panel(ctx, app, true, false);
-                    change_traffic_signal(new_signal, app, ctx);
-                    editor.change_phase(idx, app, ctx);
-                })))
+                Some(change_phase_type(
+                    idx,
+                    PhaseType::Fixed(Duration::seconds(new_duration as f64)),
+                ))
+            }
+            x if x == make_actuated => Some(change_phase_type(
+                idx,
+                PhaseType::actuated_from(current_type.simple_duration()),
+            )),
+            x if x == make_fixed => Some(change_phase_type(
+                idx,
+                PhaseType::Fixed(current_type.simple_duration()),
+            )),
+            x if x == change_actuated => {
+                if let PhaseType::Actuated {
+                    min_green,
+                    max_green,
+                    gap,
+                    skip_if_no_demand,
+                } = current_type
+                {
+                    let new_min = wizard.input_something(
+                        "Minimum green time (seconds)?",
+                        Some(format!("{}", min_green.inner_seconds() as usize)),
+                        Box::new(parse_nonzero_secs),
+                    )?;
+                    let new_max = wizard.input_something(
+                        "Maximum green time (seconds)?",
+                        Some(format!("{}", max_green.inner_seconds() as usize)),
+                        Box::new(move |line| parse_nonzero_secs(line).filter(|n| *n >= new_min)),
+                    )?;
+                    let new_gap = wizard.input_something(
+                        "End the phase after how long with no new arrivals (seconds)?",
+                        Some(format!("{}", gap.inner_seconds() as usize)),
+                        Box::new(parse_nonzero_secs),
+                    )?;
+                    Some(change_phase_type(
+                        idx,
+                        PhaseType::Actuated {
+                            min_green: Duration::seconds(new_min as f64),
+                            max_green: Duration::seconds(new_max as f64),
+                            gap: Duration::seconds(new_gap as f64),
+                            skip_if_no_demand,
+                        },
+                    ))
+                } else {
+                    unreachable!()
+                }
+            }
+            x if x == toggle_skip => {
+                if let PhaseType::Actuated {
+                    min_green,
+                    max_green,
+                    gap,
+                    skip_if_no_demand,
+                } = current_type
+                {
+                    Some(change_phase_type(
+                        idx,
+                        PhaseType::Actuated {
+                            min_green,
+                            max_green,
+                            gap,
+                            skip_if_no_demand: !skip_if_no_demand,
+                        },
+                    ))
+                } else {
+                    unreachable!()
+                }
             }
             x if x == new_before => {
                 Some(Transition::PopWithData(Box::new(move |state, app, ctx| {
@@ -644,6 +778,27 @@ fn check_for_missing_groups(
     ))
 }
 
+fn change_phase_type(idx: usize, phase_type: PhaseType) -> Transition {
+    Transition::PopWithData(Box::new(move |state, app, ctx| {
+        let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+        let orig_signal = app.primary.map.get_traffic_signal(editor.i);
+
+        let mut new_signal = orig_signal.clone();
+        new_signal.phases[idx].phase_type = phase_type;
+        editor.command_stack.push(orig_signal.clone());
+        editor.redo_stack.clear();
+        editor.top_panel = make_top_panel(ctx, app, true, false);
+        change_traffic_signal(new_signal, app, ctx);
+        editor.change_phase(idx, app, ctx);
+    }))
+}
+
+fn parse_nonzero_secs(line: String) -> Option<usize> {
+    line.parse::<usize>()
+        .ok()
+        .and_then(|n| if n != 0 { Some(n) } else { None })
+}
+
 // TODO I guess it's valid to preview without all turns possible. Some agents are just sad.
 fn make_previewer(i: IntersectionID, phase: usize, suspended_sim: Sim) -> Box<dyn State> {
     WizardState::new(Box::new(move |wiz, ctx, app| {
@@ -663,14 +818,17 @@ fn make_previewer(i: IntersectionID, phase: usize, suspended_sim: Sim) -> Box<dy
                 // TODO Use the offset correctly
                 let mut step = Duration::ZERO;
                 for idx in 0..phase {
-                    step += signal.phases[idx].duration;
+                    step += signal.phases[idx].phase_type.simple_duration();
                 }
                 app.primary.sim.normal_step(&app.primary.map, step);
 
                 spawn_agents_around(i, app);
             }
             x if x == right_now => {
-                app.primary.sim = suspended_sim.clone();
+                // Pathfinding was already updated before previewing.
+                let mut sim = suspended_sim.clone();
+                sim.handle_live_edits(&app.primary.map, &mut Timer::throwaway());
+                app.primary.sim = sim;
             }
             _ => unreachable!(),
         };
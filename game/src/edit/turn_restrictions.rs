@@ -0,0 +1,150 @@
+use crate::app::App;
+use crate::edit::{apply_map_edits, RoundaboutEditor, StopSignEditor, TrafficSignalEditor};
+use crate::game::{msg, State, Transition, WizardState};
+use ezgui::WrappedWizard;
+use map_model::{DirectedRoadID, EditCmd, IntersectionID, LaneID, Map, TurnID};
+use sim::Sim;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Ban entire movements between two roads or individual lane-to-lane turns, or allow previously
+// banned turns again. Afterwards, the intersection editor underneath is rebuilt.
+pub fn edit_turn_restrictions(app: &App, i: IntersectionID, suspended_sim: Sim) -> Box<dyn State> {
+    let map = &app.primary.map;
+    let banned = map.get_i(i).banned_turns.clone();
+
+    let mut movements: BTreeMap<(DirectedRoadID, DirectedRoadID), BTreeSet<TurnID>> =
+        BTreeMap::new();
+    let mut single_turns: Vec<(String, BTreeSet<TurnID>)> = Vec::new();
+    for t in map.get_turns_in_intersection(i) {
+        if t.between_sidewalks() {
+            continue;
+        }
+        movements
+            .entry(movement(t.id, map))
+            .or_insert_with(BTreeSet::new)
+            .insert(t.id);
+        single_turns.push((
+            format!("{:?} from {} to {}", t.turn_type, t.id.src, t.id.dst),
+            vec![t.id].into_iter().collect(),
+        ));
+    }
+    let movements = describe_movements(movements, map);
+
+    let mut banned_movements = BTreeMap::new();
+    for t in &banned {
+        banned_movements
+            .entry(movement(*t, map))
+            .or_insert_with(BTreeSet::new)
+            .insert(*t);
+    }
+    let banned_movements = describe_movements(banned_movements, map);
+
+    WizardState::new(Box::new(move |wiz, ctx, app| {
+        let ban_movement = "ban a movement between two roads";
+        let ban_turn = "ban one lane-to-lane turn";
+        let allow_movement = "allow a banned movement again";
+        let allow_all = "allow all banned turns again";
+
+        let mut choices = vec![ban_movement, ban_turn];
+        if !banned.is_empty() {
+            choices.push(allow_movement);
+            choices.push(allow_all);
+        }
+
+        let mut wizard = wiz.wrap(ctx);
+        let mut new_banned = banned.clone();
+        match wizard.choose_string("", move || choices.clone())?.as_str() {
+            x if x == ban_movement => {
+                new_banned.extend(choose(&mut wizard, "Ban which movement?", &movements)?);
+            }
+            x if x == ban_turn => {
+                new_banned.extend(choose(&mut wizard, "Ban which turn?", &single_turns)?);
+            }
+            x if x == allow_movement => {
+                for t in choose(&mut wizard, "Allow which movement?", &banned_movements)? {
+                    new_banned.remove(&t);
+                }
+            }
+            x if x == allow_all => {
+                new_banned.clear();
+            }
+            _ => unreachable!(),
+        }
+
+        if let Some(l) = orphaned_lane(i, &new_banned, &app.primary.map) {
+            return Some(Transition::Replace(msg(
+                "Error",
+                vec![format!("{} wouldn't have any turns left", l)],
+            )));
+        }
+
+        let mut edits = app.primary.map.get_edits().clone();
+        edits.commands.push(EditCmd::ChangeBannedTurns {
+            i,
+            old: banned.clone(),
+            new: new_banned,
+        });
+        apply_map_edits(ctx, app, edits);
+
+        let editor: Box<dyn State> = if app.primary.map.maybe_get_traffic_signal(i).is_some() {
+            Box::new(TrafficSignalEditor::new(i, ctx, app, suspended_sim.clone()))
+        } else if app.primary.map.maybe_get_roundabout(i).is_some() {
+            Box::new(RoundaboutEditor::new(i, ctx, app, suspended_sim.clone()))
+        } else {
+            Box::new(StopSignEditor::new(i, ctx, app, suspended_sim.clone()))
+        };
+        Some(Transition::PopThenReplace(editor))
+    }))
+}
+
+fn movement(t: TurnID, map: &Map) -> (DirectedRoadID, DirectedRoadID) {
+    (
+        map.get_l(t.src).get_directed_parent(map),
+        map.get_l(t.dst).get_directed_parent(map),
+    )
+}
+
+fn describe_movements(
+    movements: BTreeMap<(DirectedRoadID, DirectedRoadID), BTreeSet<TurnID>>,
+    map: &Map,
+) -> Vec<(String, BTreeSet<TurnID>)> {
+    movements
+        .into_iter()
+        .map(|((from, to), turns)| {
+            (
+                format!(
+                    "from {} ({}) to {} ({})",
+                    map.get_r(from.id).get_name(),
+                    from.id,
+                    map.get_r(to.id).get_name(),
+                    to.id
+                ),
+                turns,
+            )
+        })
+        .collect()
+}
+
+fn choose(
+    wizard: &mut WrappedWizard,
+    query: &str,
+    choices: &Vec<(String, BTreeSet<TurnID>)>,
+) -> Option<BTreeSet<TurnID>> {
+    let label = wizard.choose_string(query, || {
+        choices.iter().map(|(label, _)| label.clone()).collect()
+    })?;
+    choices
+        .iter()
+        .find(|(l, _)| l == &label)
+        .map(|(_, turns)| turns.clone())
+}
+
+// Banning turns mustn't leave a lane with no way out of the intersection.
+fn orphaned_lane(i: IntersectionID, banned: &BTreeSet<TurnID>, map: &Map) -> Option<LaneID> {
+    let mut has_turns: BTreeMap<LaneID, bool> = BTreeMap::new();
+    for t in map.get_turns_in_intersection(i) {
+        let ok = !banned.contains(&t.id);
+        *has_turns.entry(t.id.src).or_insert(false) |= ok;
+    }
+    has_turns.into_iter().find(|(_, ok)| !ok).map(|(l, _)| l)
+}
@@ -33,8 +33,8 @@ impl Game {
         if let Some(edits_name) = start_with_edits {
             // TODO Maybe loading screen
             let mut timer = abstutil::Timer::new("apply initial edits");
-            let edits =
-                map_model::MapEdits::load(app.primary.map.get_name(), &edits_name, &mut timer);
+            let edits = map_model::MapEdits::load(&app.primary.map, &edits_name, &mut timer)
+                .unwrap_or_else(|err| panic!("{}", err));
             crate::edit::apply_map_edits(ctx, &mut app, edits);
             app.primary
                 .map
@@ -7,7 +7,7 @@ use ezgui::{
 };
 use geom::{Circle, Distance, Polygon, Pt2D, Statistic, Time};
 use map_model::{BusRouteID, BusStopID};
-use sim::{AgentID, CarID};
+use sim::{AgentID, CarID, OnTimePerformance, BUS_BUNCHING_THRESHOLD};
 
 pub fn stop(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusStopID) -> Vec<Widget> {
     let mut rows = vec![];
@@ -52,14 +52,30 @@ pub fn stop(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusStopID)
         {
             txt.add(Line(format!("  Waiting: {}", hgram.describe())).secondary());
         }
+        if let Some(perf) = sim
+            .get_analytics()
+            .bus_on_time_performance(sim.time(), r)
+            .remove(&id)
+        {
+            txt.add(
+                Line(format!(
+                    "  On time: {} of {} ({} early, {} late)",
+                    perf.on_time,
+                    perf.total(),
+                    perf.early,
+                    perf.late
+                ))
+                .secondary(),
+            );
+        }
         rows.push(txt.draw(ctx));
     }
 
     rows
 }
 
-// TODO For now, this conflates a single bus with the whole route, but that's fine, since the sim
-// only spawns one per route anyway.
+// TODO For now, this conflates a single bus with the whole route. Routes with a schedule have many
+// buses.
 pub fn bus_status(ctx: &mut EventCtx, app: &App, details: &mut Details, id: CarID) -> Vec<Widget> {
     let mut rows = bus_header(ctx, app, details, id, Tab::BusStatus(id));
 
@@ -68,6 +84,7 @@ pub fn bus_status(ctx: &mut EventCtx, app: &App, details: &mut Details, id: CarI
 
     let route = app.primary.sim.bus_route_id(id).unwrap();
     rows.push(passenger_delay(ctx, app, details, route));
+    rows.push(service_quality(ctx, app, route));
 
     rows
 }
@@ -149,6 +166,76 @@ fn delays_over_time(ctx: &mut EventCtx, app: &App, id: BusRouteID) -> Widget {
     ])
 }
 
+fn service_quality(ctx: &mut EventCtx, app: &App, id: BusRouteID) -> Widget {
+    let route = app.primary.map.get_br(id);
+    let analytics = app.primary.sim.get_analytics();
+    let now = app.primary.sim.time();
+    let mut txt = Text::new();
+
+    if route.is_scheduled() {
+        let mut total = OnTimePerformance::default();
+        for perf in analytics.bus_on_time_performance(now, route).values() {
+            total.merge(perf);
+        }
+        if total.total() == 0 {
+            txt.add(Line("No scheduled arrivals yet"));
+        } else {
+            txt.add(Line(format!(
+                "{}% of {} arrivals on time",
+                (100.0 * (total.on_time as f64) / (total.total() as f64)).round(),
+                abstutil::prettyprint_usize(total.total())
+            )));
+            txt.add(
+                Line(format!(
+                    "  {} early, {} late",
+                    abstutil::prettyprint_usize(total.early),
+                    abstutil::prettyprint_usize(total.late)
+                ))
+                .secondary(),
+            );
+        }
+    } else {
+        txt.add(Line("No schedule for this route"));
+    }
+
+    let variation = analytics.bus_headway_variation(now, id);
+    if variation.is_empty() {
+        txt.add(Line("Not enough arrivals to measure headways"));
+    } else {
+        let bunched = variation
+            .values()
+            .filter(|x| **x > BUS_BUNCHING_THRESHOLD)
+            .count();
+        txt.add(Line(format!(
+            "Buses bunching at {} of {} stops",
+            bunched,
+            variation.len()
+        )));
+        let worst = variation.values().cloned().fold(0.0, f64::max);
+        txt.add(Line(format!("  Worst headway variation: {:.2}", worst)).secondary());
+    }
+
+    let loads = analytics.bus_passenger_loads(now, id);
+    if let Some((idx, max)) = route
+        .stops
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, stop)| loads.get(stop).map(|h| (idx, h.select(Statistic::Max))))
+        .max_by_key(|(_, max)| *max)
+    {
+        txt.add(Line(format!(
+            "Peak load: {} passengers leaving stop {}",
+            max,
+            idx + 1
+        )));
+    }
+
+    Widget::col(vec![
+        Line("Service quality").small_heading().draw(ctx),
+        txt.draw(ctx),
+    ])
+}
+
 fn passenger_delay(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusRouteID) -> Widget {
     let route = app.primary.map.get_br(id);
     let mut master_col = vec![Line("Passengers waiting").small_heading().draw(ctx)];
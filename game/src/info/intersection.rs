@@ -1,7 +1,7 @@
 use crate::app::App;
 use crate::info::{header_btns, make_tabs, throughput, DataOptions, Details, Tab};
 use abstutil::prettyprint_usize;
-use ezgui::{EventCtx, Line, LinePlot, PlotOptions, Series, Text, Widget};
+use ezgui::{EventCtx, Line, LinePlot, PlotOptions, Series, Text, TextExt, Widget};
 use geom::{Duration, Statistic, Time};
 use map_model::{IntersectionID, IntersectionType};
 use sim::Analytics;
@@ -22,6 +22,15 @@ pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: IntersectionID
     }
     rows.push(txt.draw(ctx));
 
+    if let Some(roundabout) = app.primary.map.maybe_get_roundabout(id) {
+        let ring = roundabout.ring(&app.primary.map);
+        if ring.len() > 1 {
+            rows.push(
+                format!("Part of a roundabout with {} intersections", ring.len()).draw_text(ctx),
+            );
+        }
+    }
+
     rows
 }
 
@@ -86,7 +95,7 @@ pub fn delay(
     );
     let i = app.primary.map.get_i(id);
 
-    assert!(i.is_traffic_signal());
+    assert!(i.is_traffic_signal() || i.is_roundabout());
     rows.push(opts.to_controls(ctx, app));
 
     rows.push(delay_plot(ctx, app, id, opts).margin(10));
@@ -153,6 +162,7 @@ fn header(
     let label = match i.intersection_type {
         IntersectionType::StopSign => format!("{} (Stop signs)", id),
         IntersectionType::TrafficSignal => format!("{} (Traffic signals)", id),
+        IntersectionType::Roundabout => format!("{} (Roundabout)", id),
         IntersectionType::Border => format!("Border #{}", id.0),
         IntersectionType::Construction => format!("{} (under construction)", id),
     };
@@ -169,7 +179,7 @@ fn header(
                 Tab::IntersectionTraffic(id, DataOptions::new(app)),
             ),
         ];
-        if i.is_traffic_signal() {
+        if i.is_traffic_signal() || i.is_roundabout() {
             tabs.push(("Delay", Tab::IntersectionDelay(id, DataOptions::new(app))));
         }
         tabs
@@ -24,6 +24,16 @@ pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Vec
     } else {
         kv.push(("Speed limit", r.get_speed_limit().to_string()));
     }
+    if !r.no_through_traffic.is_empty() {
+        kv.push((
+            "No through-traffic for",
+            r.no_through_traffic
+                .iter()
+                .map(|c| format!("{:?}", c))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ));
+    }
 
     kv.push(("Length", l.length().describe_rounded()));
 
@@ -49,7 +59,7 @@ pub fn debug(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Ve
         ));
     }
 
-    if let Some(types) = l.get_turn_restrictions(r) {
+    if let Some(types) = l.get_turn_restrictions(r, map.get_driving_side()) {
         kv.push(("Turn restrictions".to_string(), format!("{:?}", types)));
     }
     for (restriction, to) in &r.turn_restrictions {
@@ -2,6 +2,7 @@ use crate::app::App;
 use crate::common::Colorer;
 use crate::layer::Layers;
 use ezgui::EventCtx;
+use std::collections::BTreeSet;
 
 pub fn bike_network(ctx: &mut EventCtx, app: &App) -> Layers {
     // TODO Number and total distance
@@ -55,6 +56,23 @@ pub fn edits(ctx: &mut EventCtx, app: &App) -> Layers {
                 "{} intersections changed",
                 edits.original_intersections.len()
             ),
+            format!(
+                "{} intersections with turns banned or allowed",
+                edits.original_banned_turns.len()
+            ),
+            format!(
+                "{} roads with speed limits or access changed",
+                edits
+                    .original_speed_limits
+                    .keys()
+                    .chain(edits.original_access_restrictions.keys())
+                    .collect::<BTreeSet<_>>()
+                    .len()
+            ),
+            format!(
+                "{} roads with lanes added, removed, or resized",
+                edits.original_road_lanes.len()
+            ),
         ],
         vec![("modified lane/intersection", app.cs.edits_layer)],
     );
@@ -62,7 +80,19 @@ pub fn edits(ctx: &mut EventCtx, app: &App) -> Layers {
     for l in edits.original_lts.keys().chain(&edits.reversed_lanes) {
         colorer.add_l(*l, app.cs.edits_layer, &app.primary.map);
     }
-    for i in edits.original_intersections.keys() {
+    for r in edits
+        .original_speed_limits
+        .keys()
+        .chain(edits.original_access_restrictions.keys())
+        .chain(edits.original_road_lanes.keys())
+    {
+        colorer.add_r(*r, app.cs.edits_layer, &app.primary.map);
+    }
+    for i in edits
+        .original_intersections
+        .keys()
+        .chain(edits.original_banned_turns.keys())
+    {
         colorer.add_i(*i, app.cs.edits_layer);
     }
 
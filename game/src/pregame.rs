@@ -11,7 +11,7 @@ use ezgui::{
 };
 use geom::{Duration, Line, Pt2D, Speed};
 use instant::Instant;
-use map_model::{Map, MapEdits};
+use map_model::{Map, PermanentMapEdits};
 use rand::Rng;
 use rand_xorshift::XorShiftRng;
 
@@ -273,7 +273,7 @@ fn proposals_picker(ctx: &mut EventCtx, app: &App) -> Box<dyn State> {
     let mut buttons: Vec<Widget> = Vec::new();
     for map_name in abstutil::list_all_objects(abstutil::path_all_maps()) {
         for (_, edits) in
-            abstutil::load_all_objects::<MapEdits>(abstutil::path_all_edits(&map_name))
+            abstutil::load_all_objects::<PermanentMapEdits>(abstutil::path_all_edits(&map_name))
         {
             if !edits.proposal_description.is_empty() {
                 let mut txt = Text::new();
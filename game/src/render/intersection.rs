@@ -6,7 +6,7 @@ use crate::render::{
 };
 use abstutil::Timer;
 use ezgui::{Drawable, FancyColor, GeomBatch, GfxCtx, Line, Prerender, RewriteColor, Text};
-use geom::{Angle, Distance, Line, PolyLine, Polygon, Pt2D, Time, EPSILON_DIST};
+use geom::{Angle, Circle, Distance, Line, PolyLine, Polygon, Pt2D, Time, EPSILON_DIST};
 use map_model::raw::DrivingSide;
 use map_model::{
     Intersection, IntersectionID, IntersectionType, Map, Road, RoadWithStopSign, Turn, TurnType,
@@ -71,6 +71,16 @@ impl DrawIntersection {
             IntersectionType::Construction => {
                 default_geom.fancy_push(FancyColor::Hatching, i.polygon.clone());
             }
+            IntersectionType::Roundabout => {
+                // The ring of a full roundabout is drawn as roads. A mini-roundabout or one
+                // created by an edit is just this intersection, so mark the middle.
+                if map.get_roundabout(i.id).circulating.is_empty() {
+                    default_geom.push(
+                        cs.roundabout_island,
+                        Circle::new(i.polygon.center(), Distance::meters(2.0)).to_polygon(),
+                    );
+                }
+            }
             IntersectionType::TrafficSignal => {}
         }
 
@@ -133,8 +143,11 @@ impl Renderable for DrawIntersection {
                 .map(|(t, _)| *t != app.primary.sim.time())
                 .unwrap_or(true);
             if recalc {
-                let (idx, phase, t) =
-                    signal.current_phase_and_remaining_time(app.primary.sim.time());
+                let (idx, t) = app
+                    .primary
+                    .sim
+                    .current_phase_and_remaining_time(self.id, &app.primary.map);
+                let phase = &signal.phases[idx];
                 let mut batch = GeomBatch::new();
                 draw_signal_phase(
                     g.prerender,
@@ -351,6 +351,15 @@ impl DrawMap {
         agents.get(on).into_iter().find(|r| r.get_id() == id)
     }
 
+    // For lanes created by map edits. Lanes removed by edits stay in the quadtree, so callers have
+    // to skip them.
+    // TODO The bounding boxes of lanes with changed widths are stale.
+    pub fn add_lane(&mut self, draw: DrawLane, map: &Map) {
+        self.quadtree
+            .insert_with_box(draw.get_id(), draw.get_outline(map).get_bounds().as_bbox());
+        self.lanes.push(draw);
+    }
+
     // Unsorted, unexpanded, raw result.
     pub fn get_matching_objects(&self, bounds: Bounds) -> Vec<ID> {
         let mut results: Vec<ID> = Vec::new();
@@ -168,7 +168,8 @@ pub fn draw_signal_phase(
 
     let radius = Distance::meters(2.0);
     let center = app.primary.map.get_i(i).polygon.center();
-    let percent = time_left.unwrap() / phase.duration;
+    // For actuated phases, this is how much time is left before the phase has to end.
+    let percent = time_left.unwrap() / phase.phase_type.simple_duration();
     batch.push(
         app.cs.signal_box,
         Circle::new(center, 1.2 * radius).to_polygon(),
@@ -261,7 +262,7 @@ pub fn make_signal_diagram(
         if edit_mode {
             phase_rows.push(
                 Widget::row(vec![
-                    format!("Phase {}: {}", idx + 1, phase.duration).draw_text(ctx),
+                    format!("Phase {}: {}", idx + 1, phase.phase_type).draw_text(ctx),
                     Btn::svg_def("../data/system/assets/tools/edit.svg")
                         .build(
                             ctx,
@@ -278,7 +279,7 @@ pub fn make_signal_diagram(
                 .centered(),
             );
         } else {
-            phase_rows.push(format!("Phase {}: {}", idx + 1, phase.duration).draw_text(ctx));
+            phase_rows.push(format!("Phase {}: {}", idx + 1, phase.phase_type).draw_text(ctx));
         }
 
         let mut orig_batch = GeomBatch::new();
@@ -173,24 +173,25 @@ impl GameplayMode {
     }
 
     pub fn allows(&self, edits: &MapEdits) -> bool {
-        for cmd in &edits.commands {
-            match cmd {
-                EditCmd::ChangeLaneType { .. } | EditCmd::ReverseLane { .. } => {
-                    if !self.can_edit_lanes() {
-                        return false;
-                    }
-                }
-                EditCmd::ChangeIntersection { ref new, .. } => match new {
-                    EditIntersection::StopSign(_) => {
-                        if !self.can_edit_stop_signs() {
-                            return false;
-                        }
-                    }
-                    _ => {}
-                },
-            }
+        edits.commands.iter().all(|cmd| self.allows_cmd(cmd))
+    }
+
+    fn allows_cmd(&self, cmd: &EditCmd) -> bool {
+        match cmd {
+            EditCmd::ChangeLaneType { .. }
+            | EditCmd::ReverseLane { .. }
+            | EditCmd::ChangeBannedTurns { .. }
+            | EditCmd::ChangeSpeedLimit { .. }
+            | EditCmd::ChangeAccessRestrictions { .. }
+            | EditCmd::ChangeRoadLanes { .. }
+            | EditCmd::ChangeParkingRules { .. }
+            | EditCmd::ChangeOffstreetParkingRules { .. } => self.can_edit_lanes(),
+            EditCmd::ChangeIntersection { ref new, .. } => match new {
+                EditIntersection::StopSign(_) => self.can_edit_stop_signs(),
+                _ => true,
+            },
+            EditCmd::Batch(ref cmds) => cmds.iter().all(|cmd| self.allows_cmd(cmd)),
         }
-        true
     }
 
     pub fn initialize(&self, app: &mut App, ctx: &mut EventCtx) -> Box<dyn GameplayState> {
@@ -1368,6 +1368,13 @@ pub fn execute(_: &mut EventCtx, _: &mut App, id: ID, action: String) -> Transit
                         "Inspection",
                         vec!["This intersection is currently closed for construction."],
                     ),
+                    IntersectionType::Roundabout => msg(
+                        "Inspection",
+                        vec![
+                            "This is a roundabout. Vehicles entering yield to the ones already \
+                             circulating.",
+                        ],
+                    ),
                 }
             }
             (ID::Car(c), "draw WASH ME") => {
@@ -10,8 +10,8 @@ use crate::app::App;
 use crate::common::{tool_panel, CommonState, ContextualActions, Minimap};
 use crate::debug::DebugMode;
 use crate::edit::{
-    apply_map_edits, can_edit_lane, save_edits_as, EditMode, LaneEditor, StopSignEditor,
-    TrafficSignalEditor,
+    apply_map_edits, can_edit_lane, save_edits_as, EditMode, LaneEditor, RoundaboutEditor,
+    StopSignEditor, TrafficSignalEditor,
 };
 use crate::game::{State, Transition, WizardState};
 use crate::helpers::{cmp_duration_shorter, ID};
@@ -441,6 +441,9 @@ impl ContextualActions for Actions {
                     if app.primary.map.get_i(i).is_stop_sign() {
                         actions.push((Key::E, "edit stop sign".to_string()));
                     }
+                    if app.primary.map.get_i(i).is_roundabout() {
+                        actions.push((Key::E, "edit roundabout".to_string()));
+                    }
                 }
                 ID::Lane(l) => {
                     if !app.primary.map.get_turns_from_lane(l).is_empty() {
@@ -499,6 +502,14 @@ impl ContextualActions for Actions {
                     Box::new(StopSignEditor::new(i, ctx, app, sim_copy)),
                 )
             }
+            (ID::Intersection(i), "edit roundabout") => {
+                let edit = EditMode::new(ctx, app, self.gameplay.clone());
+                let sim_copy = edit.suspended_sim.clone();
+                Transition::PushTwice(
+                    Box::new(edit),
+                    Box::new(RoundaboutEditor::new(i, ctx, app, sim_copy)),
+                )
+            }
             (ID::Lane(l), "explore turns from this lane") => {
                 Transition::Push(TurnExplorer::new(ctx, app, l))
             }
@@ -178,6 +178,16 @@ impl Time {
     pub fn clamped_sub(self, dt: Duration) -> Time {
         Time::seconds_since_midnight((self.0 - dt.inner_seconds()).max(0.0))
     }
+
+    // Multi-day simulations keep counting up from START_OF_DAY. The first day is 0.
+    pub fn get_day(self) -> usize {
+        (self.0 / (24.0 * 3600.0)).floor() as usize
+    }
+
+    // The beginning of some day of a multi-day simulation
+    pub fn start_of_day(day: usize) -> Time {
+        Time::START_OF_DAY + Duration::hours(24 * day)
+    }
 }
 
 // 24-hour format by default
@@ -1,15 +1,26 @@
-use geom::LonLat;
-use itertools::Itertools;
+use geom::{Duration, LonLat, Time};
 use serde_derive::{Deserialize, Serialize};
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
 use transitfeed::GTFSIterator;
 
+// One variant of a route: a single direction with one particular sequence of stops. A route with
+// two directions and a short-turn pattern winds up as three of these, all with the same name.
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct Route {
     pub name: String,
+    // From GTFS direction_id. True if that's missing.
+    pub forwards: bool,
     pub stops: Vec<LonLat>,
+    // One entry per scheduled trip, sorted by departure from the first stop. Each has the time
+    // the bus should depart from every stop.
+    pub schedule: Vec<Vec<Time>>,
 }
 
+// Only trips running on this day are kept; the simulation only covers one typical weekday.
+// TODO Use calendar_dates.txt exceptions too.
+const SERVICE_DAY: &str = "wednesday";
+
 pub fn load(dir_path: &str) -> Vec<Route> {
     println!("Loading GTFS from {}", dir_path);
 
@@ -29,11 +40,18 @@ pub fn load(dir_path: &str) -> Vec<Route> {
         stop_id_to_pt.insert(rec.stop_id.clone(), LonLat::new(rec.stop_lon, rec.stop_lat));
     }
 
+    let active_services = load_active_services(dir_path);
+
     let mut trip_id_to_route_id_and_direction: HashMap<String, (String, bool)> = HashMap::new();
     for rec in
         GTFSIterator::<_, transitfeed::Trip>::from_path(&format!("{}/trips.txt", dir_path)).unwrap()
     {
         let rec = rec.unwrap();
+        if let Some(ref services) = active_services {
+            if !services.contains(&rec.service_id) {
+                continue;
+            }
+        }
         trip_id_to_route_id_and_direction.insert(
             rec.trip_id.clone(),
             (
@@ -43,48 +61,156 @@ pub fn load(dir_path: &str) -> Vec<Route> {
         );
     }
 
-    // Each (directed) route has many trips. Ignore all but the first and assume the list of stops
-    // is the same. Also assume that records with the same trip are contiguous and that
-    // stop_sequence is monotonic.
-    let mut directed_routes: HashMap<(String, bool), Vec<LonLat>> = HashMap::new();
-    for (key, group) in
-        GTFSIterator::<_, transitfeed::StopTime>::from_path(&format!("{}/stop_times.txt", dir_path))
-            .unwrap()
-            .map(|rec| rec.unwrap())
-            // TODO This only groups records with consecutive same trip ID. Might be a bug.
-            .group_by(|rec| rec.trip_id.clone())
-            .into_iter()
-    {
-        let (route_id, forwards) = trip_id_to_route_id_and_direction[&key].clone();
-        if directed_routes.contains_key(&(route_id.clone(), forwards)) {
+    // Don't assume records for the same trip are contiguous or sorted.
+    let mut stop_times_per_trip: HashMap<String, Vec<(usize, String, Option<Time>)>> =
+        HashMap::new();
+    for rec in read_csv::<StopTime>(&format!("{}/stop_times.txt", dir_path)) {
+        if !trip_id_to_route_id_and_direction.contains_key(&rec.trip_id) {
             continue;
         }
-        directed_routes.insert(
-            (route_id, forwards),
-            group.map(|rec| stop_id_to_pt[&rec.stop_id]).collect(),
-        );
+        stop_times_per_trip
+            .entry(rec.trip_id)
+            .or_insert_with(Vec::new)
+            .push((
+                rec.stop_sequence,
+                rec.stop_id,
+                if rec.departure_time.trim().is_empty() {
+                    None
+                } else {
+                    Some(parse_time(&rec.departure_time))
+                },
+            ));
+    }
+
+    // Trips with frequencies are templates; their stop_times only give relative offsets.
+    let mut frequencies: HashMap<String, Vec<Frequency>> = HashMap::new();
+    let frequencies_path = format!("{}/frequencies.txt", dir_path);
+    if abstutil::file_exists(frequencies_path.clone()) {
+        for rec in read_csv::<Frequency>(&frequencies_path) {
+            frequencies
+                .entry(rec.trip_id.clone())
+                .or_insert_with(Vec::new)
+                .push(rec);
+        }
+    }
+
+    // Group trips with the same route, direction, and sequence of stops into one variant.
+    let mut variants: BTreeMap<(String, bool, Vec<String>), Vec<Vec<Time>>> = BTreeMap::new();
+    for (trip_id, mut stop_times) in stop_times_per_trip {
+        stop_times.sort_by_key(|(seq, _, _)| *seq);
+        let (route_id, forwards) = trip_id_to_route_id_and_direction[&trip_id].clone();
+        let stops: Vec<String> = stop_times.iter().map(|(_, id, _)| id.clone()).collect();
+        let times = if let Some(times) =
+            interpolate_times(stop_times.iter().map(|(_, _, t)| *t).collect())
+        {
+            times
+        } else {
+            println!(
+                "WARNING: Skipping trip {}, because its first or last stop has no time",
+                trip_id
+            );
+            continue;
+        };
+        let schedule = variants
+            .entry((route_id, forwards, stops))
+            .or_insert_with(Vec::new);
+
+        if let Some(list) = frequencies.get(&trip_id) {
+            for freq in list {
+                let end = parse_time(&freq.end_time);
+                let headway = Duration::seconds(freq.headway_secs as f64);
+                let mut depart = parse_time(&freq.start_time);
+                while depart < end {
+                    let shift = depart - times[0];
+                    schedule.push(times.iter().map(|t| *t + shift).collect());
+                    depart += headway;
+                }
+            }
+        } else {
+            schedule.push(times);
+        }
     }
 
-    // Group together the pairs of directed routes
-    let route_ids: BTreeSet<String> = directed_routes
-        .keys()
-        .map(|(id, _)| id.to_string())
-        .collect();
     let mut results = Vec::new();
-    for route_id in route_ids {
-        let mut stops = directed_routes
-            .remove(&(route_id.clone(), true))
-            .unwrap_or_else(Vec::new);
-        if let Some(more_stops) = directed_routes.remove(&(route_id.clone(), false)) {
-            stops.extend(more_stops);
+    for ((route_id, forwards, stops), mut schedule) in variants {
+        if stops.len() < 2 {
+            continue;
         }
-        assert!(!stops.is_empty());
+        schedule.sort_by_key(|times| times[0]);
         results.push(Route {
             name: route_id_to_name[&route_id].to_string(),
-            stops,
+            forwards,
+            stops: stops.iter().map(|id| stop_id_to_pt[id]).collect(),
+            schedule,
         });
     }
-    assert!(directed_routes.is_empty());
-
     results
 }
+
+// None means there's no calendar, so keep all services.
+fn load_active_services(dir_path: &str) -> Option<HashSet<String>> {
+    let path = format!("{}/calendar.txt", dir_path);
+    if !abstutil::file_exists(path.clone()) {
+        return None;
+    }
+    let mut services = HashSet::new();
+    for rec in read_csv::<HashMap<String, String>>(&path) {
+        if rec.get(SERVICE_DAY).map(|x| x == "1").unwrap_or(false) {
+            services.insert(rec["service_id"].clone());
+        }
+    }
+    Some(services)
+}
+
+// GTFS times can go past midnight for trips that start late, like 25:10:00, so these're kept as
+// strings and parsed here instead of using transitfeed.
+#[derive(Deserialize)]
+struct StopTime {
+    trip_id: String,
+    departure_time: String,
+    stop_id: String,
+    stop_sequence: usize,
+}
+
+#[derive(Deserialize)]
+struct Frequency {
+    trip_id: String,
+    start_time: String,
+    end_time: String,
+    headway_secs: usize,
+}
+
+fn read_csv<T: serde::de::DeserializeOwned>(path: &str) -> Vec<T> {
+    let f = File::open(path).unwrap();
+    csv::Reader::from_reader(f)
+        .deserialize()
+        .map(|rec| rec.unwrap())
+        .collect()
+}
+
+// Only timepoints need a departure time. Fill in the others assuming the bus moves at a constant
+// rate between timepoints. The first and last stop need times, or there's nothing to interpolate
+// between.
+fn interpolate_times(times: Vec<Option<Time>>) -> Option<Vec<Time>> {
+    if times.first()?.is_none() || times.last()?.is_none() {
+        return None;
+    }
+    let known: Vec<usize> = (0..times.len())
+        .filter(|idx| times[*idx].is_some())
+        .collect();
+    let mut results = Vec::new();
+    for pair in known.windows(2) {
+        let (idx1, idx2) = (pair[0], pair[1]);
+        let (t1, t2) = (times[idx1].unwrap(), times[idx2].unwrap());
+        for idx in idx1..idx2 {
+            let pct = ((idx - idx1) as f64) / ((idx2 - idx1) as f64);
+            results.push(t1 + pct * (t2 - t1));
+        }
+    }
+    results.push(times.last().unwrap().unwrap());
+    Some(results)
+}
+
+fn parse_time(x: &str) -> Time {
+    Time::parse(x.trim()).unwrap_or_else(|_| panic!("Bad GTFS time {}", x))
+}
@@ -1,6 +1,16 @@
+mod report;
+
+use crate::report::{ABTestReport, PandemicReport, Report, RunResults, TripPhasesReport};
 use abstutil::{CmdArgs, Timer};
-use geom::Time;
-use sim::{GetDrawAgents, Scenario, SimFlags};
+use geom::{Duration, Time};
+use map_model::{IntersectionID, Map, MapEdits, SignalCorridor, TravelTimes};
+use sim::{
+    ABTest, GetDrawAgents, LogitModeChoice, ModeChoice, Scenario, ScenarioGenerator, Sim, SimFlags,
+};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+const PANDEMIC_SAMPLE_FREQUENCY: Duration = Duration::const_seconds(600.0);
 
 fn main() {
     let mut args = CmdArgs::new();
@@ -10,8 +20,72 @@ fn main() {
     let enable_profiler = args.enabled("--enable_profiler");
     // Every 0.1s, pretend to draw everything to make sure there are no bugs.
     let paranoia = args.enabled("--paranoia");
+    // Batch mode: run the scenario once without edits, then once per comma-separated edits name,
+    // and write the results to a .json or .csv report.
+    let report_path = args.optional("--report");
+    let edits_names: Vec<String> = args
+        .optional("--edits")
+        .map(|x| x.split(',').map(|s| s.to_string()).collect())
+        .unwrap_or_else(Vec::new);
+    // Run both sides of an A/B test (given as a path to its JSON file) and write a diff.
+    let ab_test = args.optional("--ab_test");
+    // In batch mode, coordinate the signals along the shortest driving route between two
+    // intersections, given as "i1,i2". Adds one more run with a green wave on top of the
+    // baseline, and reports how often cars stop along the corridor in every run.
+    let corridor = args.optional_parse("--corridor", |s| {
+        let parts: Vec<&str> = s.split(',').collect();
+        if parts.len() != 2 {
+            return Err(format!("--corridor needs two intersection IDs, not {}", s));
+        }
+        let i1 = parts[0].parse::<usize>().map_err(|err| err.to_string())?;
+        let i2 = parts[1].parse::<usize>().map_err(|err| err.to_string())?;
+        Ok((IntersectionID(i1), IntersectionID(i2)))
+    });
+    // Iterative assignment: run the scenario up to this many times, routing cars each time using
+    // the travel times measured so far, until the average trip time settles down.
+    let assignment_iterations =
+        args.optional_parse("--assignment_iterations", |s| s.parse::<usize>());
+    // In batch, A/B test, and assignment mode, decide again how people get around using the
+    // edited map, instead of keeping the modes from the scenario.
+    let mode_choice = if args.enabled("--mode_choice") {
+        Some(LogitModeChoice::new())
+    } else {
+        None
+    };
+    let mode_choice = mode_choice.as_ref().map(|m| m as &dyn ModeChoice);
+    // After running the simulation, write every finished and aborted trip with a breakdown of how
+    // long each phase took, to a .json or .csv file.
+    let trip_phases_path = args.optional("--trip_phases");
+    // And the path of every phase, to a .geojson file.
+    let trip_paths_path = args.optional("--trip_paths");
+    // With the pandemic model enabled, write how many people are in each state every so often, to
+    // a .json or .csv file.
+    let pandemic_series_path = args.optional("--pandemic_series");
     args.done();
 
+    if let Some(path) = ab_test {
+        run_ab_test(sim_flags, path, report_path, mode_choice);
+        return;
+    }
+    if let Some(n) = assignment_iterations {
+        run_assignment(sim_flags, num_agents, n, mode_choice);
+        return;
+    }
+    if let Some(path) = report_path {
+        run_batch(
+            sim_flags,
+            num_agents,
+            edits_names,
+            corridor,
+            path,
+            mode_choice,
+        );
+        return;
+    }
+    if !edits_names.is_empty() || corridor.is_some() {
+        panic!("--edits and --corridor only make sense with --report");
+    }
+
     let mut timer = Timer::new("setup headless");
     let (map, mut sim, mut rng) = sim_flags.load(&mut timer);
 
@@ -19,11 +93,7 @@ fn main() {
     if sim_flags.load.starts_with(&abstutil::path_all_raw_maps())
         || sim_flags.load.starts_with(&abstutil::path_all_maps())
     {
-        let s = if let Some(n) = num_agents {
-            Scenario::scaled_run(&map, n)
-        } else {
-            Scenario::small_run(&map)
-        };
+        let s = generate_scenario(&map, num_agents, &mut rng, &mut timer);
         s.instantiate(&mut sim, &map, &mut rng, &mut timer);
     }
     timer.done();
@@ -38,10 +108,23 @@ fn main() {
                 .unwrap();
         }
     }
+    let pandemic_series = RefCell::new(PandemicReport::new());
+    let record_pandemic = pandemic_series_path.is_some();
+    let pandemic_series_ref = &pandemic_series;
     let timer = Timer::new("run sim until done");
     sim.run_until_done(
         &map,
         move |sim, map| {
+            if record_pandemic {
+                let mut series = pandemic_series_ref.borrow_mut();
+                if series
+                    .last_time()
+                    .map(|t| sim.time() - t >= PANDEMIC_SAMPLE_FREQUENCY)
+                    .unwrap_or(true)
+                {
+                    series.record(sim);
+                }
+            }
             // TODO We want to savestate at the end of this time; this'll happen at the beginning.
             if Some(sim.time()) == save_at {
                 sim.save();
@@ -61,6 +144,17 @@ fn main() {
     );
     timer.done();
     println!("Done at {}", sim.time());
+    if let Some(path) = trip_phases_path {
+        TripPhasesReport::new(&sim).write(path).unwrap();
+    }
+    if let Some(path) = trip_paths_path {
+        report::write_trip_paths(&sim, &map, path);
+    }
+    if let Some(path) = pandemic_series_path {
+        let mut series = pandemic_series.into_inner();
+        series.record(&sim);
+        series.write(path).unwrap();
+    }
     if enable_profiler && save_at.is_none() {
         #[cfg(feature = "profiler")]
         {
@@ -68,3 +162,281 @@ fn main() {
         }
     }
 }
+
+fn generate_scenario(
+    map: &Map,
+    num_agents: Option<usize>,
+    rng: &mut rand_xorshift::XorShiftRng,
+    timer: &mut Timer,
+) -> Scenario {
+    if let Some(n) = num_agents {
+        ScenarioGenerator::scaled_run(n).generate(map, rng, timer)
+    } else {
+        ScenarioGenerator::small_run(map).generate(map, rng, timer)
+    }
+}
+
+// Either load a scenario directly, or generate one for a map.
+fn load_map_and_scenario(
+    sim_flags: &SimFlags,
+    num_agents: Option<usize>,
+    timer: &mut Timer,
+) -> (Map, Scenario) {
+    if sim_flags.load.starts_with("../data/system/scenarios/") {
+        let scenario: Scenario = abstutil::read_binary(sim_flags.load.clone(), timer);
+        let map = Map::new(abstutil::path_map(&scenario.map_name), false, timer);
+        (map, scenario)
+    } else if sim_flags.load.starts_with(&abstutil::path_all_raw_maps())
+        || sim_flags
+            .load
+            .starts_with(&abstutil::path_all_synthetic_maps())
+        || sim_flags.load.starts_with(&abstutil::path_all_maps())
+    {
+        let map = Map::new(sim_flags.load.clone(), sim_flags.use_map_fixes, timer);
+        let mut rng = sim_flags.make_rng();
+        let scenario = generate_scenario(&map, num_agents, &mut rng, timer);
+        (map, scenario)
+    } else {
+        panic!("Batch mode needs a map or scenario, not {}", sim_flags.load);
+    }
+}
+
+fn run_batch(
+    sim_flags: SimFlags,
+    num_agents: Option<usize>,
+    edits_names: Vec<String>,
+    corridor: Option<(IntersectionID, IntersectionID)>,
+    report_path: String,
+    mode_choice: Option<&dyn ModeChoice>,
+) {
+    let mut timer = Timer::new("run batch");
+    let (mut map, scenario) = load_map_and_scenario(&sim_flags, num_agents, &mut timer);
+
+    let mut report = Report {
+        map_name: map.get_name().to_string(),
+        scenario_name: scenario.scenario_name.clone(),
+        runs: Vec::new(),
+    };
+
+    // Always run the baseline first.
+    let mut all_edits = vec![MapEdits::new(map.get_name())];
+    for name in edits_names {
+        all_edits
+            .push(MapEdits::load(&map, &name, &mut timer).unwrap_or_else(|err| panic!("{}", err)));
+    }
+
+    // Figure out the green wave before any edits are applied to the map.
+    let mut corridor_signals = Vec::new();
+    let mut corridor_turns = BTreeSet::new();
+    if let Some((i1, i2)) = corridor {
+        let corridor = SignalCorridor::between(&map, i1, i2)
+            .unwrap_or_else(|| panic!("Cars can't drive from {} to {}", i1, i2));
+        corridor_signals = corridor.signals(&map).into_iter().map(|(i, _)| i).collect();
+        corridor_turns = corridor.turns(&map);
+        let cmd = corridor.green_wave(&map).unwrap_or_else(|| {
+            panic!(
+                "The corridor from {} to {} needs at least two traffic signals",
+                i1, i2
+            )
+        });
+        let mut edits = MapEdits::new(map.get_name());
+        edits.edits_name = "green wave".to_string();
+        edits.commands.push(cmd);
+        all_edits.push(edits);
+    }
+
+    // Trips belong to the day they start, so the last day might run past midnight.
+    let num_days = scenario
+        .people
+        .iter()
+        .flat_map(|p| p.trips.iter().map(|t| t.depart.get_day() + 1))
+        .max()
+        .unwrap_or(1);
+
+    for edits in all_edits {
+        let edits_name = edits.edits_name.clone();
+        let sim = run_scenario(
+            &mut map,
+            &scenario,
+            edits,
+            &sim_flags,
+            mode_choice,
+            &mut timer,
+        );
+        for day in 0..num_days {
+            let offset = Time::start_of_day(day) - Time::START_OF_DAY;
+            let end_time = if day == num_days - 1 {
+                sim.time() - offset
+            } else {
+                Time::start_of_day(1)
+            };
+            let results = RunResults::new(
+                edits_name.clone(),
+                day,
+                end_time,
+                &sim.get_analytics().for_day(day),
+                &corridor_signals,
+                &corridor_turns,
+            );
+            if let Some(ref c) = results.corridor {
+                println!(
+                    "{} (day {}): {} cars along the corridor stopped {} times per car",
+                    results.edits_name, day, c.num_cars, c.stops_per_car
+                );
+            }
+            report.runs.push(results);
+        }
+    }
+
+    if let Err(err) = report.write(report_path.clone()) {
+        panic!("Couldn't write report {}: {}", report_path, err);
+    }
+}
+
+fn run_ab_test(
+    sim_flags: SimFlags,
+    test_path: String,
+    report_path: Option<String>,
+    mode_choice: Option<&dyn ModeChoice>,
+) {
+    let mut timer = Timer::new("run A/B test");
+    let test: ABTest = abstutil::read_json(test_path, &mut timer);
+    let scenario: Scenario = abstutil::read_binary(
+        abstutil::path_scenario(&test.map_name, &test.scenario_name),
+        &mut timer,
+    );
+    let mut map = Map::new(abstutil::path_map(&test.map_name), false, &mut timer);
+
+    let edits1 =
+        MapEdits::load(&map, &test.edits1_name, &mut timer).unwrap_or_else(|err| panic!("{}", err));
+    let sim1 = run_scenario(
+        &mut map,
+        &scenario,
+        edits1,
+        &sim_flags,
+        mode_choice,
+        &mut timer,
+    );
+    let edits2 =
+        MapEdits::load(&map, &test.edits2_name, &mut timer).unwrap_or_else(|err| panic!("{}", err));
+    let sim2 = run_scenario(
+        &mut map,
+        &scenario,
+        edits2,
+        &sim_flags,
+        mode_choice,
+        &mut timer,
+    );
+
+    let report = ABTestReport::new(
+        test,
+        &map,
+        sim1.time(),
+        sim1.get_analytics(),
+        sim2.time(),
+        sim2.get_analytics(),
+    );
+    for line in report.describe() {
+        println!("{}", line);
+    }
+    abstutil::write_json(
+        report_path.unwrap_or_else(|| {
+            abstutil::path_ab_test_results(&report.test.map_name, &report.test.test_name)
+        }),
+        &report,
+    );
+}
+
+fn run_assignment(
+    sim_flags: SimFlags,
+    num_agents: Option<usize>,
+    max_iterations: usize,
+    mode_choice: Option<&dyn ModeChoice>,
+) {
+    let mut timer = Timer::new("run iterative assignment");
+    let (mut map, scenario) = load_map_and_scenario(&sim_flags, num_agents, &mut timer);
+
+    // Stop once the average trip time changes by less than this fraction
+    let threshold = 0.01;
+    let mut times = TravelTimes::new();
+    let mut prev_avg: Option<Duration> = None;
+    for iteration in 1..=max_iterations {
+        let sim = run_scenario(
+            &mut map,
+            &scenario,
+            MapEdits::new(map.get_name()),
+            &sim_flags,
+            mode_choice,
+            &mut timer,
+        );
+        let analytics = sim.get_analytics();
+        let durations: Vec<Duration> = analytics
+            .finished_trips
+            .iter()
+            .filter_map(|(_, _, mode, dt)| mode.map(|_| *dt))
+            .collect();
+        if durations.is_empty() {
+            println!("Nobody finished a trip, so there's nothing to assign");
+            return;
+        }
+        let avg = durations.iter().cloned().sum::<Duration>() / (durations.len() as f64);
+        println!(
+            "Iteration {}: {} trips finished, taking {} on average",
+            iteration,
+            durations.len(),
+            avg
+        );
+
+        if let Some(prev) = prev_avg {
+            let change = (avg - prev).inner_seconds().abs() / prev.inner_seconds();
+            if change < threshold {
+                println!("Converged after {} iterations", iteration);
+                return;
+            }
+        }
+        prev_avg = Some(avg);
+
+        // The method of successive averages: the nth run only counts for 1/n of the travel
+        // times, so routes settle down instead of everybody jumping to the same shortcut.
+        times.blend(&analytics.observed_travel_times(), 1.0 / (iteration as f64));
+        map.set_observed_travel_times(Some(times.clone()), &mut timer);
+    }
+    println!(
+        "Average trip time didn't converge after {} iterations",
+        max_iterations
+    );
+}
+
+// Applies the edits to the map, then runs the scenario to completion from scratch. The RNG is
+// reset every time, so two runs only differ by their edits.
+fn run_scenario(
+    map: &mut Map,
+    scenario: &Scenario,
+    edits: MapEdits,
+    sim_flags: &SimFlags,
+    mode_choice: Option<&dyn ModeChoice>,
+    timer: &mut Timer,
+) -> Sim {
+    if sim_flags.rng_seed.is_none() {
+        timer.warn(
+            "No --rng_seed specified, so runs with different edits aren't comparable".to_string(),
+        );
+    }
+    let step = format!("run {} with {}", scenario.scenario_name, edits.edits_name);
+    timer.start(&step);
+    map.apply_edits(edits, timer);
+    map.recalculate_pathfinding_after_edits(timer);
+
+    let mut rng = sim_flags.make_rng();
+    let mut sim = Sim::new(map, sim_flags.opts.clone(), timer);
+    if let Some(model) = mode_choice {
+        let mut scenario = scenario.clone();
+        scenario.choose_modes(model, map, &mut rng, timer);
+        scenario.instantiate(&mut sim, map, &mut rng, timer);
+    } else {
+        scenario.instantiate(&mut sim, map, &mut rng, timer);
+    }
+    sim.just_run_until_done(map, None);
+    timer.stop(&step);
+    sim
+}
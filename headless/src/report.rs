@@ -0,0 +1,695 @@
+use geom::{Distance, Duration, Histogram, Statistic, Time};
+use map_model::{IntersectionID, Map, RoadID, TurnID};
+use serde_derive::Serialize;
+use sim::{ABTest, Analytics, PersonID, Sim, TripID, TripMode, TripPhaseType};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{Error, Write};
+
+// Everything a batch run produces, meant to be consumed by scripts, not people.
+#[derive(Serialize)]
+pub struct Report {
+    pub map_name: String,
+    pub scenario_name: String,
+    pub runs: Vec<RunResults>,
+}
+
+// The results of running the scenario once with one set of edits. Multi-day scenarios have
+// separate results for each day.
+#[derive(Serialize)]
+pub struct RunResults {
+    pub edits_name: String,
+    pub day: usize,
+    pub end_time: Time,
+    pub trips: Vec<TripResults>,
+    pub num_aborted: usize,
+    pub per_mode: Vec<ModeSummary>,
+    pub intersection_delays: Vec<IntersectionDelays>,
+    // Only if a corridor was specified
+    pub corridor: Option<CorridorStops>,
+}
+
+#[derive(Serialize)]
+pub struct TripResults {
+    pub trip: TripID,
+    pub mode: TripMode,
+    pub departure: Time,
+    // None means the trip was aborted
+    pub finished: Option<Time>,
+    pub duration: Option<Duration>,
+}
+
+#[derive(Serialize)]
+pub struct ModeSummary {
+    pub mode: TripMode,
+    pub num_finished: usize,
+    pub num_aborted: usize,
+    // These're all None if no trips of this mode finished
+    pub mean_duration: Option<Duration>,
+    pub p50_duration: Option<Duration>,
+    pub p90_duration: Option<Duration>,
+    pub max_duration: Option<Duration>,
+}
+
+#[derive(Serialize)]
+pub struct IntersectionDelays {
+    pub id: IntersectionID,
+    pub count: usize,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub max: Duration,
+}
+
+// How often cars passing through a coordinated corridor had to stop at one of its signals
+#[derive(Serialize)]
+pub struct CorridorStops {
+    pub signals: Vec<IntersectionID>,
+    pub num_cars: usize,
+    pub total_stops: usize,
+    pub stops_per_car: f64,
+}
+
+impl RunResults {
+    // Only looks at finished and aborted trips; call this after the simulation is done.
+    pub fn new(
+        edits_name: String,
+        day: usize,
+        end_time: Time,
+        analytics: &Analytics,
+        corridor_signals: &Vec<IntersectionID>,
+        corridor_turns: &BTreeSet<TurnID>,
+    ) -> RunResults {
+        let mut trips = Vec::new();
+        let mut num_aborted = 0;
+        let mut finished_per_mode: BTreeMap<TripMode, Histogram<Duration>> = TripMode::all()
+            .into_iter()
+            .map(|m| (m, Histogram::new()))
+            .collect();
+        let mut aborted_per_mode: BTreeMap<TripMode, usize> =
+            TripMode::all().into_iter().map(|m| (m, 0)).collect();
+
+        for (t, id, maybe_mode, dt) in &analytics.finished_trips {
+            // Aborted trips still have an entry here.
+            let (departure, mode) = analytics.started_trips[id];
+            if maybe_mode.is_some() {
+                finished_per_mode.get_mut(&mode).unwrap().add(*dt);
+                trips.push(TripResults {
+                    trip: *id,
+                    mode,
+                    departure,
+                    finished: Some(*t),
+                    duration: Some(*dt),
+                });
+            } else {
+                num_aborted += 1;
+                *aborted_per_mode.get_mut(&mode).unwrap() += 1;
+                trips.push(TripResults {
+                    trip: *id,
+                    mode,
+                    departure,
+                    finished: None,
+                    duration: None,
+                });
+            }
+        }
+        trips.sort_by_key(|t| t.trip);
+
+        let per_mode = finished_per_mode
+            .into_iter()
+            .map(|(mode, hgram)| ModeSummary {
+                mode,
+                num_finished: hgram.count(),
+                num_aborted: aborted_per_mode[&mode],
+                mean_duration: maybe_select(&hgram, Statistic::Mean),
+                p50_duration: maybe_select(&hgram, Statistic::P50),
+                p90_duration: maybe_select(&hgram, Statistic::P90),
+                max_duration: maybe_select(&hgram, Statistic::Max),
+            })
+            .collect();
+
+        let mut intersection_delays = Vec::new();
+        for i in analytics.intersection_delays.keys() {
+            let hgram = analytics.intersection_delays(*i, Time::START_OF_DAY, end_time);
+            if hgram.count() == 0 {
+                continue;
+            }
+            intersection_delays.push(IntersectionDelays {
+                id: *i,
+                count: hgram.count(),
+                mean: hgram.select(Statistic::Mean),
+                p50: hgram.select(Statistic::P50),
+                p90: hgram.select(Statistic::P90),
+                max: hgram.select(Statistic::Max),
+            });
+        }
+
+        let corridor = if corridor_signals.is_empty() {
+            None
+        } else {
+            let (num_cars, total_stops) = analytics.corridor_stops(corridor_turns);
+            Some(CorridorStops {
+                signals: corridor_signals.clone(),
+                num_cars,
+                total_stops,
+                stops_per_car: if num_cars == 0 {
+                    0.0
+                } else {
+                    (total_stops as f64) / (num_cars as f64)
+                },
+            })
+        };
+
+        RunResults {
+            edits_name,
+            day,
+            end_time,
+            trips,
+            num_aborted,
+            per_mode,
+            intersection_delays,
+            corridor,
+        }
+    }
+}
+
+impl Report {
+    // Based on the extension, either write everything as one JSON file, or as a few CSV files
+    // next to each other.
+    pub fn write(&self, path: String) -> Result<(), Error> {
+        if path.ends_with(".json") {
+            abstutil::write_json(path, self);
+            return Ok(());
+        }
+        if !path.ends_with(".csv") {
+            panic!("Report {} must end with .json or .csv", path);
+        }
+        let base = path.trim_end_matches(".csv");
+
+        let mut f = File::create(&path)?;
+        writeln!(
+            f,
+            "edits_name,day,trip,mode,departure_seconds,finished_seconds,duration_seconds"
+        )?;
+        for run in &self.runs {
+            for trip in &run.trips {
+                writeln!(
+                    f,
+                    "{},{},{},{:?},{},{},{}",
+                    run.edits_name,
+                    run.day,
+                    trip.trip.0,
+                    trip.mode,
+                    trip.departure.inner_seconds(),
+                    trip.finished
+                        .map(|t| t.inner_seconds().to_string())
+                        .unwrap_or_default(),
+                    trip.duration
+                        .map(|dt| dt.inner_seconds().to_string())
+                        .unwrap_or_default(),
+                )?;
+            }
+        }
+        println!("Wrote {}", path);
+
+        let modes_path = format!("{}_modes.csv", base);
+        let mut f = File::create(&modes_path)?;
+        writeln!(
+            f,
+            "edits_name,day,mode,num_finished,num_aborted,mean_seconds,p50_seconds,p90_seconds,\
+             max_seconds"
+        )?;
+        for run in &self.runs {
+            for m in &run.per_mode {
+                writeln!(
+                    f,
+                    "{},{},{:?},{},{},{},{},{},{}",
+                    run.edits_name,
+                    run.day,
+                    m.mode,
+                    m.num_finished,
+                    m.num_aborted,
+                    csv_duration(m.mean_duration),
+                    csv_duration(m.p50_duration),
+                    csv_duration(m.p90_duration),
+                    csv_duration(m.max_duration),
+                )?;
+            }
+        }
+        println!("Wrote {}", modes_path);
+
+        let intersections_path = format!("{}_intersections.csv", base);
+        let mut f = File::create(&intersections_path)?;
+        writeln!(
+            f,
+            "edits_name,day,intersection,count,mean_seconds,p50_seconds,p90_seconds,max_seconds"
+        )?;
+        for run in &self.runs {
+            for i in &run.intersection_delays {
+                writeln!(
+                    f,
+                    "{},{},{},{},{},{},{},{}",
+                    run.edits_name,
+                    run.day,
+                    i.id.0,
+                    i.count,
+                    i.mean.inner_seconds(),
+                    i.p50.inner_seconds(),
+                    i.p90.inner_seconds(),
+                    i.max.inner_seconds(),
+                )?;
+            }
+        }
+        println!("Wrote {}", intersections_path);
+
+        if self.runs.iter().any(|run| run.corridor.is_some()) {
+            let corridor_path = format!("{}_corridor.csv", base);
+            let mut f = File::create(&corridor_path)?;
+            writeln!(f, "edits_name,day,num_cars,total_stops,stops_per_car")?;
+            for run in &self.runs {
+                if let Some(ref c) = run.corridor {
+                    writeln!(
+                        f,
+                        "{},{},{},{},{}",
+                        run.edits_name, run.day, c.num_cars, c.total_stops, c.stops_per_car
+                    )?;
+                }
+            }
+            println!("Wrote {}", corridor_path);
+        }
+
+        Ok(())
+    }
+}
+
+// Compares the two sides of an A/B test, both run to completion.
+#[derive(Serialize)]
+pub struct ABTestReport {
+    pub test: ABTest,
+    pub end_time1: Time,
+    pub end_time2: Time,
+    // Only trips that finished in both worlds
+    pub trip_deltas: Vec<TripDelta>,
+    // Finished with edits1, but aborted or unfinished with edits2
+    pub only_finished_in_1: Vec<TripID>,
+    // And the opposite
+    pub only_finished_in_2: Vec<TripID>,
+    // Only roads and intersections where the number of agents passing through changed
+    pub road_thruput: Vec<ThruputDelta<RoadID>>,
+    pub intersection_thruput: Vec<ThruputDelta<IntersectionID>>,
+}
+
+#[derive(Serialize)]
+pub struct TripDelta {
+    pub trip: TripID,
+    pub duration1: Duration,
+    pub duration2: Duration,
+    // duration2 - duration1, so negative means the trip got faster with edits2
+    pub delta: Duration,
+}
+
+#[derive(Serialize)]
+pub struct ThruputDelta<ID> {
+    pub id: ID,
+    pub count1: usize,
+    pub count2: usize,
+}
+
+impl ABTestReport {
+    pub fn new(
+        test: ABTest,
+        map: &Map,
+        end_time1: Time,
+        analytics1: &Analytics,
+        end_time2: Time,
+        analytics2: &Analytics,
+    ) -> ABTestReport {
+        let now = end_time1.max(end_time2);
+        let trip_deltas = analytics2
+            .both_finished_trips_by_id(now, analytics1)
+            .into_iter()
+            .map(|(trip, (duration2, duration1))| TripDelta {
+                trip,
+                duration1,
+                duration2,
+                delta: duration2 - duration1,
+            })
+            .collect();
+
+        let mut road_thruput = Vec::new();
+        for r in map.all_roads() {
+            let count1 = analytics1.thruput_stats.count_per_road.get(r.id);
+            let count2 = analytics2.thruput_stats.count_per_road.get(r.id);
+            if count1 != count2 {
+                road_thruput.push(ThruputDelta {
+                    id: r.id,
+                    count1,
+                    count2,
+                });
+            }
+        }
+        let mut intersection_thruput = Vec::new();
+        for i in map.all_intersections() {
+            let count1 = analytics1.thruput_stats.count_per_intersection.get(i.id);
+            let count2 = analytics2.thruput_stats.count_per_intersection.get(i.id);
+            if count1 != count2 {
+                intersection_thruput.push(ThruputDelta {
+                    id: i.id,
+                    count1,
+                    count2,
+                });
+            }
+        }
+
+        ABTestReport {
+            test,
+            end_time1,
+            end_time2,
+            trip_deltas,
+            only_finished_in_1: analytics1.trips_only_finished_here(now, analytics2),
+            only_finished_in_2: analytics2.trips_only_finished_here(now, analytics1),
+            road_thruput,
+            intersection_thruput,
+        }
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut faster = 0;
+        let mut slower = 0;
+        for d in &self.trip_deltas {
+            if d.delta < Duration::ZERO {
+                faster += 1;
+            } else if d.delta > Duration::ZERO {
+                slower += 1;
+            }
+        }
+        vec![
+            format!(
+                "{} vs {}: {} trips finished in both",
+                self.test.edits1_name,
+                self.test.edits2_name,
+                abstutil::prettyprint_usize(self.trip_deltas.len())
+            ),
+            format!(
+                "With {}, {} trips got faster and {} got slower",
+                self.test.edits2_name,
+                abstutil::prettyprint_usize(faster),
+                abstutil::prettyprint_usize(slower)
+            ),
+            format!(
+                "{} trips only finished with {}, {} only with {}",
+                abstutil::prettyprint_usize(self.only_finished_in_1.len()),
+                self.test.edits1_name,
+                abstutil::prettyprint_usize(self.only_finished_in_2.len()),
+                self.test.edits2_name
+            ),
+        ]
+    }
+}
+
+// Every finished and aborted trip, broken down by phase
+#[derive(Serialize)]
+pub struct TripPhasesReport {
+    pub trips: Vec<TripPhaseResults>,
+}
+
+#[derive(Serialize)]
+pub struct TripPhaseResults {
+    pub trip: TripID,
+    pub person: PersonID,
+    pub mode: TripMode,
+    pub departure: Time,
+    // These're None if the trip was aborted
+    pub finished: Option<Time>,
+    pub duration: Option<Duration>,
+    // Time spent stuck behind something or waiting at intersections
+    pub blocked: Option<Duration>,
+    // Total time spent in each type of phase. For aborted trips, up until they were aborted.
+    pub walking: Duration,
+    pub biking: Duration,
+    pub driving: Duration,
+    pub parking: Duration,
+    pub waiting_for_bus: Duration,
+    pub riding_bus: Duration,
+    // Only for trips that parked a car
+    pub cruising_for_parking: Option<Distance>,
+}
+
+impl TripPhasesReport {
+    // Call this after the simulation is done.
+    pub fn new(sim: &Sim) -> TripPhasesReport {
+        let mut all_phases = sim.get_analytics().get_all_trip_phases(true, None);
+        let mut trips = Vec::new();
+        for (t, id, maybe_mode, dt) in &sim.get_analytics().finished_trips {
+            let (departure, _, _, mode) = sim.trip_info(*id);
+            let mut result = TripPhaseResults {
+                trip: *id,
+                person: sim.trip_to_person(*id),
+                mode,
+                departure,
+                finished: None,
+                duration: None,
+                blocked: None,
+                walking: Duration::ZERO,
+                biking: Duration::ZERO,
+                driving: Duration::ZERO,
+                parking: Duration::ZERO,
+                waiting_for_bus: Duration::ZERO,
+                riding_bus: Duration::ZERO,
+                cruising_for_parking: sim.get_analytics().parking_cruising.get(id).cloned(),
+            };
+            if maybe_mode.is_some() {
+                result.finished = Some(*t);
+                result.duration = Some(*dt);
+                result.blocked = sim.finished_trip_time(*id).map(|(_, blocked)| blocked);
+            }
+            for p in all_phases.remove(id).unwrap_or_else(Vec::new) {
+                let dt = p.end_time.unwrap_or(*t) - p.start_time;
+                match p.phase_type {
+                    TripPhaseType::Walking => result.walking += dt,
+                    TripPhaseType::Biking => result.biking += dt,
+                    TripPhaseType::Driving => result.driving += dt,
+                    TripPhaseType::Parking => result.parking += dt,
+                    TripPhaseType::WaitingForBus(_, _) => result.waiting_for_bus += dt,
+                    TripPhaseType::RidingBus(_, _, _) => result.riding_bus += dt,
+                    TripPhaseType::Aborted | TripPhaseType::Finished => {}
+                }
+            }
+            trips.push(result);
+        }
+        trips.sort_by_key(|t| t.trip);
+        TripPhasesReport { trips }
+    }
+
+    // Based on the extension, write JSON or CSV.
+    pub fn write(&self, path: String) -> Result<(), Error> {
+        if path.ends_with(".json") {
+            abstutil::write_json(path, self);
+            return Ok(());
+        }
+        if !path.ends_with(".csv") {
+            panic!("Trip phases {} must end with .json or .csv", path);
+        }
+
+        let mut f = File::create(&path)?;
+        writeln!(
+            f,
+            "trip,person,mode,departure_seconds,finished_seconds,duration_seconds,\
+             blocked_seconds,walking_seconds,biking_seconds,driving_seconds,parking_seconds,\
+             waiting_for_bus_seconds,riding_bus_seconds,cruising_for_parking_meters"
+        )?;
+        for trip in &self.trips {
+            writeln!(
+                f,
+                "{},{},{:?},{},{},{},{},{},{},{},{},{},{},{}",
+                trip.trip.0,
+                trip.person.0,
+                trip.mode,
+                trip.departure.inner_seconds(),
+                trip.finished
+                    .map(|t| t.inner_seconds().to_string())
+                    .unwrap_or_default(),
+                csv_duration(trip.duration),
+                csv_duration(trip.blocked),
+                trip.walking.inner_seconds(),
+                trip.biking.inner_seconds(),
+                trip.driving.inner_seconds(),
+                trip.parking.inner_seconds(),
+                trip.waiting_for_bus.inner_seconds(),
+                trip.riding_bus.inner_seconds(),
+                trip.cruising_for_parking
+                    .map(|d| d.inner_meters().to_string())
+                    .unwrap_or_default(),
+            )?;
+        }
+        println!("Wrote {}", path);
+        Ok(())
+    }
+}
+
+// How many people are in each state of the pandemic model over time
+#[derive(Serialize)]
+pub struct PandemicReport {
+    pub samples: Vec<SEIRCounts>,
+}
+
+#[derive(Serialize)]
+pub struct SEIRCounts {
+    pub time: Time,
+    pub sane: usize,
+    pub exposed: usize,
+    pub infected: usize,
+    pub recovered: usize,
+    pub dead: usize,
+}
+
+impl PandemicReport {
+    pub fn new() -> PandemicReport {
+        PandemicReport {
+            samples: Vec::new(),
+        }
+    }
+
+    // Panics if the simulation doesn't have the pandemic model enabled.
+    pub fn record(&mut self, sim: &Sim) {
+        let model = sim
+            .get_pandemic_model()
+            .expect("Use --pandemic to record the pandemic model");
+        self.samples.push(SEIRCounts {
+            time: sim.time(),
+            sane: model.count_sane(),
+            exposed: model.count_exposed(),
+            infected: model.count_infected(),
+            recovered: model.count_recovered(),
+            dead: model.count_dead(),
+        });
+    }
+
+    pub fn last_time(&self) -> Option<Time> {
+        self.samples.last().map(|s| s.time)
+    }
+
+    // Based on the extension, write JSON or CSV.
+    pub fn write(&self, path: String) -> Result<(), Error> {
+        if path.ends_with(".json") {
+            abstutil::write_json(path, self);
+            return Ok(());
+        }
+        if !path.ends_with(".csv") {
+            panic!("Pandemic series {} must end with .json or .csv", path);
+        }
+
+        let mut f = File::create(&path)?;
+        writeln!(f, "time_seconds,sane,exposed,infected,recovered,dead")?;
+        for s in &self.samples {
+            writeln!(
+                f,
+                "{},{},{},{},{},{}",
+                s.time.inner_seconds(),
+                s.sane,
+                s.exposed,
+                s.infected,
+                s.recovered,
+                s.dead
+            )?;
+        }
+        println!("Wrote {}", path);
+        Ok(())
+    }
+}
+
+// Writes a GeoJSON file with a line for the path of every phase of every finished or aborted trip.
+// Paths are recalculated using the current map, so call this before editing it.
+pub fn write_trip_paths(sim: &Sim, map: &Map, path: String) {
+    let done: BTreeSet<TripID> = sim
+        .get_analytics()
+        .finished_trips
+        .iter()
+        .map(|(_, id, _, _)| *id)
+        .collect();
+    let mut features = Vec::new();
+    for (id, phases) in sim.get_analytics().get_all_trip_phases(false, Some(map)) {
+        if !done.contains(&id) {
+            continue;
+        }
+        for p in phases {
+            if let Some((start_dist, ref trip_path)) = p.path {
+                if let Some(pl) = trip_path.trace(map, start_dist, None) {
+                    features.push(Feature {
+                        feature_type: "Feature".to_string(),
+                        geometry: Geometry {
+                            geometry_type: "LineString".to_string(),
+                            coordinates: pl
+                                .points()
+                                .iter()
+                                .map(|pt| {
+                                    let gps = pt.forcibly_to_gps(map.get_gps_bounds());
+                                    vec![gps.longitude, gps.latitude]
+                                })
+                                .collect(),
+                        },
+                        properties: PathProperties {
+                            trip: id,
+                            person: sim.trip_to_person(id),
+                            phase: p.phase_type.describe(map),
+                            start: p.start_time,
+                            end: p.end_time,
+                        },
+                    });
+                }
+            }
+        }
+    }
+    abstutil::write_json(
+        path,
+        &FeatureCollection {
+            feature_type: "FeatureCollection".to_string(),
+            features,
+        },
+    );
+}
+
+// Just enough of GeoJSON to express paths
+#[derive(Serialize)]
+struct FeatureCollection {
+    #[serde(rename = "type")]
+    feature_type: String,
+    features: Vec<Feature>,
+}
+
+#[derive(Serialize)]
+struct Feature {
+    #[serde(rename = "type")]
+    feature_type: String,
+    geometry: Geometry,
+    properties: PathProperties,
+}
+
+#[derive(Serialize)]
+struct Geometry {
+    #[serde(rename = "type")]
+    geometry_type: String,
+    coordinates: Vec<Vec<f64>>,
+}
+
+#[derive(Serialize)]
+struct PathProperties {
+    trip: TripID,
+    person: PersonID,
+    phase: String,
+    start: Time,
+    end: Option<Time>,
+}
+
+fn maybe_select(hgram: &Histogram<Duration>, stat: Statistic) -> Option<Duration> {
+    if hgram.count() == 0 {
+        None
+    } else {
+        Some(hgram.select(stat))
+    }
+}
+
+fn csv_duration(dt: Option<Duration>) -> String {
+    dt.map(|dt| dt.inner_seconds().to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,94 @@
+use crate::utils::download;
+use map_model::raw::DrivingSide;
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+
+// Everything needed to import the maps of one city, read from data/input/cities/<city>.json.
+// Paths are relative to the importer directory. Besides the downloads, nothing touches the
+// network.
+#[derive(Deserialize)]
+pub struct CityConfig {
+    // An OSM extract covering every map
+    pub osm: String,
+    // A directory of Osmosis polygons. Each one becomes a map, clipped from the OSM extract.
+    pub clipping_polygons: String,
+    pub driving_side: DrivingSide,
+    // A directory with GTFS text files
+    pub gtfs: Option<String>,
+    // An SRTM .hgt file, named after its southwest corner, like N47W122.hgt
+    pub elevation: Option<String>,
+    // GeoJSON polygons with a name property, split between the maps they fall into
+    pub neighborhoods: Option<String>,
+    // These are King County GIS datasets, converted to ExtraShapes. See convert_osm for the
+    // attributes used.
+    pub parking_shapes: Option<String>,
+    pub offstreet_parking: Option<String>,
+    pub sidewalks: Option<String>,
+    // Each map gets one scenario per source when importing with --scenario.
+    #[serde(default)]
+    pub demand: Vec<DemandSource>,
+    // Input files to fetch from URLs when they're missing. Leave this out to work offline.
+    #[serde(default)]
+    pub downloads: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+pub enum DemandSource {
+    // Soundcast outputs from PSRC. Only covers the Puget Sound.
+    PSRC,
+    // A .csv or .geojson file of individual trips between lon/lat points. See od.rs.
+    Trips(String),
+    // An origin-destination matrix: the centroid of each zone, then trip counts between them
+    ODMatrix { zones: String, matrix: String },
+}
+
+impl CityConfig {
+    pub fn load(city: &str) -> CityConfig {
+        abstutil::read_json(
+            format!("../data/input/cities/{}.json", city),
+            &mut abstutil::Timer::throwaway(),
+        )
+    }
+
+    // Download anything missing, then make sure every input exists before spending time on the
+    // import.
+    pub fn check_input(&self) {
+        for (output, url) in &self.downloads {
+            download(output, url);
+        }
+
+        let mut paths = vec![&self.osm, &self.clipping_polygons];
+        for path in &[
+            &self.gtfs,
+            &self.elevation,
+            &self.neighborhoods,
+            &self.parking_shapes,
+            &self.offstreet_parking,
+            &self.sidewalks,
+        ] {
+            if let Some(path) = path {
+                paths.push(path);
+            }
+        }
+        for demand in &self.demand {
+            match demand {
+                DemandSource::PSRC => {}
+                DemandSource::Trips(path) => {
+                    paths.push(path);
+                }
+                DemandSource::ODMatrix { zones, matrix } => {
+                    paths.push(zones);
+                    paths.push(matrix);
+                }
+            }
+        }
+        for path in paths {
+            if !abstutil::file_exists(path.to_string()) {
+                panic!(
+                    "{} is missing. Add it to downloads, or get it some other way",
+                    path
+                );
+            }
+        }
+    }
+}
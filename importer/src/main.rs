@@ -1,11 +1,21 @@
+mod config;
+mod neighborhoods;
+mod od;
 mod psrc;
 mod seattle;
 mod utils;
 
+use config::{CityConfig, DemandSource};
+
 struct Job {
+    city: String,
     osm_to_raw: bool,
     raw_to_map: bool,
     scenario: bool,
+    // Paths to demand in generic formats, instead of PSRC's
+    trips: Option<String>,
+    od_zones_csv: Option<String>,
+    od_matrix_csv: Option<String>,
 
     use_fixes: bool,
     only_map: Option<String>,
@@ -14,36 +24,98 @@ struct Job {
 fn main() {
     let mut args = abstutil::CmdArgs::new();
     let job = Job {
-        // Download all raw input files, then convert OSM to the intermediate RawMap.
+        // Which city in data/input/cities/ to import
+        city: args
+            .optional("--city")
+            .unwrap_or_else(|| "seattle".to_string()),
+        // Download any missing raw input files, then convert OSM to the intermediate RawMap.
         osm_to_raw: args.enabled("--raw"),
         // Convert the RawMap to the final Map format.
         raw_to_map: args.enabled("--map"),
-        // Download trip demand data, then produce the typical weekday scenario.
+        // Produce a scenario from each of the city's demand sources.
         scenario: args.enabled("--scenario"),
+        // Produce a scenario from a .csv or .geojson file of individual trips between lon/lat
+        // points.
+        trips: args.optional("--trips"),
+        // Produce a scenario from an origin-destination matrix. Both of these are needed.
+        od_zones_csv: args.optional("--od_zones_csv"),
+        od_matrix_csv: args.optional("--od_matrix_csv"),
 
         // By default, use geometry fixes from map_editor.
         use_fixes: !args.enabled("--nofixes"),
-        // Only process one map. If not specified, process all maps defined by clipping polygons in
-        // data/input/polygons/.
+        // Only process one map. If not specified, process all maps defined by the city's clipping
+        // polygons.
         only_map: args.optional_free(),
     };
+    // Instead of importing anything, write the trips of this scenario to a .csv or .geojson file.
+    let export_scenario = args.optional("--export_scenario");
+    // Instead of importing anything, write an osmChange file proposing the map_editor fixes, and
+    // optionally some edits, to OSM. Pass --output and one map name.
+    let osm_change = args.enabled("--osm_change");
+    let edits = args.optional("--edits");
+    // Instead of importing anything, save neighborhood polygons from a GeoJSON file with a name
+    // property for each feature, or write all of them to a GeoJSON file, summarizing what's
+    // inside. --residents is an optional scenario name used to count the people living in each.
+    let import_neighborhoods = args.optional("--import_neighborhoods");
+    let export_neighborhoods = args.optional("--export_neighborhoods");
+    let residents = args.optional("--residents");
+    let output = args.optional("--output");
     args.done();
-    if !job.osm_to_raw && !job.raw_to_map && !job.scenario {
-        println!("Nothing to do! Pass some combination of --raw, --map, --scenario");
+    if let Some(path) = export_scenario {
+        let output = output.expect("--export_scenario needs --output");
+        let mut timer = abstutil::Timer::new(format!("Exporting {}", path));
+        let scenario: sim::Scenario = abstutil::read_binary(path, &mut timer);
+        let map = map_model::Map::new(abstutil::path_map(&scenario.map_name), false, &mut timer);
+        popdat::write_trips(&popdat::scenario_to_rows(&scenario, &map), output).unwrap();
+        return;
+    }
+    if osm_change {
+        let name = job.only_map.expect("--osm_change needs a map name");
+        utils::osm_change(&name, edits, &output.expect("--osm_change needs --output"));
+        return;
+    }
+    if import_neighborhoods.is_some() || export_neighborhoods.is_some() {
+        let name = job
+            .only_map
+            .expect("--import_neighborhoods and --export_neighborhoods need a map name");
+        let mut timer = abstutil::Timer::new(format!("Neighborhoods for {}", name));
+        let map = map_model::Map::new(abstutil::path_map(&name), false, &mut timer);
+        if let Some(path) = import_neighborhoods {
+            convert_osm::neighborhoods::convert(path, name.clone(), map.get_gps_bounds());
+        }
+        if let Some(path) = export_neighborhoods {
+            let scenario: Option<sim::Scenario> = residents.map(|scenario_name| {
+                abstutil::read_binary(abstutil::path_scenario(&name, &scenario_name), &mut timer)
+            });
+            neighborhoods::export(&map, scenario.as_ref(), path, &mut timer);
+        }
+        return;
+    }
+    let custom_demand = job.trips.is_some() || job.od_matrix_csv.is_some();
+    if !job.osm_to_raw && !job.raw_to_map && !job.scenario && !custom_demand {
+        println!(
+            "Nothing to do! Pass some combination of --raw, --map, --scenario, --trips, \
+             --od_matrix_csv"
+        );
+        std::process::exit(1);
+    }
+    if job.od_matrix_csv.is_some() != job.od_zones_csv.is_some() {
+        println!("--od_matrix_csv and --od_zones_csv must be used together");
         std::process::exit(1);
     }
 
+    let city = CityConfig::load(&job.city);
     let names = if let Some(n) = job.only_map {
         println!("- Just working on {}", n);
         vec![n]
     } else {
-        println!("- Working on all Seattle maps");
-        abstutil::list_all_objects("../data/input/polygons".to_string())
+        println!("- Working on all {} maps", job.city);
+        abstutil::list_all_objects(city.clipping_polygons.clone())
     };
 
     for name in names {
         if job.osm_to_raw {
-            seattle::osm_to_raw(&name);
+            utils::osm_to_raw(&name, &city);
         }
 
         if job.raw_to_map {
@@ -51,11 +123,39 @@ fn main() {
         }
 
         if job.scenario {
-            seattle::ensure_popdat_exists(job.use_fixes);
-
             let mut timer = abstutil::Timer::new(format!("Scenario for {}", name));
             let map = map_model::Map::new(abstutil::path_map(&name), job.use_fixes, &mut timer);
-            popdat::trips_to_scenario(&map, &mut timer).save();
+            for demand in &city.demand {
+                match demand {
+                    DemandSource::PSRC => {
+                        seattle::ensure_popdat_exists(&city, job.use_fixes);
+                        popdat::trips_to_scenario(&map, &mut timer).save();
+                    }
+                    DemandSource::Trips(path) => {
+                        od::import_trips(&map, path, &abstutil::basename(path), &mut timer);
+                    }
+                    DemandSource::ODMatrix { zones, matrix } => {
+                        od::import_od_matrix(
+                            &map,
+                            zones,
+                            matrix,
+                            &abstutil::basename(matrix),
+                            &mut timer,
+                        );
+                    }
+                }
+            }
+        }
+
+        if custom_demand {
+            let mut timer = abstutil::Timer::new(format!("Importing demand for {}", name));
+            let map = map_model::Map::new(abstutil::path_map(&name), job.use_fixes, &mut timer);
+            if let Some(ref path) = job.trips {
+                od::import_trips(&map, path, &abstutil::basename(path), &mut timer);
+            }
+            if let (Some(zones), Some(matrix)) = (&job.od_zones_csv, &job.od_matrix_csv) {
+                od::import_od_matrix(&map, zones, matrix, &abstutil::basename(matrix), &mut timer);
+            }
         }
     }
 }
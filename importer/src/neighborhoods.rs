@@ -0,0 +1,112 @@
+use abstutil::Timer;
+use map_model::{FullNeighborhoodInfo, LaneType, Map, NeighborhoodBuilder};
+use serde_derive::Serialize;
+use sim::Scenario;
+use std::collections::BTreeMap;
+
+// Every neighborhood of a map as a GeoJSON polygon, summarizing what's inside for area-based
+// reports. The file can be imported again with convert_osm::neighborhoods::convert.
+pub fn export(map: &Map, scenario: Option<&Scenario>, path: String, timer: &mut Timer) {
+    let infos = FullNeighborhoodInfo::load_all(map);
+
+    // Somebody lives wherever their first trip of the day starts.
+    let mut residents_per_bldg: BTreeMap<_, usize> = BTreeMap::new();
+    if let Some(scenario) = scenario {
+        for person in &scenario.people {
+            if let Some(b) = person.trips.get(0).and_then(|t| t.trip.start_building()) {
+                *residents_per_bldg.entry(b).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut features = Vec::new();
+    for (name, builder) in abstutil::load_all_objects::<NeighborhoodBuilder>(
+        abstutil::path_all_neighborhoods(map.get_name()),
+    ) {
+        let info = &infos[&name];
+        let mut lane_km: BTreeMap<LaneType, f64> = BTreeMap::new();
+        for r in &info.roads {
+            let r = map.get_r(*r);
+            for (l, lt) in r
+                .children_forwards
+                .iter()
+                .chain(r.children_backwards.iter())
+            {
+                *lane_km.entry(*lt).or_insert(0.0) +=
+                    map.get_l(*l).length().inner_meters() / 1000.0;
+            }
+        }
+
+        let mut ring: Vec<Vec<f64>> = builder
+            .points
+            .iter()
+            .map(|pt| vec![pt.longitude, pt.latitude])
+            .collect();
+        ring.push(ring[0].clone());
+        features.push(Feature {
+            feature_type: "Feature".to_string(),
+            geometry: Geometry {
+                geometry_type: "Polygon".to_string(),
+                coordinates: vec![ring],
+            },
+            properties: NeighborhoodSummary {
+                name,
+                buildings: info.buildings.len(),
+                roads: info.roads.len(),
+                lane_km: lane_km
+                    .into_iter()
+                    .map(|(lt, km)| (format!("{:?}", lt), km))
+                    .collect(),
+                residents: scenario.map(|_| {
+                    info.buildings
+                        .iter()
+                        .map(|b| residents_per_bldg.get(b).cloned().unwrap_or(0))
+                        .sum()
+                }),
+            },
+        });
+    }
+    timer.note(format!("Exporting {} neighborhoods", features.len()));
+    abstutil::write_json(
+        path,
+        &FeatureCollection {
+            feature_type: "FeatureCollection".to_string(),
+            features,
+        },
+    );
+}
+
+#[derive(Serialize)]
+struct NeighborhoodSummary {
+    name: String,
+    buildings: usize,
+    roads: usize,
+    // Total length of all lanes of each type, in kilometers
+    lane_km: BTreeMap<String, f64>,
+    // Only present if a scenario was used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    residents: Option<usize>,
+}
+
+// Just enough of GeoJSON to express neighborhoods
+#[derive(Serialize)]
+struct FeatureCollection {
+    #[serde(rename = "type")]
+    feature_type: String,
+    features: Vec<Feature>,
+}
+
+#[derive(Serialize)]
+struct Feature {
+    #[serde(rename = "type")]
+    feature_type: String,
+    geometry: Geometry,
+    properties: NeighborhoodSummary,
+}
+
+#[derive(Serialize)]
+struct Geometry {
+    #[serde(rename = "type")]
+    geometry_type: String,
+    coordinates: Vec<Vec<Vec<f64>>>,
+}
@@ -0,0 +1,99 @@
+use abstutil::{FileWithProgress, Timer};
+use geom::{LonLat, Time};
+use map_model::Map;
+use popdat::{ODBucket, TripRow};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+
+// Demand from generic CSV files, for cities without Soundcast output.
+
+// One row per trip, as a CSV or GeoJSON file in the format popdat::write_trips produces. Only
+// origin_lon, origin_lat, destination_lon, destination_lat, departure (HH:MM:SS), and mode (walk,
+// bike, transit, or drive) are required.
+pub fn import_trips(map: &Map, path: &str, scenario_name: &str, timer: &mut Timer) {
+    let rows = if path.ends_with(".geojson") {
+        popdat::read_geojson_trips(path.to_string(), timer)
+    } else {
+        read_trips_csv(path, timer).unwrap()
+    };
+    let people = popdat::rows_to_people(rows).unwrap_or_else(|err| panic!("{}: {}", path, err));
+    popdat::external_people_to_scenario(scenario_name, people, map, timer).save();
+}
+
+// One CSV with the centroid of each zone (zone, lon, lat), and another with rows of origin zone,
+// destination zone, mode, start and end of the departure window (HH:MM:SS), and number of trips.
+pub fn import_od_matrix(
+    map: &Map,
+    zones_path: &str,
+    matrix_path: &str,
+    scenario_name: &str,
+    timer: &mut Timer,
+) {
+    let (zones, buckets) = read_od_matrix(zones_path, matrix_path, timer).unwrap();
+    let mut rng = XorShiftRng::from_seed([42; 16]);
+    popdat::od_to_scenario(scenario_name, &zones, buckets, map, &mut rng, timer).save();
+}
+
+fn read_trips_csv(path: &str, timer: &mut Timer) -> Result<Vec<TripRow>, failure::Error> {
+    let mut rows = Vec::new();
+    let (reader, done) = FileWithProgress::new(path)?;
+    for rec in csv::Reader::from_reader(reader).deserialize() {
+        rows.push(rec?);
+    }
+    done(timer);
+    Ok(rows)
+}
+
+fn read_od_matrix(
+    zones_path: &str,
+    matrix_path: &str,
+    timer: &mut Timer,
+) -> Result<(BTreeMap<String, LonLat>, Vec<ODBucket>), failure::Error> {
+    let mut zones = BTreeMap::new();
+    let (reader, done) = FileWithProgress::new(zones_path)?;
+    for rec in csv::Reader::from_reader(reader).deserialize() {
+        let rec: RawZone = rec?;
+        zones.insert(rec.zone, LonLat::new(rec.lon, rec.lat));
+    }
+    done(timer);
+
+    let mut buckets = Vec::new();
+    let (reader, done) = FileWithProgress::new(matrix_path)?;
+    for rec in csv::Reader::from_reader(reader).deserialize() {
+        let rec: RawODBucket = rec?;
+        buckets.push(ODBucket {
+            from_zone: rec.origin,
+            to_zone: rec.destination,
+            mode: popdat::parse_mode(&rec.mode).map_err(failure::err_msg)?,
+            start: parse_time(&rec.start)?,
+            end: parse_time(&rec.end)?,
+            num_trips: rec.trips,
+        });
+    }
+    done(timer);
+
+    Ok((zones, buckets))
+}
+
+fn parse_time(x: &str) -> Result<Time, failure::Error> {
+    Time::parse(x.trim()).map_err(|_| failure::err_msg(format!("Bad time {}", x)))
+}
+
+#[derive(Debug, Deserialize)]
+struct RawZone {
+    zone: String,
+    lon: f64,
+    lat: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawODBucket {
+    origin: String,
+    destination: String,
+    mode: String,
+    start: String,
+    end: String,
+    trips: usize,
+}
@@ -1,3 +1,6 @@
+use crate::config::CityConfig;
+use map_model::raw::{DrivingSide, MapFixes, RawMap};
+use map_model::{Map, MapEdits};
 use std::path::Path;
 use std::process::Command;
 
@@ -86,6 +89,70 @@ fn run(cmd: &mut Command) {
     }
 }
 
+// Raw input -> RawMap for one map of a city
+pub fn osm_to_raw(name: &str, city: &CityConfig) {
+    city.check_input();
+    let clip = format!("{}/{}.poly", city.clipping_polygons, name);
+    osmconvert(
+        &city.osm,
+        clip.clone(),
+        format!("../data/input/osm/{}.osm", name),
+    );
+    rm(format!("../data/input/neighborhoods/{}", name));
+    rm(format!("../data/system/maps/{}.bin", name));
+
+    println!("- Running convert_osm");
+    let output = format!("../data/input/raw_maps/{}.bin", name);
+    let map = convert_osm::convert(
+        &convert_osm::Flags {
+            osm: format!("../data/input/osm/{}.osm", name),
+            parking_shapes: city.parking_shapes.clone(),
+            offstreet_parking: city.offstreet_parking.clone(),
+            sidewalks: city.sidewalks.clone(),
+            gtfs: city.gtfs.clone(),
+            neighborhoods: city.neighborhoods.clone(),
+            elevation: city.elevation.clone(),
+            clip: Some(clip),
+            drive_on_right: city.driving_side == DrivingSide::Right,
+            output: output.clone(),
+        },
+        &mut abstutil::Timer::throwaway(),
+    );
+    println!("- Saving {}", output);
+    abstutil::write_binary(output, &map);
+}
+
+// Propose the map_editor fixes and maybe some edits to OSM, comparing against the local extract
+// this map was imported from.
+pub fn osm_change(name: &str, edits_name: Option<String>, output: &str) {
+    let mut timer = abstutil::Timer::new(format!("osmChange for {}", name));
+    let raw: RawMap = abstutil::read_binary(abstutil::path_raw_map(name), &mut timer);
+    let mut change = convert_osm::OsmChange::new();
+    let mut fixes_names = vec!["huge_seattle"];
+    if name != "huge_seattle" {
+        fixes_names.push(name);
+    }
+    for fixes_name in fixes_names {
+        if let Ok(fixes) =
+            abstutil::maybe_read_json::<MapFixes>(abstutil::path_fixes(fixes_name), &mut timer)
+        {
+            change.add_fixes(&raw, &fixes, &mut timer);
+        }
+    }
+    if let Some(edits_name) = edits_name {
+        let mut map = Map::new(abstutil::path_map(name), false, &mut timer);
+        let edits =
+            MapEdits::load(&map, &edits_name, &mut timer).unwrap_or_else(|err| panic!("{}", err));
+        map.apply_edits(edits, &mut timer);
+        change.add_edits(&map, &mut timer);
+    }
+    change.write(
+        &format!("../data/input/osm/{}.osm", name),
+        output,
+        &mut timer,
+    );
+}
+
 // Converts a RawMap to a Map.
 pub fn raw_to_map(name: &str, use_fixes: bool) {
     let mut timer = abstutil::Timer::new(format!("Raw->Map for {}", name));
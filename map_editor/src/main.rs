@@ -1,5 +1,4 @@
 mod model;
-mod upstream;
 mod world;
 
 use abstutil::{CmdArgs, Timer};
@@ -89,7 +88,7 @@ impl UI {
                             (None, "save raw map"),
                             (hotkey(Key::F), "save map fixes"),
                             (hotkey(Key::J), "warp to something"),
-                            (None, "produce OSM parking+sidewalk diff"),
+                            (None, "produce osmChange of fixes"),
                             (hotkey(Key::G), "preview all intersections"),
                             (None, "find overlapping intersections"),
                             (hotkey(Key::Z), "find/clear short roads"),
@@ -217,7 +216,8 @@ impl GUI for UI {
                     }
                     Some(ID::Road(r)) => {
                         let could_swap = {
-                            let lanes = self.model.map.roads[&r].get_spec();
+                            let lanes =
+                                self.model.map.roads[&r].get_spec(self.model.map.driving_side);
                             lanes.fwd != lanes.back
                         };
 
@@ -252,7 +252,7 @@ impl GUI for UI {
                         {
                             let road = &self.model.map.roads[&r];
                             self.state = State::StampingRoads(
-                                road.get_spec().to_string(),
+                                road.get_spec(self.model.map.driving_side).to_string(),
                                 road.osm_tags
                                     .get(osm::NAME)
                                     .cloned()
@@ -316,8 +316,8 @@ impl GUI for UI {
                                 "warp to something" => {
                                     self.state = State::EnteringWarp(Wizard::new());
                                 }
-                                "produce OSM parking+sidewalk diff" => {
-                                    upstream::find_diffs(&self.model.map);
+                                "produce osmChange of fixes" => {
+                                    self.model.export_osm_change();
                                 }
                                 "preview all intersections" => {
                                     if !self.model.intersection_geom {
@@ -401,7 +401,9 @@ impl GUI for UI {
             State::EditingLanes(id, ref mut wizard) => {
                 if let Some(s) = wizard.wrap(ctx).input_string_prefilled(
                     "Specify the lanes",
-                    self.model.map.roads[&id].get_spec().to_string(),
+                    self.model.map.roads[&id]
+                        .get_spec(self.model.map.driving_side)
+                        .to_string(),
                 ) {
                     self.model.edit_lanes(id, s, ctx.prerender);
                     self.state = State::viewing();
@@ -1,5 +1,7 @@
 use crate::world::{Object, ObjectID, World};
 use abstutil::Timer;
+#[cfg(not(target_arch = "wasm32"))]
+use convert_osm::OsmChange;
 use ezgui::{Color, Line, Prerender, Text};
 use geom::{Bounds, Circle, Distance, FindClosest, GPSBounds, PolyLine, Polygon, Pt2D};
 use map_model::raw::{
@@ -137,6 +139,24 @@ impl Model {
         );
     }
 
+    #[cfg(target_arch = "wasm32")]
+    pub fn export_osm_change(&self) {}
+
+    // Compares against the local OSM extract this map was imported from.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_osm_change(&self) {
+        let mut timer = Timer::new("produce osmChange");
+        let orig: RawMap =
+            abstutil::read_binary(abstutil::path_raw_map(&self.map.name), &mut timer);
+        let mut change = OsmChange::new();
+        change.add_fixes(&orig, &self.map.generate_fixes(&mut timer), &mut timer);
+        change.write(
+            &format!("../data/input/osm/{}.osm", self.map.name),
+            "diff.osc",
+            &mut timer,
+        );
+    }
+
     fn compute_bounds(&self) -> Bounds {
         let mut bounds = Bounds::new();
         for b in self.map.buildings.values() {
@@ -259,6 +279,7 @@ impl Model {
             IntersectionType::StopSign => Color::RED,
             IntersectionType::Border => Color::BLUE,
             IntersectionType::Construction => Color::ORANGE,
+            IntersectionType::Roundabout => Color::PURPLE,
         };
 
         let poly = if self.intersection_geom && !self.map.roads_per_intersection(id).is_empty() {
@@ -310,6 +331,8 @@ impl Model {
                 }
             }
             IntersectionType::Border => IntersectionType::StopSign,
+            // Roundabouts only come from OSM tags; toggling one joins the cycle above.
+            IntersectionType::Roundabout => IntersectionType::StopSign,
             // These shouldn't exist in a basemap!
             IntersectionType::Construction => unreachable!(),
         };
@@ -419,8 +442,9 @@ impl Model {
         self.road_deleted(id);
 
         let (mut lanes, osm_tags) = {
+            let driving_side = self.map.driving_side;
             let r = self.map.roads.get_mut(&id).unwrap();
-            (r.get_spec(), &mut r.osm_tags)
+            (r.get_spec(driving_side), &mut r.osm_tags)
         };
         mem::swap(&mut lanes.fwd, &mut lanes.back);
         osm_tags.insert(osm::SYNTHETIC_LANES.to_string(), lanes.to_string());
@@ -583,7 +607,7 @@ impl Model {
             r.synthetic() && r.osm_tags.get(osm::NAME) == Some(&"Streety McStreetFace".to_string());
         let lanes_unknown = r.osm_tags.contains_key(osm::INFERRED_PARKING)
             || r.osm_tags.contains_key(osm::INFERRED_SIDEWALKS);
-        let spec = r.get_spec();
+        let spec = r.get_spec(self.map.driving_side);
         let center_pts = PolyLine::new(r.center_points.clone());
 
         let mut obj = Object::blank(ID::Road(id));
@@ -1,4 +1,4 @@
-use crate::{osm, LaneID, Map, Position};
+use crate::{osm, LaneID, Map, ParkingRules, Position};
 use geom::{Line, Polygon, Pt2D};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
@@ -29,6 +29,7 @@ pub struct OffstreetParking {
     pub driveway_line: Line,
     // Guaranteed to be at least 7m before the end of the lane
     pub driving_pos: Position,
+    pub rules: ParkingRules,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
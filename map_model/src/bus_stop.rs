@@ -1,4 +1,5 @@
 use crate::{LaneID, Position};
+use geom::Time;
 use serde_derive::{Deserialize, Serialize};
 use std::fmt;
 
@@ -39,4 +40,30 @@ pub struct BusRoute {
     pub id: BusRouteID,
     pub name: String,
     pub stops: Vec<BusStopID>,
+    // If this is empty, a fixed fleet of buses loops around the stops forever. Otherwise, each
+    // entry is one scheduled trip, with the time the bus should depart from every stop. The bus
+    // starts at the first stop and vanishes after the last.
+    pub schedule: Vec<Vec<Time>>,
+}
+
+impl BusRoute {
+    pub fn is_scheduled(&self) -> bool {
+        !self.schedule.is_empty()
+    }
+
+    // Only keeps the stops where the mask is true, keeping the schedule consistent.
+    pub(crate) fn retain_stops(&mut self, keep: &Vec<bool>) {
+        assert_eq!(keep.len(), self.stops.len());
+        self.stops = apply_mask(std::mem::replace(&mut self.stops, Vec::new()), keep);
+        for times in self.schedule.iter_mut() {
+            *times = apply_mask(std::mem::replace(times, Vec::new()), keep);
+        }
+    }
+}
+
+pub(crate) fn apply_mask<T>(list: Vec<T>, keep: &Vec<bool>) -> Vec<T> {
+    list.into_iter()
+        .zip(keep.iter())
+        .filter_map(|(x, ok)| if *ok { Some(x) } else { None })
+        .collect()
 }
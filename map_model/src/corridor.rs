@@ -0,0 +1,172 @@
+use crate::{
+    DirectedRoadID, EditCmd, EditIntersection, IntersectionID, Map, PathConstraints, TurnGroupID,
+    TurnID,
+};
+use geom::Duration;
+use petgraph::graphmap::DiGraphMap;
+use std::collections::BTreeSet;
+
+// A chain of roads along an arterial, in the direction of travel. The traffic signals along it can
+// be coordinated, so that somebody driving at the speed limit hits a green wave.
+#[derive(Clone, Debug)]
+pub struct SignalCorridor {
+    pub roads: Vec<DirectedRoadID>,
+}
+
+impl SignalCorridor {
+    // The shortest chain of roads that cars can use between two intersections. Ignores turn
+    // restrictions.
+    pub fn between(map: &Map, from: IntersectionID, to: IntersectionID) -> Option<SignalCorridor> {
+        let mut graph: DiGraphMap<IntersectionID, DirectedRoadID> = DiGraphMap::new();
+        for r in map.all_roads() {
+            for fwds in vec![true, false] {
+                let dr = DirectedRoadID {
+                    id: r.id,
+                    forwards: fwds,
+                };
+                if dr.lanes(PathConstraints::Car, map).is_empty() {
+                    continue;
+                }
+                // If there are parallel roads between two intersections, keep the shorter one.
+                let (i1, i2) = (dr.src_i(map), dr.dst_i(map));
+                if let Some(other) = graph.edge_weight(i1, i2) {
+                    if map.get_r(other.id).center_pts.length() <= r.center_pts.length() {
+                        continue;
+                    }
+                }
+                graph.add_edge(i1, i2, dr);
+            }
+        }
+        let (_, path) = petgraph::algo::astar(
+            &graph,
+            from,
+            |i| i == to,
+            |(_, _, dr)| map.get_r(dr.id).center_pts.length().inner_meters(),
+            |_| 0.0,
+        )?;
+        if path.len() < 2 {
+            return None;
+        }
+        Some(SignalCorridor {
+            roads: path
+                .windows(2)
+                .map(|pair| *graph.edge_weight(pair[0], pair[1]).unwrap())
+                .collect(),
+        })
+    }
+
+    // Every traffic signal along the corridor, including the two ends, in order, with how long it
+    // takes to get there from the first one at the speed limit.
+    pub fn signals(&self, map: &Map) -> Vec<(IntersectionID, Duration)> {
+        let mut results = Vec::new();
+        let first = self.roads[0].src_i(map);
+        if map.get_i(first).is_traffic_signal() {
+            results.push((first, Duration::ZERO));
+        }
+        let mut time = Duration::ZERO;
+        for dr in &self.roads {
+            // Start counting after the first signal
+            if !results.is_empty() {
+                let r = map.get_r(dr.id);
+                time += r.center_pts.length() / r.get_speed_limit();
+            }
+            let i = dr.dst_i(map);
+            if map.get_i(i).is_traffic_signal() {
+                results.push((i, time));
+            }
+        }
+        results
+    }
+
+    // Every turn at the corridor's signals that follows the corridor. At the first signal, that's
+    // anything turning onto the corridor; at the last, anything leaving it.
+    pub fn turns(&self, map: &Map) -> BTreeSet<TurnID> {
+        let mut turns = BTreeSet::new();
+        for (i, _) in self.signals(map) {
+            let signal = map.get_traffic_signal(i);
+            for g in self.turn_groups(i, map) {
+                turns.extend(signal.turn_groups[&g].members.iter().cloned());
+            }
+        }
+        turns
+    }
+
+    // Changes the offsets of every signal after the first, so that the phase serving the corridor
+    // turns green just as vehicles from the previous signal arrive. This works best if the signals
+    // share the same cycle length. Returns one batch of edits, or None if there aren't at least
+    // two signals.
+    pub fn green_wave(&self, map: &Map) -> Option<EditCmd> {
+        let signals = self.signals(map);
+        if signals.len() < 2 {
+            return None;
+        }
+
+        // When does the corridor's phase start at the first signal, relative to the cycle?
+        let (first_i, _) = signals[0];
+        let first = map.get_traffic_signal(first_i);
+        let first_start = self.phase_start(first_i, map) - first.offset;
+
+        let mut cmds = Vec::new();
+        for (i, arrival) in signals.into_iter().skip(1) {
+            let mut signal = map.get_traffic_signal(i).clone();
+            let cycle = signal.cycle_length();
+            let raw = self.phase_start(i, map) - first_start - arrival;
+            signal.offset = ((raw % cycle) + cycle) % cycle;
+            cmds.push(EditCmd::ChangeIntersection {
+                i,
+                old: map.get_i_edit(i),
+                new: EditIntersection::TrafficSignal(signal),
+            });
+        }
+        Some(EditCmd::Batch(cmds))
+    }
+
+    // The movements at a signal along the corridor that keep following it.
+    fn turn_groups(&self, i: IntersectionID, map: &Map) -> Vec<TurnGroupID> {
+        let signal = map.get_traffic_signal(i);
+        let from = self.roads.iter().find(|dr| dr.dst_i(map) == i).cloned();
+        let to = self.roads.iter().find(|dr| dr.src_i(map) == i).cloned();
+        if let (Some(from), Some(to)) = (from, to) {
+            let g = TurnGroupID {
+                from,
+                to,
+                parent: i,
+                crosswalk: false,
+            };
+            if signal.turn_groups.contains_key(&g) {
+                return vec![g];
+            }
+        }
+        // The corridor ends here or the movement is missing, so anything coming from the corridor
+        // will do. At the start, anything turning onto it.
+        signal
+            .turn_groups
+            .keys()
+            .filter(|g| {
+                !g.crosswalk
+                    && if from.is_some() {
+                        Some(g.from) == from
+                    } else {
+                        Some(g.to) == to
+                    }
+            })
+            .cloned()
+            .collect()
+    }
+
+    // How far into the cycle does the first phase letting corridor traffic through begin?
+    fn phase_start(&self, i: IntersectionID, map: &Map) -> Duration {
+        let signal = map.get_traffic_signal(i);
+        let groups = self.turn_groups(i, map);
+
+        let mut start = Duration::ZERO;
+        for phase in &signal.phases {
+            if groups.iter().any(|g| phase.protected_groups.contains(g)) {
+                return start;
+            }
+            start += phase.phase_type.simple_duration();
+        }
+        // No phase protects the movement, so just start the wave at the beginning of the cycle.
+        Duration::ZERO
+    }
+}
@@ -0,0 +1,377 @@
+mod perma;
+
+use crate::edits::perma::LegacyMapEdits;
+pub use crate::edits::perma::{PermanentMapEdits, EDITS_VERSION};
+use crate::{
+    BuildingID, ControlRoundabout, ControlStopSign, ControlTrafficSignal, IntersectionID, LaneID,
+    LaneType, Map, ParkingRules, PathConstraints, RoadID, TurnID,
+};
+use abstutil::{retain_btreemap, retain_btreeset, Timer};
+use geom::{Distance, Speed};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MapEdits {
+    pub map_name: String,
+    pub edits_name: String,
+    pub commands: Vec<EditCmd>,
+
+    // Derived from commands, kept up to date by update_derived
+    pub original_lts: BTreeMap<LaneID, LaneType>,
+    pub reversed_lanes: BTreeSet<LaneID>,
+    pub original_intersections: BTreeMap<IntersectionID, EditIntersection>,
+    pub original_banned_turns: BTreeMap<IntersectionID, BTreeSet<TurnID>>,
+    pub original_speed_limits: BTreeMap<RoadID, Speed>,
+    pub original_access_restrictions: BTreeMap<RoadID, BTreeSet<PathConstraints>>,
+    pub original_road_lanes: BTreeMap<RoadID, Vec<EditLane>>,
+    pub original_parking_rules: BTreeMap<RoadID, ParkingRules>,
+    pub original_offstreet_parking_rules: BTreeMap<BuildingID, ParkingRules>,
+
+    // Edits without these are player generated.
+    pub proposal_description: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum EditIntersection {
+    StopSign(ControlStopSign),
+    TrafficSignal(ControlTrafficSignal),
+    Roundabout(ControlRoundabout),
+    Closed,
+}
+
+// One lane of a road, as far as adding, removing, and resizing lanes is concerned. A road's lanes
+// are listed forwards first, then backwards, each side from the center of the road outwards.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EditLane {
+    pub id: LaneID,
+    pub lane_type: LaneType,
+    pub fwd: bool,
+    pub width: Distance,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum EditCmd {
+    ChangeLaneType {
+        id: LaneID,
+        lt: LaneType,
+        orig_lt: LaneType,
+    },
+    ReverseLane {
+        l: LaneID,
+        // New intended dst_i
+        dst_i: IntersectionID,
+    },
+    ChangeIntersection {
+        i: IntersectionID,
+        new: EditIntersection,
+        old: EditIntersection,
+    },
+    // Ban or allow any turns (including crosswalks) at one intersection. These are the full set of
+    // banned turns, not just the changes.
+    ChangeBannedTurns {
+        i: IntersectionID,
+        new: BTreeSet<TurnID>,
+        old: BTreeSet<TurnID>,
+    },
+    ChangeSpeedLimit {
+        id: RoadID,
+        new: Speed,
+        old: Speed,
+    },
+    // The full set of vehicle types that can't pass through the road
+    ChangeAccessRestrictions {
+        id: RoadID,
+        new: BTreeSet<PathConstraints>,
+        old: BTreeSet<PathConstraints>,
+    },
+    // The full layout of a road's lanes. Lanes can be added (with a brand new LaneID), removed,
+    // reordered, or resized.
+    ChangeRoadLanes {
+        r: RoadID,
+        new: Vec<EditLane>,
+        old: Vec<EditLane>,
+    },
+    // The price and time limit of every parking lane along a road
+    ChangeParkingRules {
+        id: RoadID,
+        new: ParkingRules,
+        old: ParkingRules,
+    },
+    // The price and time limit of a building's off-street parking
+    ChangeOffstreetParkingRules {
+        b: BuildingID,
+        new: ParkingRules,
+        old: ParkingRules,
+    },
+    // Several commands that should be applied and undone together
+    Batch(Vec<EditCmd>),
+}
+
+pub struct EditEffects {
+    pub changed_roads: BTreeSet<RoadID>,
+    pub changed_intersections: BTreeSet<IntersectionID>,
+    pub added_turns: BTreeSet<TurnID>,
+    pub deleted_turns: BTreeSet<TurnID>,
+}
+
+impl MapEdits {
+    pub fn new(map_name: &str) -> MapEdits {
+        MapEdits {
+            map_name: map_name.to_string(),
+            // Something has to fill this out later
+            edits_name: "untitled edits".to_string(),
+            proposal_description: Vec::new(),
+            commands: Vec::new(),
+
+            original_lts: BTreeMap::new(),
+            reversed_lanes: BTreeSet::new(),
+            original_intersections: BTreeMap::new(),
+            original_banned_turns: BTreeMap::new(),
+            original_speed_limits: BTreeMap::new(),
+            original_access_restrictions: BTreeMap::new(),
+            original_road_lanes: BTreeMap::new(),
+            original_parking_rules: BTreeMap::new(),
+            original_offstreet_parking_rules: BTreeMap::new(),
+        }
+    }
+
+    // Commands that don't apply to this map anymore are skipped with a warning. Edits from before
+    // the format was versioned are migrated.
+    pub fn load(map: &Map, edits_name: &str, timer: &mut Timer) -> Result<MapEdits, String> {
+        if edits_name == "untitled edits" {
+            return Ok(MapEdits::new(map.get_name()));
+        }
+        MapEdits::load_from_file(map, abstutil::path_edits(map.get_name(), edits_name), timer)
+    }
+
+    pub fn load_from_file(map: &Map, path: String, timer: &mut Timer) -> Result<MapEdits, String> {
+        // Only versioned edits have this field. Peek at it first, so a problem parsing the rest of
+        // the file is reported against the right format.
+        #[derive(Deserialize)]
+        struct Version {
+            version: Option<usize>,
+        }
+        let result = match abstutil::maybe_read_json::<Version>(path.clone(), timer) {
+            Ok(Version { version: Some(_) }) => {
+                abstutil::maybe_read_json::<PermanentMapEdits>(path.clone(), timer)
+                    .map(|perma| perma.from_permanent(map, timer))
+            }
+            Ok(Version { version: None }) => {
+                abstutil::maybe_read_json::<LegacyMapEdits>(path.clone(), timer)
+                    .map(|legacy| legacy.migrate(map, timer))
+            }
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(edits) => edits,
+            Err(err) => Err(format!("Couldn't load {}: {}", path, err)),
+        }
+    }
+
+    pub(crate) fn save(&mut self, map: &Map) {
+        self.compress(map);
+
+        assert_ne!(self.edits_name, "untitled edits");
+        abstutil::write_json(
+            abstutil::path_edits(&self.map_name, &self.edits_name),
+            &PermanentMapEdits::to_permanent(self, map),
+        );
+    }
+
+    // Original lane types, reversed lanes, all changed intersections, and the original banned
+    // turns, speed limits, access restrictions, lane layouts, and parking rules
+    pub(crate) fn update_derived(&mut self, map: &Map) {
+        let mut orig_lts = BTreeMap::new();
+        let mut reversed_lanes = BTreeSet::new();
+        let mut orig_intersections: BTreeMap<IntersectionID, EditIntersection> = BTreeMap::new();
+        let mut orig_banned_turns: BTreeMap<IntersectionID, BTreeSet<TurnID>> = BTreeMap::new();
+        let mut orig_speed_limits: BTreeMap<RoadID, Speed> = BTreeMap::new();
+        let mut orig_access: BTreeMap<RoadID, BTreeSet<PathConstraints>> = BTreeMap::new();
+        let mut orig_road_lanes: BTreeMap<RoadID, Vec<EditLane>> = BTreeMap::new();
+        let mut orig_parking: BTreeMap<RoadID, ParkingRules> = BTreeMap::new();
+        let mut orig_offstreet: BTreeMap<BuildingID, ParkingRules> = BTreeMap::new();
+
+        let mut cmds = Vec::new();
+        flatten(&self.commands, &mut cmds);
+        for cmd in cmds {
+            match cmd {
+                EditCmd::ChangeLaneType { id, orig_lt, .. } => {
+                    if !orig_lts.contains_key(id) {
+                        orig_lts.insert(*id, *orig_lt);
+                    }
+                }
+                EditCmd::ReverseLane { l, .. } => {
+                    if reversed_lanes.contains(l) {
+                        reversed_lanes.remove(l);
+                    } else {
+                        reversed_lanes.insert(*l);
+                    }
+                }
+                EditCmd::ChangeIntersection { i, ref old, .. } => {
+                    if !orig_intersections.contains_key(i) {
+                        orig_intersections.insert(*i, old.clone());
+                    }
+                }
+                EditCmd::ChangeBannedTurns { i, ref old, .. } => {
+                    if !orig_banned_turns.contains_key(i) {
+                        orig_banned_turns.insert(*i, old.clone());
+                    }
+                }
+                EditCmd::ChangeSpeedLimit { id, old, .. } => {
+                    if !orig_speed_limits.contains_key(id) {
+                        orig_speed_limits.insert(*id, *old);
+                    }
+                }
+                EditCmd::ChangeAccessRestrictions { id, ref old, .. } => {
+                    if !orig_access.contains_key(id) {
+                        orig_access.insert(*id, old.clone());
+                    }
+                }
+                EditCmd::ChangeRoadLanes { r, ref old, .. } => {
+                    if !orig_road_lanes.contains_key(r) {
+                        orig_road_lanes.insert(*r, old.clone());
+                    }
+                }
+                EditCmd::ChangeParkingRules { id, old, .. } => {
+                    if !orig_parking.contains_key(id) {
+                        orig_parking.insert(*id, *old);
+                    }
+                }
+                EditCmd::ChangeOffstreetParkingRules { b, old, .. } => {
+                    if !orig_offstreet.contains_key(b) {
+                        orig_offstreet.insert(*b, *old);
+                    }
+                }
+                EditCmd::Batch(_) => unreachable!(),
+            }
+        }
+
+        retain_btreemap(&mut orig_lts, |l, lt| map.get_l(*l).lane_type != *lt);
+        retain_btreemap(&mut orig_intersections, |i, orig| {
+            map.get_i_edit(*i) != orig.clone()
+        });
+        retain_btreemap(&mut orig_banned_turns, |i, orig| {
+            &map.get_i(*i).banned_turns != orig
+        });
+        retain_btreemap(&mut orig_speed_limits, |r, orig| {
+            map.get_r(*r).speed_limit != *orig
+        });
+        retain_btreemap(&mut orig_access, |r, orig| {
+            &map.get_r(*r).no_through_traffic != orig
+        });
+        retain_btreemap(&mut orig_road_lanes, |r, orig| &map.get_r_edit(*r) != orig);
+        retain_btreemap(&mut orig_parking, |r, orig| {
+            map.get_r(*r).parking_rules != *orig
+        });
+        retain_btreemap(&mut orig_offstreet, |b, orig| {
+            map.get_b(*b).parking.as_ref().map(|p| p.rules) != Some(*orig)
+        });
+        // The lane layout covers the type and direction of every lane on the road, including lanes
+        // that were removed.
+        retain_btreemap(&mut orig_lts, |l, _| {
+            !orig_road_lanes.contains_key(&map.get_l(*l).parent)
+        });
+        retain_btreeset(&mut reversed_lanes, |l| {
+            !orig_road_lanes.contains_key(&map.get_l(*l).parent)
+        });
+
+        self.original_lts = orig_lts;
+        self.reversed_lanes = reversed_lanes;
+        self.original_intersections = orig_intersections;
+        self.original_banned_turns = orig_banned_turns;
+        self.original_speed_limits = orig_speed_limits;
+        self.original_access_restrictions = orig_access;
+        self.original_road_lanes = orig_road_lanes;
+        self.original_parking_rules = orig_parking;
+        self.original_offstreet_parking_rules = orig_offstreet;
+    }
+
+    // Assumes update_derived has been called.
+    pub(crate) fn compress(&mut self, map: &Map) {
+        // Lanes might be created here, so these come first.
+        for (r, old) in &self.original_road_lanes {
+            self.commands.push(EditCmd::ChangeRoadLanes {
+                r: *r,
+                old: old.clone(),
+                new: map.get_r_edit(*r),
+            });
+        }
+        for (l, orig_lt) in &self.original_lts {
+            self.commands.push(EditCmd::ChangeLaneType {
+                id: *l,
+                lt: map.get_l(*l).lane_type,
+                orig_lt: *orig_lt,
+            });
+        }
+        for l in &self.reversed_lanes {
+            self.commands.push(EditCmd::ReverseLane {
+                l: *l,
+                dst_i: map.get_l(*l).dst_i,
+            });
+        }
+        for (i, old) in &self.original_intersections {
+            self.commands.push(EditCmd::ChangeIntersection {
+                i: *i,
+                old: old.clone(),
+                new: map.get_i_edit(*i),
+            });
+        }
+        for (i, old) in &self.original_banned_turns {
+            self.commands.push(EditCmd::ChangeBannedTurns {
+                i: *i,
+                old: old.clone(),
+                new: map.get_i(*i).banned_turns.clone(),
+            });
+        }
+        for (r, old) in &self.original_speed_limits {
+            self.commands.push(EditCmd::ChangeSpeedLimit {
+                id: *r,
+                old: *old,
+                new: map.get_r(*r).speed_limit,
+            });
+        }
+        for (r, old) in &self.original_access_restrictions {
+            self.commands.push(EditCmd::ChangeAccessRestrictions {
+                id: *r,
+                old: old.clone(),
+                new: map.get_r(*r).no_through_traffic.clone(),
+            });
+        }
+        for (r, old) in &self.original_parking_rules {
+            self.commands.push(EditCmd::ChangeParkingRules {
+                id: *r,
+                old: *old,
+                new: map.get_r(*r).parking_rules,
+            });
+        }
+        for (b, old) in &self.original_offstreet_parking_rules {
+            self.commands.push(EditCmd::ChangeOffstreetParkingRules {
+                b: *b,
+                old: *old,
+                new: map.get_b(*b).parking.as_ref().unwrap().rules,
+            });
+        }
+    }
+}
+
+fn flatten<'a>(cmds: &'a Vec<EditCmd>, results: &mut Vec<&'a EditCmd>) {
+    for cmd in cmds {
+        if let EditCmd::Batch(ref list) = cmd {
+            flatten(list, results);
+        } else {
+            results.push(cmd);
+        }
+    }
+}
+
+impl EditEffects {
+    pub fn new() -> EditEffects {
+        EditEffects {
+            changed_roads: BTreeSet::new(),
+            changed_intersections: BTreeSet::new(),
+            added_turns: BTreeSet::new(),
+            deleted_turns: BTreeSet::new(),
+        }
+    }
+}
@@ -0,0 +1,870 @@
+use crate::edits::{EditCmd, EditIntersection, EditLane, MapEdits};
+use crate::raw::{OriginalIntersection, OriginalRoad};
+use crate::{
+    BuildingID, ControlRoundabout, ControlStopSign, ControlTrafficSignal, DirectedRoadID,
+    IntersectionID, LaneID, LaneType, Map, ParkingRules, PathConstraints, Phase, PhaseType, RoadID,
+    TurnGroup, TurnGroupID, TurnID,
+};
+use abstutil::{deserialize_btreemap, Timer};
+use geom::{Distance, Duration, Speed};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// LaneIDs, IntersectionIDs, and friends are only valid for one import of a map. Edits are saved in
+// this form instead, referring to things by OSM IDs, so they survive reimporting.
+// Increment this for any breaking change to the format.
+pub const EDITS_VERSION: usize = 1;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PermanentMapEdits {
+    pub map_name: String,
+    pub edits_name: String,
+    pub version: usize,
+    commands: Vec<PermanentEditCmd>,
+
+    // Edits without these are player generated.
+    pub proposal_description: Vec<String>,
+}
+
+// A lane, without relying on LaneIDs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PermanentLane {
+    // The idx-th lane of the road, in the order they're imported from OSM. If the road doesn't
+    // have num_lanes anymore, the lane can't be found.
+    Original {
+        road: OriginalRoad,
+        idx: usize,
+        num_lanes: usize,
+    },
+    // Lanes created by edits have no equivalent in OSM. This is the n-th one created, counting
+    // after all of the imported lanes.
+    Added(usize),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PermanentTurn {
+    pub parent: OriginalIntersection,
+    pub src: PermanentLane,
+    pub dst: PermanentLane,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PermanentDirectedRoad {
+    pub road: OriginalRoad,
+    pub forwards: bool,
+}
+
+// The intersection is implied by the command.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PermanentTurnGroup {
+    pub from: PermanentDirectedRoad,
+    pub to: PermanentDirectedRoad,
+    pub crosswalk: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PermanentPhase {
+    pub protected_groups: BTreeSet<PermanentTurnGroup>,
+    pub yield_groups: BTreeSet<PermanentTurnGroup>,
+    pub phase_type: PhaseType,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum PermanentEditIntersection {
+    StopSign {
+        must_stop: BTreeMap<OriginalRoad, bool>,
+    },
+    TrafficSignal {
+        phases: Vec<PermanentPhase>,
+        offset: Duration,
+    },
+    // Which roads are circulating just depends on the map, so there's nothing to store.
+    Roundabout,
+    Closed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PermanentEditLane {
+    pub id: PermanentLane,
+    pub lane_type: LaneType,
+    pub fwd: bool,
+    pub width: Distance,
+}
+
+// Mirrors EditCmd
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum PermanentEditCmd {
+    ChangeLaneType {
+        id: PermanentLane,
+        lt: LaneType,
+        orig_lt: LaneType,
+    },
+    ReverseLane {
+        l: PermanentLane,
+        dst_i: OriginalIntersection,
+    },
+    ChangeIntersection {
+        i: OriginalIntersection,
+        new: PermanentEditIntersection,
+        old: PermanentEditIntersection,
+    },
+    ChangeBannedTurns {
+        i: OriginalIntersection,
+        new: BTreeSet<PermanentTurn>,
+        old: BTreeSet<PermanentTurn>,
+    },
+    ChangeSpeedLimit {
+        id: OriginalRoad,
+        new: Speed,
+        old: Speed,
+    },
+    ChangeAccessRestrictions {
+        id: OriginalRoad,
+        new: BTreeSet<PathConstraints>,
+        old: BTreeSet<PathConstraints>,
+    },
+    ChangeRoadLanes {
+        r: OriginalRoad,
+        new: Vec<PermanentEditLane>,
+        old: Vec<PermanentEditLane>,
+    },
+    ChangeParkingRules {
+        id: OriginalRoad,
+        new: ParkingRules,
+        old: ParkingRules,
+    },
+    // Buildings are identified by their OSM way
+    ChangeOffstreetParkingRules {
+        osm_way_id: i64,
+        new: ParkingRules,
+        old: ParkingRules,
+    },
+    Batch(Vec<PermanentEditCmd>),
+}
+
+// Before edits were versioned, they were just MapEdits, referring to IDs directly. The derived
+// fields are ignored. The legacy types are a frozen copy of that format, so don't change them along
+// with EditCmd and friends.
+#[derive(Deserialize)]
+pub(crate) struct LegacyMapEdits {
+    map_name: String,
+    edits_name: String,
+    commands: Vec<LegacyEditCmd>,
+    proposal_description: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+enum LegacyEditCmd {
+    ChangeLaneType {
+        id: LaneID,
+        lt: LaneType,
+        orig_lt: LaneType,
+    },
+    ReverseLane {
+        l: LaneID,
+        dst_i: IntersectionID,
+    },
+    ChangeIntersection {
+        i: IntersectionID,
+        new: LegacyEditIntersection,
+        old: LegacyEditIntersection,
+    },
+}
+
+#[derive(Deserialize, Debug)]
+enum LegacyEditIntersection {
+    StopSign(LegacyStopSign),
+    TrafficSignal(LegacyTrafficSignal),
+    Closed,
+}
+
+// Everything else can be derived from the map.
+#[derive(Deserialize, Debug)]
+struct LegacyStopSign {
+    #[serde(deserialize_with = "deserialize_btreemap")]
+    roads: BTreeMap<RoadID, LegacyRoadWithStopSign>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LegacyRoadWithStopSign {
+    must_stop: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct LegacyTrafficSignal {
+    phases: Vec<LegacyPhase>,
+    offset: Duration,
+}
+
+// Every phase had a fixed duration back then.
+#[derive(Deserialize, Debug)]
+struct LegacyPhase {
+    protected_groups: BTreeSet<TurnGroupID>,
+    yield_groups: BTreeSet<TurnGroupID>,
+    duration: Duration,
+}
+
+impl PermanentMapEdits {
+    pub fn to_permanent(edits: &MapEdits, map: &Map) -> PermanentMapEdits {
+        let lookup = Lookup::new(map);
+        PermanentMapEdits {
+            map_name: edits.map_name.clone(),
+            edits_name: edits.edits_name.clone(),
+            version: EDITS_VERSION,
+            commands: edits
+                .commands
+                .iter()
+                .map(|cmd| lookup.perma_cmd(cmd))
+                .collect(),
+            proposal_description: edits.proposal_description.clone(),
+        }
+    }
+
+    // Commands that no longer match the map are skipped, with a warning. Anything that depends
+    // on them, like changing the type of a lane that a skipped command added, is skipped too.
+    pub fn from_permanent(self, map: &Map, timer: &mut Timer) -> Result<MapEdits, String> {
+        if self.map_name != map.get_name() {
+            return Err(format!(
+                "Edits {} are for {}, not {}",
+                self.edits_name,
+                self.map_name,
+                map.get_name()
+            ));
+        }
+        if self.version > EDITS_VERSION {
+            return Err(format!(
+                "Edits {} are version {}, but only up to {} is understood",
+                self.edits_name, self.version, EDITS_VERSION
+            ));
+        }
+
+        let mut lookup = Lookup::new(map);
+        let mut edits = MapEdits::new(map.get_name());
+        edits.edits_name = self.edits_name;
+        edits.proposal_description = self.proposal_description;
+        for (idx, cmd) in self.commands.into_iter().enumerate() {
+            match lookup.cmd(cmd.clone()) {
+                Ok(cmd) => {
+                    edits.commands.push(cmd);
+                }
+                Err(err) => {
+                    timer.warn(format!(
+                        "Skipping command {} of {}, it no longer applies: {}. {:?}",
+                        idx + 1,
+                        edits.edits_name,
+                        err,
+                        cmd
+                    ));
+                }
+            }
+        }
+        Ok(edits)
+    }
+}
+
+impl LegacyMapEdits {
+    // There's no way to tell if the IDs are from the same import of the map, so the best we can
+    // do is skip commands that obviously don't make sense anymore.
+    pub(crate) fn migrate(self, map: &Map, timer: &mut Timer) -> Result<MapEdits, String> {
+        if self.map_name != map.get_name() {
+            return Err(format!(
+                "Edits {} are for {}, not {}",
+                self.edits_name,
+                self.map_name,
+                map.get_name()
+            ));
+        }
+        timer.note(format!(
+            "Migrating {} from the unversioned edits format",
+            self.edits_name
+        ));
+
+        // Going through the permanent form checks everything along the way.
+        let mut lookup = Lookup::new(map);
+        let mut edits = MapEdits::new(map.get_name());
+        edits.edits_name = self.edits_name;
+        edits.proposal_description = self.proposal_description;
+        for (idx, cmd) in self.commands.into_iter().enumerate() {
+            let result = match lookup.check_legacy_cmd(&cmd) {
+                Ok(()) => {
+                    let perma = lookup.legacy_perma_cmd(&cmd);
+                    lookup.cmd(perma)
+                }
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(cmd) => {
+                    edits.commands.push(cmd);
+                }
+                Err(err) => {
+                    timer.warn(format!(
+                        "Skipping command {} of {}, it no longer applies: {}. {:?}",
+                        idx + 1,
+                        edits.edits_name,
+                        err,
+                        cmd
+                    ));
+                }
+            }
+        }
+        Ok(edits)
+    }
+}
+
+struct Lookup<'a> {
+    map: &'a Map,
+    roads: BTreeMap<OriginalRoad, RoadID>,
+    intersections: BTreeMap<OriginalIntersection, IntersectionID>,
+    buildings: BTreeMap<i64, BuildingID>,
+    // Per road, the first imported lane and how many there are. A road's imported lanes are
+    // consecutive.
+    imported_lanes: BTreeMap<RoadID, (usize, usize)>,
+    // Lanes that earlier commands create, when going from permanent IDs
+    added_lanes: BTreeSet<LaneID>,
+}
+
+impl<'a> Lookup<'a> {
+    fn new(map: &'a Map) -> Lookup<'a> {
+        let mut imported_lanes: BTreeMap<RoadID, (usize, usize)> = BTreeMap::new();
+        for l in map.all_lanes().iter().take(map.num_original_lanes()) {
+            let entry = imported_lanes.entry(l.parent).or_insert((l.id.0, 0));
+            entry.1 += 1;
+        }
+        Lookup {
+            map,
+            roads: map.all_roads().iter().map(|r| (r.orig_id, r.id)).collect(),
+            intersections: map
+                .all_intersections()
+                .iter()
+                .map(|i| (i.orig_id, i.id))
+                .collect(),
+            buildings: map
+                .all_buildings()
+                .iter()
+                .map(|b| (b.osm_way_id, b.id))
+                .collect(),
+            imported_lanes,
+            added_lanes: BTreeSet::new(),
+        }
+    }
+
+    fn perma_lane(&self, l: LaneID) -> PermanentLane {
+        if l.0 >= self.map.num_original_lanes() {
+            return PermanentLane::Added(l.0 - self.map.num_original_lanes());
+        }
+        let parent = self.map.get_l(l).parent;
+        let (first, num_lanes) = self.imported_lanes[&parent];
+        PermanentLane::Original {
+            road: self.map.get_r(parent).orig_id,
+            idx: l.0 - first,
+            num_lanes,
+        }
+    }
+
+    fn perma_turn(&self, t: TurnID) -> PermanentTurn {
+        PermanentTurn {
+            parent: self.map.get_i(t.parent).orig_id,
+            src: self.perma_lane(t.src),
+            dst: self.perma_lane(t.dst),
+        }
+    }
+
+    fn perma_dr(&self, dr: DirectedRoadID) -> PermanentDirectedRoad {
+        PermanentDirectedRoad {
+            road: self.map.get_r(dr.id).orig_id,
+            forwards: dr.forwards,
+        }
+    }
+
+    fn perma_group(&self, g: TurnGroupID) -> PermanentTurnGroup {
+        PermanentTurnGroup {
+            from: self.perma_dr(g.from),
+            to: self.perma_dr(g.to),
+            crosswalk: g.crosswalk,
+        }
+    }
+
+    fn perma_intersection(&self, edit: &EditIntersection) -> PermanentEditIntersection {
+        match edit {
+            EditIntersection::StopSign(ref ss) => PermanentEditIntersection::StopSign {
+                must_stop: ss
+                    .roads
+                    .iter()
+                    .map(|(r, val)| (self.map.get_r(*r).orig_id, val.must_stop))
+                    .collect(),
+            },
+            EditIntersection::TrafficSignal(ref ts) => PermanentEditIntersection::TrafficSignal {
+                phases: ts
+                    .phases
+                    .iter()
+                    .map(|p| PermanentPhase {
+                        protected_groups: p
+                            .protected_groups
+                            .iter()
+                            .map(|g| self.perma_group(*g))
+                            .collect(),
+                        yield_groups: p
+                            .yield_groups
+                            .iter()
+                            .map(|g| self.perma_group(*g))
+                            .collect(),
+                        phase_type: p.phase_type.clone(),
+                    })
+                    .collect(),
+                offset: ts.offset,
+            },
+            EditIntersection::Roundabout(_) => PermanentEditIntersection::Roundabout,
+            EditIntersection::Closed => PermanentEditIntersection::Closed,
+        }
+    }
+
+    fn perma_edit_lanes(&self, lanes: &Vec<EditLane>) -> Vec<PermanentEditLane> {
+        lanes
+            .iter()
+            .map(|spec| PermanentEditLane {
+                id: self.perma_lane(spec.id),
+                lane_type: spec.lane_type,
+                fwd: spec.fwd,
+                width: spec.width,
+            })
+            .collect()
+    }
+
+    fn perma_cmd(&self, cmd: &EditCmd) -> PermanentEditCmd {
+        match cmd {
+            EditCmd::ChangeLaneType { id, lt, orig_lt } => PermanentEditCmd::ChangeLaneType {
+                id: self.perma_lane(*id),
+                lt: *lt,
+                orig_lt: *orig_lt,
+            },
+            EditCmd::ReverseLane { l, dst_i } => PermanentEditCmd::ReverseLane {
+                l: self.perma_lane(*l),
+                dst_i: self.map.get_i(*dst_i).orig_id,
+            },
+            EditCmd::ChangeIntersection {
+                i,
+                ref new,
+                ref old,
+            } => PermanentEditCmd::ChangeIntersection {
+                i: self.map.get_i(*i).orig_id,
+                new: self.perma_intersection(new),
+                old: self.perma_intersection(old),
+            },
+            EditCmd::ChangeBannedTurns {
+                i,
+                ref new,
+                ref old,
+            } => PermanentEditCmd::ChangeBannedTurns {
+                i: self.map.get_i(*i).orig_id,
+                new: new.iter().map(|t| self.perma_turn(*t)).collect(),
+                old: old.iter().map(|t| self.perma_turn(*t)).collect(),
+            },
+            EditCmd::ChangeSpeedLimit { id, new, old } => PermanentEditCmd::ChangeSpeedLimit {
+                id: self.map.get_r(*id).orig_id,
+                new: *new,
+                old: *old,
+            },
+            EditCmd::ChangeAccessRestrictions {
+                id,
+                ref new,
+                ref old,
+            } => PermanentEditCmd::ChangeAccessRestrictions {
+                id: self.map.get_r(*id).orig_id,
+                new: new.clone(),
+                old: old.clone(),
+            },
+            EditCmd::ChangeRoadLanes {
+                r,
+                ref new,
+                ref old,
+            } => PermanentEditCmd::ChangeRoadLanes {
+                r: self.map.get_r(*r).orig_id,
+                new: self.perma_edit_lanes(new),
+                old: self.perma_edit_lanes(old),
+            },
+            EditCmd::ChangeParkingRules { id, new, old } => PermanentEditCmd::ChangeParkingRules {
+                id: self.map.get_r(*id).orig_id,
+                new: *new,
+                old: *old,
+            },
+            EditCmd::ChangeOffstreetParkingRules { b, new, old } => {
+                PermanentEditCmd::ChangeOffstreetParkingRules {
+                    osm_way_id: self.map.get_b(*b).osm_way_id,
+                    new: *new,
+                    old: *old,
+                }
+            }
+            EditCmd::Batch(ref cmds) => {
+                PermanentEditCmd::Batch(cmds.iter().map(|cmd| self.perma_cmd(cmd)).collect())
+            }
+        }
+    }
+
+    fn road(&self, id: OriginalRoad) -> Result<RoadID, String> {
+        self.roads
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| format!("can't find {}", id))
+    }
+
+    fn intersection(&self, id: OriginalIntersection) -> Result<IntersectionID, String> {
+        self.intersections
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| format!("can't find {}", id))
+    }
+
+    fn lane(&self, l: PermanentLane) -> Result<LaneID, String> {
+        match l {
+            PermanentLane::Original {
+                road,
+                idx,
+                num_lanes,
+            } => {
+                let r = self.road(road)?;
+                let (first, actual_num_lanes) = self.imported_lanes[&r];
+                if num_lanes != actual_num_lanes {
+                    return Err(format!(
+                        "{} used to have {} lanes, but now has {}",
+                        road, num_lanes, actual_num_lanes
+                    ));
+                }
+                Ok(LaneID(first + idx))
+            }
+            PermanentLane::Added(n) => {
+                let id = LaneID(self.map.num_original_lanes() + n);
+                if self.added_lanes.contains(&id) {
+                    Ok(id)
+                } else {
+                    Err(format!("the command creating {:?} was skipped", l))
+                }
+            }
+        }
+    }
+
+    fn turn(&self, t: PermanentTurn) -> Result<TurnID, String> {
+        Ok(TurnID {
+            parent: self.intersection(t.parent)?,
+            src: self.lane(t.src)?,
+            dst: self.lane(t.dst)?,
+        })
+    }
+
+    fn dr(&self, dr: PermanentDirectedRoad) -> Result<DirectedRoadID, String> {
+        Ok(DirectedRoadID {
+            id: self.road(dr.road)?,
+            forwards: dr.forwards,
+        })
+    }
+
+    fn group(&self, i: IntersectionID, g: PermanentTurnGroup) -> Result<TurnGroupID, String> {
+        Ok(TurnGroupID {
+            from: self.dr(g.from)?,
+            to: self.dr(g.to)?,
+            parent: i,
+            crosswalk: g.crosswalk,
+        })
+    }
+
+    fn edit_intersection(
+        &self,
+        i: IntersectionID,
+        edit: PermanentEditIntersection,
+    ) -> Result<EditIntersection, String> {
+        match edit {
+            PermanentEditIntersection::StopSign { must_stop } => {
+                let mut ss = ControlStopSign::new(self.map, i);
+                for (r, stop) in must_stop {
+                    let r = self.road(r)?;
+                    if let Some(val) = ss.roads.get_mut(&r) {
+                        val.must_stop = stop;
+                    } else {
+                        return Err(format!("{} isn't connected to {}", i, r));
+                    }
+                }
+                Ok(EditIntersection::StopSign(ss))
+            }
+            PermanentEditIntersection::TrafficSignal { phases, offset } => {
+                let mut ts_phases = Vec::new();
+                for p in phases {
+                    let mut phase = Phase::new();
+                    for g in p.protected_groups {
+                        phase.protected_groups.insert(self.group(i, g)?);
+                    }
+                    for g in p.yield_groups {
+                        phase.yield_groups.insert(self.group(i, g)?);
+                    }
+                    phase.phase_type = p.phase_type;
+                    ts_phases.push(phase);
+                }
+                let ts = ControlTrafficSignal {
+                    id: i,
+                    phases: ts_phases,
+                    offset,
+                    turn_groups: TurnGroup::for_i(i, self.map),
+                }
+                .validate()?;
+                Ok(EditIntersection::TrafficSignal(ts))
+            }
+            PermanentEditIntersection::Roundabout => Ok(EditIntersection::Roundabout(
+                ControlRoundabout::new(self.map, i),
+            )),
+            PermanentEditIntersection::Closed => Ok(EditIntersection::Closed),
+        }
+    }
+
+    fn edit_lanes(
+        &self,
+        r: RoadID,
+        lanes: Vec<PermanentEditLane>,
+    ) -> Result<Vec<EditLane>, String> {
+        let mut results = Vec::new();
+        for spec in lanes {
+            let id = match spec.id {
+                // This is the command that creates the lane
+                PermanentLane::Added(n) => LaneID(self.map.num_original_lanes() + n),
+                PermanentLane::Original { .. } => self.lane(spec.id)?,
+            };
+            if id.0 < self.map.num_original_lanes() && self.map.get_l(id).parent != r {
+                return Err(format!("{} doesn't belong to {}", id, r));
+            }
+            results.push(EditLane {
+                id,
+                lane_type: spec.lane_type,
+                fwd: spec.fwd,
+                width: spec.width,
+            });
+        }
+        Ok(results)
+    }
+
+    // Later commands might refer to lanes created by this one, so this isn't &self.
+    fn cmd(&mut self, cmd: PermanentEditCmd) -> Result<EditCmd, String> {
+        match cmd {
+            PermanentEditCmd::ChangeLaneType { id, lt, orig_lt } => Ok(EditCmd::ChangeLaneType {
+                id: self.lane(id)?,
+                lt,
+                orig_lt,
+            }),
+            PermanentEditCmd::ReverseLane { l, dst_i } => {
+                let l = self.lane(l)?;
+                let dst_i = self.intersection(dst_i)?;
+                let lane = self.map.get_l(l);
+                if lane.src_i != dst_i && lane.dst_i != dst_i {
+                    return Err(format!("{} doesn't touch {}", l, dst_i));
+                }
+                Ok(EditCmd::ReverseLane { l, dst_i })
+            }
+            PermanentEditCmd::ChangeIntersection { i, new, old } => {
+                let i = self.intersection(i)?;
+                if self.map.get_i(i).is_border() {
+                    return Err(format!("{} is a border", i));
+                }
+                Ok(EditCmd::ChangeIntersection {
+                    i,
+                    new: self.edit_intersection(i, new)?,
+                    old: self.edit_intersection(i, old)?,
+                })
+            }
+            PermanentEditCmd::ChangeBannedTurns { i, new, old } => {
+                let i = self.intersection(i)?;
+                let mut new_turns = BTreeSet::new();
+                for t in new {
+                    new_turns.insert(self.turn(t)?);
+                }
+                let mut old_turns = BTreeSet::new();
+                for t in old {
+                    old_turns.insert(self.turn(t)?);
+                }
+                Ok(EditCmd::ChangeBannedTurns {
+                    i,
+                    new: new_turns,
+                    old: old_turns,
+                })
+            }
+            PermanentEditCmd::ChangeSpeedLimit { id, new, old } => Ok(EditCmd::ChangeSpeedLimit {
+                id: self.road(id)?,
+                new,
+                old,
+            }),
+            PermanentEditCmd::ChangeAccessRestrictions { id, new, old } => {
+                Ok(EditCmd::ChangeAccessRestrictions {
+                    id: self.road(id)?,
+                    new,
+                    old,
+                })
+            }
+            PermanentEditCmd::ChangeRoadLanes { r, new, old } => {
+                let r = self.road(r)?;
+                let new = self.edit_lanes(r, new)?;
+                let old = self.edit_lanes(r, old)?;
+                for spec in &new {
+                    if spec.id.0 >= self.map.num_original_lanes() {
+                        self.added_lanes.insert(spec.id);
+                    }
+                }
+                Ok(EditCmd::ChangeRoadLanes { r, new, old })
+            }
+            PermanentEditCmd::ChangeParkingRules { id, new, old } => {
+                Ok(EditCmd::ChangeParkingRules {
+                    id: self.road(id)?,
+                    new,
+                    old,
+                })
+            }
+            PermanentEditCmd::ChangeOffstreetParkingRules {
+                osm_way_id,
+                new,
+                old,
+            } => {
+                let b =
+                    self.buildings.get(&osm_way_id).cloned().ok_or_else(|| {
+                        format!("can't find building with OSM way {}", osm_way_id)
+                    })?;
+                if self.map.get_b(b).parking.is_none() {
+                    return Err(format!("{} doesn't have off-street parking", b));
+                }
+                Ok(EditCmd::ChangeOffstreetParkingRules { b, new, old })
+            }
+            PermanentEditCmd::Batch(cmds) => {
+                // If any part of the batch is skipped, so is all of it, including the lanes that
+                // earlier parts added.
+                let added_before = self.added_lanes.clone();
+                let mut results = Vec::new();
+                for cmd in cmds {
+                    match self.cmd(cmd) {
+                        Ok(cmd) => {
+                            results.push(cmd);
+                        }
+                        Err(err) => {
+                            self.added_lanes = added_before;
+                            return Err(err);
+                        }
+                    }
+                }
+                Ok(EditCmd::Batch(results))
+            }
+        }
+    }
+
+    // Before turning legacy commands into the permanent form, make sure the IDs exist at all.
+    fn check_legacy_cmd(&self, cmd: &LegacyEditCmd) -> Result<(), String> {
+        // Legacy edits couldn't add lanes.
+        let check_l = |l: LaneID| {
+            if l.0 < self.map.num_original_lanes() {
+                Ok(())
+            } else {
+                Err(format!("{} doesn't exist", l))
+            }
+        };
+        let check_i = |i: IntersectionID| {
+            if i.0 < self.map.all_intersections().len() {
+                Ok(())
+            } else {
+                Err(format!("{} doesn't exist", i))
+            }
+        };
+        let check_r = |r: RoadID| {
+            if r.0 < self.map.all_roads().len() {
+                Ok(())
+            } else {
+                Err(format!("{} doesn't exist", r))
+            }
+        };
+
+        match cmd {
+            LegacyEditCmd::ChangeLaneType { id, .. } => check_l(*id),
+            LegacyEditCmd::ReverseLane { l, dst_i } => {
+                check_l(*l)?;
+                check_i(*dst_i)
+            }
+            LegacyEditCmd::ChangeIntersection {
+                i,
+                ref new,
+                ref old,
+            } => {
+                check_i(*i)?;
+                for edit in vec![new, old] {
+                    match edit {
+                        LegacyEditIntersection::StopSign(ref ss) => {
+                            for r in ss.roads.keys() {
+                                check_r(*r)?;
+                            }
+                        }
+                        LegacyEditIntersection::TrafficSignal(ref ts) => {
+                            for p in &ts.phases {
+                                for g in p.protected_groups.iter().chain(p.yield_groups.iter()) {
+                                    check_r(g.from.id)?;
+                                    check_r(g.to.id)?;
+                                }
+                            }
+                        }
+                        LegacyEditIntersection::Closed => {}
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn legacy_perma_intersection(
+        &self,
+        edit: &LegacyEditIntersection,
+    ) -> PermanentEditIntersection {
+        match edit {
+            LegacyEditIntersection::StopSign(ref ss) => PermanentEditIntersection::StopSign {
+                must_stop: ss
+                    .roads
+                    .iter()
+                    .map(|(r, val)| (self.map.get_r(*r).orig_id, val.must_stop))
+                    .collect(),
+            },
+            LegacyEditIntersection::TrafficSignal(ref ts) => {
+                PermanentEditIntersection::TrafficSignal {
+                    phases: ts
+                        .phases
+                        .iter()
+                        .map(|p| PermanentPhase {
+                            protected_groups: p
+                                .protected_groups
+                                .iter()
+                                .map(|g| self.perma_group(*g))
+                                .collect(),
+                            yield_groups: p
+                                .yield_groups
+                                .iter()
+                                .map(|g| self.perma_group(*g))
+                                .collect(),
+                            phase_type: PhaseType::Fixed(p.duration),
+                        })
+                        .collect(),
+                    offset: ts.offset,
+                }
+            }
+            LegacyEditIntersection::Closed => PermanentEditIntersection::Closed,
+        }
+    }
+
+    fn legacy_perma_cmd(&self, cmd: &LegacyEditCmd) -> PermanentEditCmd {
+        match cmd {
+            LegacyEditCmd::ChangeLaneType { id, lt, orig_lt } => PermanentEditCmd::ChangeLaneType {
+                id: self.perma_lane(*id),
+                lt: *lt,
+                orig_lt: *orig_lt,
+            },
+            LegacyEditCmd::ReverseLane { l, dst_i } => PermanentEditCmd::ReverseLane {
+                l: self.perma_lane(*l),
+                dst_i: self.map.get_i(*dst_i).orig_id,
+            },
+            LegacyEditCmd::ChangeIntersection {
+                i,
+                ref new,
+                ref old,
+            } => PermanentEditCmd::ChangeIntersection {
+                i: self.map.get_i(*i).orig_id,
+                new: self.legacy_perma_intersection(new),
+                old: self.legacy_perma_intersection(old),
+            },
+        }
+    }
+}
@@ -21,6 +21,8 @@ pub enum IntersectionType {
     TrafficSignal,
     Border,
     Construction,
+    // Everybody entering yields to traffic already circulating
+    Roundabout,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -41,6 +43,8 @@ pub struct Intersection {
 
     // TODO Maybe DirectedRoadIDs
     pub roads: BTreeSet<RoadID>,
+    // Turns removed by map edits. These won't be created when turns are recalculated.
+    pub banned_turns: BTreeSet<TurnID>,
 }
 
 impl Intersection {
@@ -60,6 +64,10 @@ impl Intersection {
         self.intersection_type == IntersectionType::TrafficSignal
     }
 
+    pub fn is_roundabout(&self) -> bool {
+        self.intersection_type == IntersectionType::Roundabout
+    }
+
     pub fn get_incoming_lanes(&self, map: &Map, constraints: PathConstraints) -> Vec<LaneID> {
         self.incoming_lanes
             .iter()
@@ -1,9 +1,10 @@
 use crate::pathfind;
+use crate::raw::DrivingSide;
 use crate::{
     osm, BuildingID, BusStopID, DirectedRoadID, IntersectionID, Map, PathConstraints, Road, RoadID,
     TurnType,
 };
-use geom::{Angle, Distance, Line, PolyLine, Pt2D};
+use geom::{Angle, Distance, Duration, Line, PolyLine, Pt2D};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::fmt;
@@ -11,6 +12,24 @@ use std::fmt;
 // Bit longer than the longest car.
 pub const PARKING_SPOT_LENGTH: Distance = Distance::const_meters(8.0);
 
+// What it takes to park somewhere. Initially everything is free and unlimited, but map edits can
+// change these per road (for on-street parking) or per building (for off-street parking).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParkingRules {
+    // In dollars
+    pub price_per_hour: f64,
+    pub time_limit: Option<Duration>,
+}
+
+impl ParkingRules {
+    pub fn free() -> ParkingRules {
+        ParkingRules {
+            price_per_hour: 0.0,
+            time_limit: None,
+        }
+    }
+}
+
 // TODO reconsider pub usize. maybe outside world shouldnt know.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct LaneID(pub usize);
@@ -192,7 +211,11 @@ impl Lane {
         }
     }
 
-    pub fn get_turn_restrictions(&self, road: &Road) -> Option<BTreeSet<TurnType>> {
+    pub fn get_turn_restrictions(
+        &self,
+        road: &Road,
+        driving_side: DrivingSide,
+    ) -> Option<BTreeSet<TurnType>> {
         if !self.is_driving() {
             return None;
         }
@@ -209,7 +232,12 @@ impl Lane {
         };
         let parts: Vec<&str> = all.split('|').collect();
         // TODO Verify the number of lanes matches up
-        let part = parts.get(offset)?;
+        // turn:lanes goes from left to right, but the offset starts from the center of the road.
+        let idx = match driving_side {
+            DrivingSide::Right => offset,
+            DrivingSide::Left => parts.len().checked_sub(offset + 1)?,
+        };
+        let part = parts.get(idx)?;
         if part == &"none" {
             return None;
         }
@@ -274,3 +302,19 @@ impl Lane {
         grade
     }
 }
+
+// How much faster or slower than on flat ground somebody bikes up (positive) or down (negative) a
+// grade. Climbing is much slower; descending is faster, but people brake before it gets scary.
+pub fn biking_speed_factor(grade: f64) -> f64 {
+    if grade >= 0.0 {
+        1.0 / (1.0 + 15.0 * grade)
+    } else {
+        1.0 + (-10.0 * grade).min(0.5)
+    }
+}
+
+// Tobler's hiking function, relative to walking on flat ground. Walking is fastest slightly
+// downhill. https://en.wikipedia.org/wiki/Tobler%27s_hiking_function
+pub fn walking_speed_factor(grade: f64) -> f64 {
+    (-3.5 * ((grade + 0.05).abs() - 0.05)).exp()
+}
@@ -2,6 +2,7 @@ mod area;
 mod building;
 mod bus_stop;
 pub mod connectivity;
+mod corridor;
 mod edits;
 mod intersection;
 mod lane;
@@ -12,6 +13,7 @@ pub mod osm;
 mod pathfind;
 pub mod raw;
 mod road;
+mod roundabouts;
 mod stop_signs;
 mod traffic_signals;
 mod traversable;
@@ -20,16 +22,23 @@ mod turn;
 pub use crate::area::{Area, AreaID, AreaType};
 pub use crate::building::{Building, BuildingID, FrontPath, OffstreetParking};
 pub use crate::bus_stop::{BusRoute, BusRouteID, BusStop, BusStopID};
-pub use crate::edits::{EditCmd, EditEffects, EditIntersection, MapEdits};
+pub use crate::corridor::SignalCorridor;
+pub use crate::edits::{
+    EditCmd, EditEffects, EditIntersection, EditLane, MapEdits, PermanentMapEdits, EDITS_VERSION,
+};
 pub use crate::intersection::{Intersection, IntersectionID, IntersectionType};
-pub use crate::lane::{Lane, LaneID, LaneType, PARKING_SPOT_LENGTH};
-pub use crate::make::RoadSpec;
+pub use crate::lane::{
+    biking_speed_factor, walking_speed_factor, Lane, LaneID, LaneType, ParkingRules,
+    PARKING_SPOT_LENGTH,
+};
+pub use crate::make::{get_osm_lane_tags, RoadSpec};
 pub use crate::map::Map;
 pub use crate::neighborhood::{FullNeighborhoodInfo, Neighborhood, NeighborhoodBuilder};
-pub use crate::pathfind::{Path, PathConstraints, PathRequest, PathStep};
+pub use crate::pathfind::{Path, PathConstraints, PathRequest, PathStep, TravelTimes};
 pub use crate::road::{DirectedRoadID, Road, RoadID};
+pub use crate::roundabouts::ControlRoundabout;
 pub use crate::stop_signs::{ControlStopSign, RoadWithStopSign};
-pub use crate::traffic_signals::{ControlTrafficSignal, Phase};
+pub use crate::traffic_signals::{ControlTrafficSignal, Phase, PhaseType};
 pub use crate::traversable::{Position, Traversable};
 pub use crate::turn::{Turn, TurnGroup, TurnGroupID, TurnID, TurnPriority, TurnType};
 use abstutil::Cloneable;
@@ -46,4 +55,5 @@ impl Cloneable for LaneType {}
 impl Cloneable for MapEdits {}
 impl Cloneable for Neighborhood {}
 impl Cloneable for NeighborhoodBuilder {}
+impl Cloneable for PathConstraints {}
 impl Cloneable for raw::RestrictionType {}
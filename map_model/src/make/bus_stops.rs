@@ -1,3 +1,4 @@
+use crate::bus_stop::apply_mask;
 use crate::make::sidewalk_finder::find_sidewalk_points;
 use crate::{
     BusRoute, BusRouteID, BusStop, BusStopID, LaneID, LaneType, Map, PathConstraints, PathRequest,
@@ -17,18 +18,20 @@ pub fn make_bus_stops(
 ) -> (BTreeMap<BusStopID, BusStop>, Vec<BusRoute>) {
     timer.start("make bus stops");
     let mut bus_stop_pts: HashSet<HashablePt2D> = HashSet::new();
-    let mut route_lookups: HashMap<String, Vec<HashablePt2D>> = HashMap::new();
+    // Per route, the point of every stop, or None if it's off the map
+    let mut route_lookups: Vec<Vec<Option<HashablePt2D>>> = Vec::new();
     for route in bus_routes {
+        let mut pts = Vec::new();
         for gps in &route.stops {
             if let Some(pt) = Pt2D::from_gps(*gps, gps_bounds) {
                 let hash_pt = pt.to_hashable();
                 bus_stop_pts.insert(hash_pt);
-                route_lookups
-                    .entry(route.name.clone())
-                    .or_insert_with(Vec::new)
-                    .push(hash_pt);
+                pts.push(Some(hash_pt));
+            } else {
+                pts.push(None);
             }
         }
+        route_lookups.push(pts);
     }
 
     let mut stops_per_sidewalk: MultiMap<LaneID, (Distance, HashablePt2D)> = MultiMap::new();
@@ -79,20 +82,22 @@ pub fn make_bus_stops(
     }
 
     let mut routes: Vec<BusRoute> = Vec::new();
-    for route in bus_routes {
-        let route_name = route.name.to_string();
-        let stops: Vec<BusStopID> = route_lookups
-            .remove(&route_name)
-            .unwrap_or_else(Vec::new)
+    for (route, pts) in bus_routes.iter().zip(route_lookups.into_iter()) {
+        let maybe_stops: Vec<Option<BusStopID>> = pts
             .into_iter()
-            .filter_map(|pt| point_to_stop_id.get(&pt))
-            .cloned()
+            .map(|pt| pt.and_then(|pt| point_to_stop_id.get(&pt).cloned()))
             .collect();
+        let keep: Vec<bool> = maybe_stops.iter().map(|s| s.is_some()).collect();
         let id = BusRouteID(routes.len());
         routes.push(BusRoute {
             id,
-            name: route_name.to_string(),
-            stops,
+            name: route.name.to_string(),
+            stops: maybe_stops.into_iter().flatten().collect(),
+            schedule: route
+                .schedule
+                .iter()
+                .map(|times| apply_mask(times.clone(), &keep))
+                .collect(),
         });
     }
     timer.stop("make bus stops");
@@ -101,25 +106,34 @@ pub fn make_bus_stops(
 
 pub fn fix_bus_route(map: &Map, r: &mut BusRoute) -> bool {
     // Trim out stops if needed; map borders sometimes mean some paths don't work.
-    let mut stops = Vec::new();
-    for stop in r.stops.drain(..) {
-        if stops.is_empty() {
-            stops.push(stop);
+    let mut keep = Vec::new();
+    let mut last_kept: Option<BusStopID> = None;
+    for stop in &r.stops {
+        let ok = if let Some(prev) = last_kept {
+            check_stops(prev, *stop, map)
         } else {
-            if check_stops(*stops.last().unwrap(), stop, map) {
-                stops.push(stop);
-            }
+            true
+        };
+        keep.push(ok);
+        if ok {
+            last_kept = Some(*stop);
         }
     }
-    // Don't forget the last and first
-    while stops.len() >= 2 {
-        if check_stops(*stops.last().unwrap(), stops[0], map) {
-            break;
+    r.retain_stops(&keep);
+
+    // Buses following a schedule vanish after the last stop. The others loop around, so don't
+    // forget the last and first.
+    if !r.is_scheduled() {
+        while r.stops.len() >= 2 {
+            if check_stops(*r.stops.last().unwrap(), r.stops[0], map) {
+                break;
+            }
+            // TODO Or the front one
+            let mut keep = vec![true; r.stops.len()];
+            *keep.last_mut().unwrap() = false;
+            r.retain_stops(&keep);
         }
-        // TODO Or the front one
-        stops.pop();
     }
-    r.stops = stops;
     r.stops.len() >= 2
 }
 
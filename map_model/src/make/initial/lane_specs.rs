@@ -1,10 +1,14 @@
+use crate::raw::DrivingSide;
 use crate::{osm, LaneType};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::{fmt, iter};
 
 // (original direction, reversed direction)
-pub fn get_lane_types(osm_tags: &BTreeMap<String, String>) -> (Vec<LaneType>, Vec<LaneType>) {
+pub fn get_lane_types(
+    osm_tags: &BTreeMap<String, String>,
+    driving_side: DrivingSide,
+) -> (Vec<LaneType>, Vec<LaneType>) {
     if let Some(s) = osm_tags.get(osm::SYNTHETIC_LANES) {
         if let Some(spec) = RoadSpec::parse(s.to_string()) {
             return (spec.fwd, spec.back);
@@ -103,10 +107,14 @@ pub fn get_lane_types(osm_tags: &BTreeMap<String, String>) -> (Vec<LaneType>, Ve
             back_side.push(LaneType::Biking);
         }
     } else {
-        if osm_tags.get("cycleway:right") == Some(&"lane".to_string()) {
+        if osm_tags.get(&format!("cycleway:{}", driving_side.osm_side(true)))
+            == Some(&"lane".to_string())
+        {
             fwd_side.push(LaneType::Biking);
         }
-        if osm_tags.get("cycleway:left") == Some(&"lane".to_string()) {
+        if osm_tags.get(&format!("cycleway:{}", driving_side.osm_side(false)))
+            == Some(&"lane".to_string())
+        {
             back_side.push(LaneType::Biking);
         }
     }
@@ -116,9 +124,9 @@ pub fn get_lane_types(osm_tags: &BTreeMap<String, String>) -> (Vec<LaneType>, Ve
             || value == Some(&"diagonal".to_string())
             || value == Some(&"perpendicular".to_string())
     }
-    let parking_lane_fwd = has_parking(osm_tags.get(osm::PARKING_RIGHT))
+    let parking_lane_fwd = has_parking(osm_tags.get(&parking_key(driving_side, true)))
         || has_parking(osm_tags.get(osm::PARKING_BOTH));
-    let parking_lane_back = has_parking(osm_tags.get(osm::PARKING_LEFT))
+    let parking_lane_back = has_parking(osm_tags.get(&parking_key(driving_side, false)))
         || has_parking(osm_tags.get(osm::PARKING_BOTH));
     if parking_lane_fwd {
         fwd_side.push(LaneType::Parking);
@@ -133,15 +141,145 @@ pub fn get_lane_types(osm_tags: &BTreeMap<String, String>) -> (Vec<LaneType>, Ve
     {
         fwd_side.push(LaneType::Sidewalk);
         back_side.push(LaneType::Sidewalk);
-    } else if osm_tags.get(osm::SIDEWALK) == Some(&"right".to_string()) {
+    } else if osm_tags.get(osm::SIDEWALK) == Some(&driving_side.osm_side(true).to_string()) {
         fwd_side.push(LaneType::Sidewalk);
-    } else if osm_tags.get(osm::SIDEWALK) == Some(&"left".to_string()) {
+    } else if osm_tags.get(osm::SIDEWALK) == Some(&driving_side.osm_side(false).to_string()) {
         back_side.push(LaneType::Sidewalk);
     }
 
     (fwd_side, back_side)
 }
 
+// parking:lane:right or parking:lane:left for the forwards or backwards lanes
+fn parking_key(driving_side: DrivingSide, fwds: bool) -> String {
+    if driving_side.osm_side(fwds) == "right" {
+        osm::PARKING_RIGHT.to_string()
+    } else {
+        osm::PARKING_LEFT.to_string()
+    }
+}
+
+// The inverse of get_lane_types: rewrite OSM tags so that get_lane_types produces this layout
+// instead. Only the tags describing the kinds of lanes that changed (driving, bike, parking, or
+// sidewalks) are touched, so existing tagging is kept where possible. Some layouts, like a bike
+// lane between parking and the sidewalk, can't be described this way.
+pub fn get_osm_lane_tags(
+    osm_tags: &BTreeMap<String, String>,
+    fwd: &Vec<LaneType>,
+    back: &Vec<LaneType>,
+    driving_side: DrivingSide,
+) -> Result<BTreeMap<String, String>, String> {
+    let mut tags = osm_tags.clone();
+    tags.remove(osm::SYNTHETIC_LANES);
+    let (orig_fwd, orig_back) = get_lane_types(&tags, driving_side);
+
+    let count = |lts: &Vec<LaneType>, lt: LaneType| lts.iter().filter(|x| **x == lt).count();
+    // Bus lanes take the place of a driving lane.
+    let driving = |f: &Vec<LaneType>, b: &Vec<LaneType>| {
+        (
+            count(f, LaneType::Driving) + count(f, LaneType::Bus),
+            count(b, LaneType::Driving) + count(b, LaneType::Bus),
+            f.contains(&LaneType::SharedLeftTurn),
+        )
+    };
+    if driving(fwd, back) != driving(&orig_fwd, &orig_back) {
+        let (num_fwd, num_back, center) = driving(fwd, back);
+        for key in &[
+            "lanes",
+            "lanes:forward",
+            "lanes:backward",
+            "lanes:both_ways",
+            "centre_turn_lane",
+        ] {
+            tags.remove(*key);
+        }
+        if num_back == 0 {
+            if tags.get("oneway") != Some(&"reversible".to_string()) {
+                tags.insert("oneway".to_string(), "yes".to_string());
+            }
+        } else {
+            tags.remove("oneway");
+            tags.insert("lanes:backward".to_string(), num_back.to_string());
+        }
+        if num_back > 0 || center {
+            tags.insert("lanes:forward".to_string(), num_fwd.to_string());
+        }
+        if center {
+            tags.insert("lanes:both_ways".to_string(), "1".to_string());
+        }
+        tags.insert(
+            "lanes".to_string(),
+            (num_fwd + num_back + if center { 1 } else { 0 }).to_string(),
+        );
+    }
+
+    let sides =
+        |lt: LaneType, f: &Vec<LaneType>, b: &Vec<LaneType>| (f.contains(&lt), b.contains(&lt));
+
+    let bikes = sides(LaneType::Biking, fwd, back);
+    if bikes != sides(LaneType::Biking, &orig_fwd, &orig_back) {
+        for key in &[
+            "cycleway",
+            "cycleway:left",
+            "cycleway:right",
+            "cycleway:both",
+        ] {
+            tags.remove(*key);
+        }
+        if bikes.0 {
+            tags.insert(
+                format!("cycleway:{}", driving_side.osm_side(true)),
+                "lane".to_string(),
+            );
+        }
+        if bikes.1 {
+            tags.insert(
+                format!("cycleway:{}", driving_side.osm_side(false)),
+                "lane".to_string(),
+            );
+        }
+    }
+
+    let parking = sides(LaneType::Parking, fwd, back);
+    if parking != sides(LaneType::Parking, &orig_fwd, &orig_back) {
+        for key in &[osm::PARKING_LEFT, osm::PARKING_RIGHT, osm::PARKING_BOTH] {
+            tags.remove(*key);
+        }
+        let value = |has: bool| (if has { "parallel" } else { "no_parking" }).to_string();
+        if parking.0 == parking.1 {
+            tags.insert(osm::PARKING_BOTH.to_string(), value(parking.0));
+        } else {
+            tags.insert(parking_key(driving_side, true), value(parking.0));
+            tags.insert(parking_key(driving_side, false), value(parking.1));
+        }
+    }
+
+    let sidewalks = sides(LaneType::Sidewalk, fwd, back);
+    if sidewalks != sides(LaneType::Sidewalk, &orig_fwd, &orig_back) {
+        tags.insert(
+            osm::SIDEWALK.to_string(),
+            match sidewalks {
+                (true, true) => "both",
+                (true, false) => driving_side.osm_side(true),
+                (false, true) => driving_side.osm_side(false),
+                (false, false) => "none",
+            }
+            .to_string(),
+        );
+    }
+
+    if get_lane_types(&tags, driving_side) != (fwd.clone(), back.clone()) {
+        return Err(format!(
+            "{} can't be described by OSM tags",
+            RoadSpec {
+                fwd: fwd.clone(),
+                back: back.clone(),
+            }
+        ));
+    }
+    Ok(tags)
+}
+
 // This is a convenient way for map_editor to plumb instructions here.
 #[derive(Serialize, Deserialize)]
 pub struct RoadSpec {
@@ -19,7 +19,7 @@ pub fn make_all_turns(
     assert!(!i.is_border());
 
     let mut raw_turns: Vec<Turn> = Vec::new();
-    raw_turns.extend(make_vehicle_turns(i, roads, lanes, timer));
+    raw_turns.extend(make_vehicle_turns(driving_side, i, roads, lanes, timer));
     raw_turns.extend(make_walking_turns(driving_side, i, roads, lanes, timer));
     let unique_turns = ensure_unique(raw_turns);
 
@@ -29,8 +29,11 @@ pub fn make_all_turns(
         if !does_turn_pass_restrictions(&turn, &i.roads, roads, lanes) {
             continue;
         }
+        if i.banned_turns.contains(&turn.id) {
+            continue;
+        }
 
-        if is_turn_allowed(&turn, roads, lanes) {
+        if is_turn_allowed(&turn, roads, lanes, driving_side) {
             final_turns.push(turn);
         } else {
             filtered_turns
@@ -98,6 +101,7 @@ fn ensure_unique(turns: Vec<Turn>) -> Vec<Turn> {
 }
 
 fn make_vehicle_turns(
+    driving_side: DrivingSide,
     i: &Intersection,
     all_roads: &Vec<Road>,
     lanes: &Vec<Lane>,
@@ -161,14 +165,17 @@ fn make_vehicle_turns(
                 let angle1 = lanes[incoming[0].0].last_line().angle();
                 let angle2 = lanes[outgoing[0].0].first_line().angle();
 
+                // The turn types are physical, but which lanes make them depends on the side of the
+                // road everybody drives on. The rest of this is written in terms of
+                // DrivingSide::Right, where right turns don't cross oncoming traffic.
                 let type_from_angle = TurnType::from_angles(angle1, angle2);
-                let tt = if type_from_angle == TurnType::Right {
+                let tt = if driving_side.turn_type(type_from_angle) == TurnType::Right {
                     // This one's fragile, based on angles. Really we care that there aren't roads
                     // between the two.
                     if wraparound_get(&sorted_roads, (idx1 as isize) - 1).id == r2.id
                         || wraparound_get(&sorted_roads, (idx1 as isize) + 1).id == r2.id
                     {
-                        TurnType::Right
+                        type_from_angle
                     } else {
                         TurnType::Straight
                     }
@@ -176,7 +183,7 @@ fn make_vehicle_turns(
                     type_from_angle
                 };
 
-                match tt {
+                match driving_side.turn_type(tt) {
                     TurnType::Straight => {
                         // Cartesian product. Additionally detect where the lane-changing movements
                         // happen. But we have to use the indices assuming all travel lanes, not
@@ -211,9 +218,11 @@ fn make_vehicle_turns(
                                 }
                                 if let Some(mut t) = make_vehicle_turn(lanes, i.id, l1, *l2, tt) {
                                     if idx1 < idx2 {
-                                        t.turn_type = TurnType::LaneChangeRight;
+                                        t.turn_type =
+                                            driving_side.turn_type(TurnType::LaneChangeRight);
                                     } else if idx1 > idx2 {
-                                        t.turn_type = TurnType::LaneChangeLeft;
+                                        t.turn_type =
+                                            driving_side.turn_type(TurnType::LaneChangeLeft);
                                     }
                                     result.push(Some(t));
                                 }
@@ -720,10 +729,15 @@ fn from_pt(pt: Point2d<f64>) -> Pt2D {
     Pt2D::new(pt.x, pt.y)
 }
 
-fn is_turn_allowed(turn: &Turn, roads: &Vec<Road>, lanes: &Vec<Lane>) -> bool {
+fn is_turn_allowed(
+    turn: &Turn,
+    roads: &Vec<Road>,
+    lanes: &Vec<Lane>,
+    driving_side: DrivingSide,
+) -> bool {
     let l = &lanes[turn.id.src.0];
     let r = &roads[l.parent.0];
-    if let Some(types) = l.get_turn_restrictions(r) {
+    if let Some(types) = l.get_turn_restrictions(r, driving_side) {
         types.contains(&turn.turn_type)
     } else {
         true
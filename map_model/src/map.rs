@@ -1,17 +1,21 @@
-use crate::pathfind::Pathfinder;
+use crate::pathfind::{pathfind_with_extra_costs, Pathfinder};
 use crate::raw::{DrivingSide, OriginalIntersection, OriginalRoad, RawMap};
 use crate::{
     connectivity, make, Area, AreaID, Building, BuildingID, BusRoute, BusRouteID, BusStop,
-    BusStopID, ControlStopSign, ControlTrafficSignal, EditCmd, EditEffects, EditIntersection,
-    Intersection, IntersectionID, IntersectionType, Lane, LaneID, LaneType, MapEdits, Path,
-    PathConstraints, PathRequest, Position, Road, RoadID, Turn, TurnGroupID, TurnID, TurnType,
-    NORMAL_LANE_THICKNESS, SIDEWALK_THICKNESS,
+    BusStopID, ControlRoundabout, ControlStopSign, ControlTrafficSignal, EditCmd, EditEffects,
+    EditIntersection, Intersection, IntersectionID, IntersectionType, Lane, LaneID, LaneType,
+    MapEdits, ParkingRules, Path, PathConstraints, PathRequest, Position, Road, RoadID,
+    TravelTimes, Traversable, Turn, TurnGroupID, TurnID, TurnType, NORMAL_LANE_THICKNESS,
+    SIDEWALK_THICKNESS,
 };
 use abstutil::{deserialize_btreemap, serialize_btreemap, Error, Timer, Warn};
 use geom::{Angle, Bounds, Distance, GPSBounds, Line, PolyLine, Polygon, Pt2D};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
 
+// Matches how far the initial geometry extends dead-ends past the end of the road.
+const DEADEND_LENGTH: Distance = Distance::const_meters(10.0);
+
 #[derive(Serialize, Deserialize)]
 pub struct Map {
     roads: Vec<Road>,
@@ -32,9 +36,10 @@ pub struct Map {
     areas: Vec<Area>,
     boundary_polygon: Polygon,
 
-    // Note that border nodes belong in neither!
+    // Note that border nodes belong in none of these!
     stop_signs: BTreeMap<IntersectionID, ControlStopSign>,
     traffic_signals: BTreeMap<IntersectionID, ControlTrafficSignal>,
+    roundabouts: BTreeMap<IntersectionID, ControlRoundabout>,
 
     gps_bounds: GPSBounds,
     bounds: Bounds,
@@ -44,6 +49,12 @@ pub struct Map {
     // TODO Argh, hack, initialization order is hard!
     pathfinder: Option<Pathfinder>,
     pathfinder_dirty: bool,
+    // Lanes were added or removed, so the pathfinding graphs can't be updated in-place.
+    pathfinder_rebuild: bool,
+    // Lanes with higher IDs were added by edits.
+    num_original_lanes: usize,
+    // Measured by a previous simulation, for routing cars around congestion
+    observed_travel_times: Option<TravelTimes>,
 
     name: String,
     edits: MapEdits,
@@ -97,18 +108,22 @@ impl Map {
             ]),
             stop_signs: BTreeMap::new(),
             traffic_signals: BTreeMap::new(),
+            roundabouts: BTreeMap::new(),
             gps_bounds: GPSBounds::new(),
             bounds: Bounds::new(),
             driving_side: DrivingSide::Right,
             turn_lookup: Vec::new(),
             pathfinder: None,
             pathfinder_dirty: false,
+            pathfinder_rebuild: false,
+            num_original_lanes: 0,
+            observed_travel_times: None,
             name: "blank".to_string(),
             edits: MapEdits::new("blank"),
         }
     }
 
-    fn create_from_raw(raw: RawMap, timer: &mut Timer) -> Map {
+    pub fn create_from_raw(raw: RawMap, timer: &mut Timer) -> Map {
         timer.start("raw_map to InitialMap");
         let gps_bounds = raw.gps_bounds.clone();
         let bounds = gps_bounds.to_bounds();
@@ -126,6 +141,7 @@ impl Map {
             let mut stop_signs: BTreeMap<IntersectionID, ControlStopSign> = BTreeMap::new();
             let mut traffic_signals: BTreeMap<IntersectionID, ControlTrafficSignal> =
                 BTreeMap::new();
+            let mut roundabouts: BTreeMap<IntersectionID, ControlRoundabout> = BTreeMap::new();
             for i in &m.intersections {
                 match i.intersection_type {
                     IntersectionType::StopSign => {
@@ -134,11 +150,15 @@ impl Map {
                     IntersectionType::TrafficSignal => {
                         traffic_signals.insert(i.id, ControlTrafficSignal::new(&m, i.id, timer));
                     }
+                    IntersectionType::Roundabout => {
+                        roundabouts.insert(i.id, ControlRoundabout::new(&m, i.id));
+                    }
                     IntersectionType::Border | IntersectionType::Construction => {}
                 };
             }
             m.stop_signs = stop_signs;
             m.traffic_signals = traffic_signals;
+            m.roundabouts = roundabouts;
         }
 
         // Here's a fun one: we can't set up walking_using_transit yet, because we haven't
@@ -275,6 +295,10 @@ impl Map {
         self.traffic_signals.get(&id)
     }
 
+    pub fn maybe_get_roundabout(&self, id: IntersectionID) -> Option<&ControlRoundabout> {
+        self.roundabouts.get(&id)
+    }
+
     pub fn get_r(&self, id: RoadID) -> &Road {
         &self.roads[id.0]
     }
@@ -312,6 +336,10 @@ impl Map {
         &self.traffic_signals[&id]
     }
 
+    pub fn get_roundabout(&self, id: IntersectionID) -> &ControlRoundabout {
+        &self.roundabouts[&id]
+    }
+
     pub fn lookup_turn_by_idx(&self, idx: usize) -> Option<TurnID> {
         self.turn_lookup.get(idx).cloned()
     }
@@ -441,6 +469,25 @@ impl Map {
         &self.name
     }
 
+    pub fn get_observed_travel_times(&self) -> Option<&TravelTimes> {
+        self.observed_travel_times.as_ref()
+    }
+
+    // Route cars using how long things took in a previous simulation, or go back to assuming
+    // everybody drives the speed limit with None.
+    pub fn set_observed_travel_times(&mut self, times: Option<TravelTimes>, timer: &mut Timer) {
+        if self.observed_travel_times == times {
+            return;
+        }
+        self.observed_travel_times = times;
+        self.pathfinder_dirty = true;
+        self.recalculate_pathfinding_after_edits(timer);
+    }
+
+    pub(crate) fn num_original_lanes(&self) -> usize {
+        self.num_original_lanes
+    }
+
     pub fn all_bus_stops(&self) -> &BTreeMap<BusStopID, BusStop> {
         &self.bus_stops
     }
@@ -613,6 +660,17 @@ impl Map {
         self.pathfinder.as_ref().unwrap().pathfind(req, self)
     }
 
+    // Slower than pathfind, but the cost of any lane or turn can be changed on the fly, like to
+    // avoid live traffic. Not for pedestrians.
+    pub fn pathfind_with_extra_costs(
+        &self,
+        req: PathRequest,
+        extra_cost: &dyn Fn(Traversable) -> usize,
+    ) -> Option<Path> {
+        assert!(req.constraints != PathConstraints::Pedestrian);
+        pathfind_with_extra_costs(&req, self, extra_cost)
+    }
+
     pub fn should_use_transit(
         &self,
         start: Position,
@@ -693,11 +751,33 @@ impl Map {
             IntersectionType::TrafficSignal => {
                 EditIntersection::TrafficSignal(self.get_traffic_signal(i).clone())
             }
+            IntersectionType::Roundabout => {
+                EditIntersection::Roundabout(self.get_roundabout(i).clone())
+            }
             IntersectionType::Construction => EditIntersection::Closed,
             IntersectionType::Border => unreachable!(),
         }
     }
 
+    pub fn get_r_edit(&self, r: RoadID) -> Vec<EditLane> {
+        let road = self.get_r(r);
+        let mut lanes = Vec::new();
+        for (fwd, children) in vec![
+            (true, &road.children_forwards),
+            (false, &road.children_backwards),
+        ] {
+            for (l, lt) in children {
+                lanes.push(EditLane {
+                    id: *l,
+                    lane_type: *lt,
+                    fwd,
+                    width: self.get_l(*l).width,
+                });
+            }
+        }
+        lanes
+    }
+
     pub fn save_edits(&mut self) {
         let mut edits = std::mem::replace(&mut self.edits, MapEdits::new(&self.name));
         edits.save(self);
@@ -781,9 +861,18 @@ impl Map {
             return;
         }
 
-        let mut pathfinder = self.pathfinder.take().unwrap();
-        pathfinder.apply_edits(self, timer);
-        self.pathfinder = Some(pathfinder);
+        if self.pathfinder_rebuild {
+            // The graphs have one node per lane (or sidewalk), so start over.
+            self.pathfinder = None;
+            let mut pathfinder = Pathfinder::new_without_transit(self, timer);
+            pathfinder.setup_walking_with_transit(self);
+            self.pathfinder = Some(pathfinder);
+            self.pathfinder_rebuild = false;
+        } else {
+            let mut pathfinder = self.pathfinder.take().unwrap();
+            pathfinder.apply_edits(self, timer);
+            self.pathfinder = Some(pathfinder);
+        }
 
         // Also recompute parking blackholes. This is cheap enough to do from scratch.
         timer.start("recompute parking blackholes");
@@ -818,12 +907,16 @@ fn make_half_map(
         boundary_polygon: raw.boundary_polygon.clone(),
         stop_signs: BTreeMap::new(),
         traffic_signals: BTreeMap::new(),
+        roundabouts: BTreeMap::new(),
         gps_bounds,
         bounds,
         driving_side: raw.driving_side,
         turn_lookup: Vec::new(),
         pathfinder: None,
         pathfinder_dirty: false,
+        pathfinder_rebuild: false,
+        num_original_lanes: 0,
+        observed_travel_times: None,
         name: raw.name.clone(),
         edits: MapEdits::new(&raw.name),
     };
@@ -851,6 +944,7 @@ fn make_half_map(
             incoming_lanes: Vec::new(),
             outgoing_lanes: Vec::new(),
             roads: i.roads.iter().map(|id| road_id_mapping[id]).collect(),
+            banned_turns: BTreeSet::new(),
         });
         intersection_id_mapping.insert(i.id, id);
     }
@@ -887,6 +981,9 @@ fn make_half_map(
             center_pts: r.trimmed_center_pts.clone(),
             src_i: i1,
             dst_i: i2,
+            speed_limit: Road::speed_limit_from_osm(&raw.roads[&r.id].osm_tags),
+            no_through_traffic: BTreeSet::new(),
+            parking_rules: ParkingRules::free(),
         };
 
         for lane in &r.lane_specs {
@@ -937,6 +1034,7 @@ fn make_half_map(
         }
         map.roads.push(road);
     }
+    map.num_original_lanes = map.lanes.len();
 
     for i in map.intersections.iter_mut() {
         if is_border(i, &map.lanes) {
@@ -954,6 +1052,13 @@ fn make_half_map(
         if i.is_closed() {
             continue;
         }
+        // Every intersection on the ring of a roundabout belongs to it, unless OSM says there's a
+        // traffic signal there.
+        if i.intersection_type != IntersectionType::TrafficSignal
+            && i.roads.iter().any(|r| map.roads[r.0].is_roundabout())
+        {
+            i.intersection_type = IntersectionType::Roundabout;
+        }
 
         if i.incoming_lanes.is_empty() || i.outgoing_lanes.is_empty() {
             timer.warn(format!("{:?} is orphaned!", i));
@@ -1116,6 +1221,7 @@ impl EditCmd {
 
                 map.stop_signs.remove(i);
                 map.traffic_signals.remove(i);
+                map.roundabouts.remove(i);
                 effects.changed_intersections.insert(*i);
                 match new {
                     EditIntersection::StopSign(ref ss) => {
@@ -1126,6 +1232,10 @@ impl EditCmd {
                         map.intersections[i.0].intersection_type = IntersectionType::TrafficSignal;
                         map.traffic_signals.insert(*i, ts.clone());
                     }
+                    EditIntersection::Roundabout(ref r) => {
+                        map.intersections[i.0].intersection_type = IntersectionType::Roundabout;
+                        map.roundabouts.insert(*i, r.clone());
+                    }
                     EditIntersection::Closed => {
                         map.intersections[i.0].intersection_type = IntersectionType::Construction;
                     }
@@ -1136,6 +1246,162 @@ impl EditCmd {
                 }
                 true
             }
+            EditCmd::ChangeBannedTurns { i, ref new, .. } => {
+                if &map.intersections[i.0].banned_turns == new {
+                    return false;
+                }
+
+                map.intersections[i.0].banned_turns = new.clone();
+                effects.changed_intersections.insert(*i);
+                recalculate_turns(*i, map, effects, timer);
+                true
+            }
+            EditCmd::ChangeSpeedLimit { id, new, .. } => {
+                if map.roads[id.0].speed_limit == *new {
+                    return false;
+                }
+
+                map.roads[id.0].speed_limit = *new;
+                effects.changed_roads.insert(*id);
+                true
+            }
+            EditCmd::ChangeAccessRestrictions { id, ref new, .. } => {
+                if &map.roads[id.0].no_through_traffic == new {
+                    return false;
+                }
+
+                map.roads[id.0].no_through_traffic = new.clone();
+                effects.changed_roads.insert(*id);
+                true
+            }
+            EditCmd::ChangeParkingRules { id, new, .. } => {
+                if map.roads[id.0].parking_rules == *new {
+                    return false;
+                }
+
+                map.roads[id.0].parking_rules = *new;
+                effects.changed_roads.insert(*id);
+                true
+            }
+            EditCmd::ChangeOffstreetParkingRules { b, new, .. } => {
+                if let Some(ref mut p) = map.buildings[b.0].parking {
+                    if p.rules == *new {
+                        return false;
+                    }
+                    p.rules = *new;
+                    true
+                } else {
+                    timer.warn(format!("Can't change parking rules of {}, it has none", b));
+                    false
+                }
+            }
+            EditCmd::ChangeRoadLanes { r, ref new, .. } => {
+                let r = *r;
+                if &map.get_r_edit(r) == new {
+                    return false;
+                }
+
+                let (i1, i2) = (map.roads[r.0].src_i, map.roads[r.0].dst_i);
+
+                // Detach all of the current lanes. The ones that aren't part of the new layout
+                // are left behind, closed off. LaneIDs index into lots of things, so they're never
+                // reused for something else.
+                let old_lanes = map.roads[r.0].all_lanes();
+                for l in &old_lanes {
+                    let l = *l;
+                    let lane = &mut map.lanes[l.0];
+                    map.intersections[lane.src_i.0]
+                        .outgoing_lanes
+                        .retain(|x| *x != l);
+                    map.intersections[lane.dst_i.0]
+                        .incoming_lanes
+                        .retain(|x| *x != l);
+                    lane.lane_type = LaneType::Construction;
+                }
+                map.roads[r.0].children_forwards.clear();
+                map.roads[r.0].children_backwards.clear();
+
+                let center = map.roads[r.0].center_pts.clone();
+                let mut width_fwd = Distance::ZERO;
+                let mut width_back = Distance::ZERO;
+                for spec in new {
+                    while map.lanes.len() <= spec.id.0 {
+                        // Filled out below, or left closed if an earlier new lane was undone
+                        let id = LaneID(map.lanes.len());
+                        map.lanes.push(Lane {
+                            id,
+                            parent: r,
+                            lane_type: LaneType::Construction,
+                            lane_center_pts: center.clone(),
+                            width: spec.width,
+                            src_i: i1,
+                            dst_i: i2,
+                            building_paths: Vec::new(),
+                            bus_stops: Vec::new(),
+                            parking_blackhole: None,
+                        });
+                    }
+                    // Placeholders might've been created for a different road
+                    let parent = map.lanes[spec.id.0].parent;
+                    if parent != r && map.roads[parent.0].all_lanes().contains(&spec.id) {
+                        panic!("{} already belongs to {}, not {}", spec.id, parent, r);
+                    }
+
+                    let (src_i, dst_i, unshifted_pts, other_lanes_width) = if spec.fwd {
+                        let w = width_fwd;
+                        width_fwd += spec.width;
+                        map.roads[r.0]
+                            .children_forwards
+                            .push((spec.id, spec.lane_type));
+                        (i1, i2, center.clone(), w)
+                    } else {
+                        let w = width_back;
+                        width_back += spec.width;
+                        map.roads[r.0]
+                            .children_backwards
+                            .push((spec.id, spec.lane_type));
+                        (i2, i1, center.reversed(), w)
+                    };
+                    let lane_center_pts = map
+                        .right_shift(unshifted_pts, other_lanes_width + spec.width / 2.0)
+                        .with_context(timer, format!("shift for {}", spec.id));
+
+                    let lane = &mut map.lanes[spec.id.0];
+                    lane.parent = r;
+                    lane.lane_type = spec.lane_type;
+                    lane.width = spec.width;
+                    lane.src_i = src_i;
+                    lane.dst_i = dst_i;
+                    lane.lane_center_pts = lane_center_pts;
+                    map.intersections[src_i.0].outgoing_lanes.push(spec.id);
+                    map.intersections[dst_i.0].incoming_lanes.push(spec.id);
+                }
+                if old_lanes.len() != new.len()
+                    || old_lanes
+                        .iter()
+                        .any(|l| new.iter().all(|spec| spec.id != *l))
+                {
+                    map.pathfinder_rebuild = true;
+                }
+                fix_positions_after_resize(r, map);
+
+                for i in vec![i1, i2] {
+                    map.intersections[i.0].polygon = intersection_polygon(map, i, timer);
+                    effects.changed_intersections.insert(i);
+                }
+
+                effects.changed_roads.insert(r);
+                recalculate_turns(i1, map, effects, timer);
+                recalculate_turns(i2, map, effects, timer);
+                true
+            }
+            EditCmd::Batch(ref cmds) => {
+                let mut changed = false;
+                for cmd in cmds {
+                    changed |= cmd.apply(effects, map, timer);
+                }
+                changed
+            }
         }
     }
 
@@ -1171,6 +1437,148 @@ impl EditCmd {
                 new: old.clone(),
             }
             .apply(effects, map, timer),
+            EditCmd::ChangeBannedTurns {
+                i,
+                ref old,
+                ref new,
+            } => EditCmd::ChangeBannedTurns {
+                i: *i,
+                old: new.clone(),
+                new: old.clone(),
+            }
+            .apply(effects, map, timer),
+            EditCmd::ChangeSpeedLimit { id, old, new } => EditCmd::ChangeSpeedLimit {
+                id: *id,
+                old: *new,
+                new: *old,
+            }
+            .apply(effects, map, timer),
+            EditCmd::ChangeAccessRestrictions {
+                id,
+                ref old,
+                ref new,
+            } => EditCmd::ChangeAccessRestrictions {
+                id: *id,
+                old: new.clone(),
+                new: old.clone(),
+            }
+            .apply(effects, map, timer),
+            EditCmd::ChangeParkingRules { id, old, new } => EditCmd::ChangeParkingRules {
+                id: *id,
+                old: *new,
+                new: *old,
+            }
+            .apply(effects, map, timer),
+            EditCmd::ChangeOffstreetParkingRules { b, old, new } => {
+                EditCmd::ChangeOffstreetParkingRules {
+                    b: *b,
+                    old: *new,
+                    new: *old,
+                }
+                .apply(effects, map, timer)
+            }
+            EditCmd::ChangeRoadLanes {
+                r,
+                ref old,
+                ref new,
+            } => EditCmd::ChangeRoadLanes {
+                r: *r,
+                old: new.clone(),
+                new: old.clone(),
+            }
+            .apply(effects, map, timer),
+            EditCmd::Batch(ref cmds) => {
+                let mut changed = false;
+                for cmd in cmds.iter().rev() {
+                    changed |= cmd.undo(effects, map, timer);
+                }
+                changed
+            }
+        }
+    }
+}
+
+// Where a road meets an intersection, the corners of its forwards and backwards edges. The
+// intersection's polygon is built from these.
+fn road_corners(map: &Map, r: RoadID, i: IntersectionID, timer: &mut Timer) -> (Pt2D, Pt2D) {
+    let road = &map.roads[r.0];
+    let fwd = map
+        .right_shift(road.center_pts.clone(), road.width_fwd(map))
+        .with_context(timer, format!("corners of {}", r));
+    let back = map
+        .left_shift(road.center_pts.clone(), road.width_back(map))
+        .with_context(timer, format!("corners of {}", r));
+    if road.dst_i == i {
+        (fwd.last_pt(), back.last_pt())
+    } else {
+        (fwd.first_pt(), back.first_pt())
+    }
+}
+
+// Rebuilds an intersection's polygon from the corners of the roads touching it, after one of them
+// changed width. Unlike the initial geometry, roads aren't trimmed back again, and the polygon just
+// connects their ends, in order around the intersection.
+fn intersection_polygon(map: &Map, i: IntersectionID, timer: &mut Timer) -> Polygon {
+    let center = map.intersections[i.0].polygon.center();
+    let mut pts = Vec::new();
+    for r in &map.intersections[i.0].roads {
+        let (fwd, back) = road_corners(map, *r, i, timer);
+        pts.push(fwd);
+        pts.push(back);
+    }
+    if map.intersections[i.0].roads.len() == 1 {
+        // Dead-ends cover a bit past the end of the road.
+        let road = &map.roads[map.intersections[i.0].roads.iter().next().unwrap().0];
+        let angle = if road.dst_i == i {
+            road.center_pts.last_line().angle()
+        } else {
+            road.center_pts.first_line().angle().opposite()
+        };
+        let extra: Vec<Pt2D> = pts
+            .iter()
+            .map(|pt| pt.project_away(DEADEND_LENGTH, angle))
+            .collect();
+        pts.extend(extra);
+    }
+    pts.sort_by_key(|pt| (center.angle_to(*pt).normalized_degrees() * 100.0) as i64);
+    pts.dedup_by(|a, b| a.approx_eq(*b, Distance::meters(0.1)));
+    pts.push(pts[0]);
+    Polygon::new(&pts)
+}
+
+// After a road's lanes change, their lengths might shift a bit. Keep buildings and bus stops
+// along the road within bounds.
+fn fix_positions_after_resize(r: RoadID, map: &mut Map) {
+    let clamp = |pos: Position, map: &Map| {
+        let len = map.get_l(pos.lane()).length();
+        if pos.dist_along() > len {
+            Position::new(pos.lane(), len)
+        } else {
+            pos
+        }
+    };
+
+    for l in map.roads[r.0].all_lanes() {
+        for b in map.lanes[l.0].building_paths.clone() {
+            let sidewalk = clamp(map.buildings[b.0].front_path.sidewalk, map);
+            let pt = sidewalk.pt(map);
+            let bldg = &mut map.buildings[b.0];
+            bldg.front_path.sidewalk = sidewalk;
+            if let Some(line) = Line::maybe_new(bldg.front_path.line.pt1(), pt) {
+                bldg.front_path.line = line;
+            }
+        }
+        for bs in map.lanes[l.0].bus_stops.clone() {
+            let sidewalk_pos = clamp(map.bus_stops[&bs].sidewalk_pos, map);
+            map.bus_stops.get_mut(&bs).unwrap().sidewalk_pos = sidewalk_pos;
+        }
+    }
+    for idx in 0..map.buildings.len() {
+        if let Some(pos) = map.buildings[idx].parking.as_ref().map(|p| p.driving_pos) {
+            if map.lanes[pos.lane().0].parent == r {
+                let driving_pos = clamp(pos, map);
+                map.buildings[idx].parking.as_mut().unwrap().driving_pos = driving_pos;
+            }
         }
     }
 }
@@ -1214,8 +1622,9 @@ fn recalculate_turns(
     // TODO Deal with turn_lookup
 
     match i.intersection_type {
-        // Stop sign policy doesn't depend on incoming lane types. Leave edits alone.
-        IntersectionType::StopSign => {}
+        // Stop sign and roundabout policies don't depend on incoming lane types. Leave edits
+        // alone.
+        IntersectionType::StopSign | IntersectionType::Roundabout => {}
         IntersectionType::TrafficSignal => {
             map.traffic_signals
                 .insert(id, ControlTrafficSignal::new(map, id, timer));
@@ -1223,3 +1632,424 @@ fn recalculate_turns(
         IntersectionType::Border | IntersectionType::Construction => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osm;
+    use crate::raw::{RawIntersection, RawRoad};
+    use crate::{PhaseType, TurnPriority};
+    use geom::{Duration, LonLat};
+
+    // A four-way intersection with a traffic signal and two lanes each way. For DrivingSide::Left,
+    // the geometry and OSM tags are mirrored left-to-right, so the map should be the exact mirror
+    // image of the DrivingSide::Right one.
+    fn four_way(driving_side: DrivingSide) -> Map {
+        let mirror = |x: f64, y: f64| match driving_side {
+            DrivingSide::Right => Pt2D::new(x, y),
+            DrivingSide::Left => Pt2D::new(400.0 - x, y),
+        };
+        let (near, far) = match driving_side {
+            DrivingSide::Right => ("right", "left"),
+            DrivingSide::Left => ("left", "right"),
+        };
+
+        let mut raw = RawMap::blank("four_way".to_string());
+        raw.driving_side = driving_side;
+        raw.gps_bounds.update(LonLat::new(-122.0, 47.0));
+        raw.gps_bounds.update(LonLat::new(-121.99, 47.01));
+        raw.boundary_polygon = raw.gps_bounds.to_bounds().get_rectangle();
+
+        let center = OriginalIntersection { osm_node_id: 0 };
+        raw.intersections.insert(
+            center,
+            RawIntersection {
+                point: mirror(200.0, 200.0),
+                intersection_type: IntersectionType::TrafficSignal,
+                elevation: Distance::ZERO,
+            },
+        );
+        for (id, (x, y)) in vec![(200.0, 50.0), (50.0, 200.0), (200.0, 350.0), (350.0, 200.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let id = (id + 1) as i64;
+            let i2 = OriginalIntersection { osm_node_id: id };
+            raw.intersections.insert(
+                i2,
+                RawIntersection {
+                    point: mirror(x, y),
+                    intersection_type: IntersectionType::Border,
+                    elevation: Distance::ZERO,
+                },
+            );
+
+            let mut osm_tags = BTreeMap::new();
+            osm_tags.insert(osm::OSM_WAY_ID.to_string(), id.to_string());
+            osm_tags.insert(osm::NAME.to_string(), format!("Road {}", id));
+            osm_tags.insert(osm::HIGHWAY.to_string(), "primary".to_string());
+            osm_tags.insert("lanes".to_string(), "4".to_string());
+            osm_tags.insert(osm::SIDEWALK.to_string(), "both".to_string());
+            // One road has parking on one side and a bike lane on the other.
+            if id == 1 {
+                osm_tags.insert(format!("parking:lane:{}", near), "parallel".to_string());
+                osm_tags.insert(format!("cycleway:{}", far), "lane".to_string());
+            }
+            raw.roads.insert(
+                OriginalRoad {
+                    osm_way_id: id,
+                    i1: center,
+                    i2,
+                },
+                RawRoad {
+                    center_points: vec![mirror(200.0, 200.0), mirror(x, y)],
+                    osm_tags,
+                    turn_restrictions: Vec::new(),
+                },
+            );
+        }
+
+        Map::create_from_raw(raw, &mut Timer::throwaway())
+    }
+
+    // Describe each vehicle turn independently of the driving side: the road and lane offset on
+    // both ends, and the turn type in terms of DrivingSide::Right.
+    fn turn_structure(map: &Map) -> BTreeSet<(i64, bool, usize, i64, bool, usize, TurnType)> {
+        let lane = |l: LaneID| {
+            let r = map.get_parent(l);
+            let (fwds, offset) = r.dir_and_offset(l);
+            (r.orig_id.osm_way_id, fwds, offset)
+        };
+        map.all_turns()
+            .values()
+            .map(|t| {
+                let (r1, fwds1, offset1) = lane(t.id.src);
+                let (r2, fwds2, offset2) = lane(t.id.dst);
+                let tt = map.get_driving_side().turn_type(t.turn_type);
+                (r1, fwds1, offset1, r2, fwds2, offset2, tt)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lane_placement() {
+        let right = four_way(DrivingSide::Right);
+        let left = four_way(DrivingSide::Left);
+
+        let road = right.get_r(RoadID(0));
+        assert_eq!(road.orig_id.osm_way_id, 1);
+        assert_eq!(
+            road.get_lane_types(),
+            (
+                vec![
+                    LaneType::Driving,
+                    LaneType::Driving,
+                    LaneType::Parking,
+                    LaneType::Sidewalk
+                ],
+                vec![
+                    LaneType::Driving,
+                    LaneType::Driving,
+                    LaneType::Biking,
+                    LaneType::Sidewalk
+                ]
+            )
+        );
+        assert_eq!(
+            road.get_lane_types(),
+            left.get_r(RoadID(0)).get_lane_types()
+        );
+
+        // Road 1 is vertical, so every lane is a constant distance to the side. The mirrored lanes
+        // should be on the opposite side.
+        for (l1, l2) in right.all_lanes().iter().zip(left.all_lanes().iter()) {
+            assert_eq!(l1.lane_type, l2.lane_type);
+            if l1.parent == RoadID(0) {
+                let x1 = l1.lane_center_pts.middle().x();
+                let x2 = l2.lane_center_pts.middle().x();
+                assert!(
+                    (x1 + x2 - 400.0).abs() < 0.1,
+                    "{} is at x={} driving on the right, but x={} on the left",
+                    l1.id,
+                    x1,
+                    x2
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_turns_and_phases() {
+        let right = four_way(DrivingSide::Right);
+        let left = four_way(DrivingSide::Left);
+
+        // Turns that don't cross oncoming traffic only start from the lane closest to the curb.
+        let structure = turn_structure(&right);
+        assert!(structure
+            .iter()
+            .any(|(_, _, offset, _, _, _, tt)| *tt == TurnType::Right && *offset == 1));
+        assert!(!structure
+            .iter()
+            .any(|(_, _, offset, _, _, _, tt)| *tt == TurnType::Right && *offset == 0));
+        assert!(!structure
+            .iter()
+            .any(|(_, _, offset, _, _, _, tt)| *tt == TurnType::Left && *offset == 1));
+        assert_eq!(structure, turn_structure(&left));
+
+        // Mirroring reverses the order of roads around the intersection, so policies like one phase
+        // per road can order their phases differently.
+        let right_policies = ControlTrafficSignal::get_possible_policies(&right, IntersectionID(0));
+        let left_policies = ControlTrafficSignal::get_possible_policies(&left, IntersectionID(0));
+        assert_eq!(right_policies.len(), left_policies.len());
+        for ((name1, ts1), (name2, ts2)) in right_policies.iter().zip(left_policies.iter()) {
+            assert_eq!(name1, name2);
+            assert_eq!(ts1.phases.len(), ts2.phases.len(), "{}", name1);
+            for phase in &ts1.phases {
+                assert!(ts2.phases.contains(phase), "{}: {:?}", name1, phase);
+            }
+        }
+    }
+
+    // A ring of four one-way roads, each node also connected to a two-way road leading out to a
+    // border. OSM says the third node on the ring has a traffic signal.
+    fn roundabout() -> Map {
+        let mut raw = RawMap::blank("roundabout".to_string());
+        raw.gps_bounds.update(LonLat::new(-122.0, 47.0));
+        raw.gps_bounds.update(LonLat::new(-121.99, 47.01));
+        raw.boundary_polygon = raw.gps_bounds.to_bounds().get_rectangle();
+
+        let ring = vec![
+            (300.0, 200.0),
+            (200.0, 100.0),
+            (100.0, 200.0),
+            (200.0, 300.0),
+        ];
+        let outside = vec![
+            (450.0, 200.0),
+            (200.0, -50.0),
+            (-50.0, 200.0),
+            (200.0, 450.0),
+        ];
+        let mut add_road = |id: i64, i1: i64, i2: i64, pts: Vec<Pt2D>, roundabout: bool| {
+            let mut osm_tags = BTreeMap::new();
+            osm_tags.insert(osm::OSM_WAY_ID.to_string(), id.to_string());
+            osm_tags.insert(osm::HIGHWAY.to_string(), "primary".to_string());
+            if roundabout {
+                osm_tags.insert("junction".to_string(), "roundabout".to_string());
+                osm_tags.insert("oneway".to_string(), "yes".to_string());
+                osm_tags.insert("lanes".to_string(), "1".to_string());
+                osm_tags.insert(osm::SIDEWALK.to_string(), "none".to_string());
+            } else {
+                osm_tags.insert("lanes".to_string(), "2".to_string());
+                osm_tags.insert(osm::SIDEWALK.to_string(), "both".to_string());
+            }
+            raw.roads.insert(
+                OriginalRoad {
+                    osm_way_id: id,
+                    i1: OriginalIntersection { osm_node_id: i1 },
+                    i2: OriginalIntersection { osm_node_id: i2 },
+                },
+                RawRoad {
+                    center_points: pts,
+                    osm_tags,
+                    turn_restrictions: Vec::new(),
+                },
+            );
+        };
+        for idx in 0..4 {
+            let node = (idx + 1) as i64;
+            let next = ((idx + 1) % 4 + 1) as i64;
+            let (x1, y1) = ring[idx];
+            let (x2, y2) = ring[(idx + 1) % 4];
+            let (x3, y3) = outside[idx];
+            add_road(
+                node,
+                node,
+                next,
+                vec![Pt2D::new(x1, y1), Pt2D::new(x2, y2)],
+                true,
+            );
+            add_road(
+                node + 4,
+                node,
+                node + 4,
+                vec![Pt2D::new(x1, y1), Pt2D::new(x3, y3)],
+                false,
+            );
+        }
+        for idx in 0..4 {
+            let node = (idx + 1) as i64;
+            let (x1, y1) = ring[idx];
+            let (x3, y3) = outside[idx];
+            raw.intersections.insert(
+                OriginalIntersection { osm_node_id: node },
+                RawIntersection {
+                    point: Pt2D::new(x1, y1),
+                    intersection_type: if node == 3 {
+                        IntersectionType::TrafficSignal
+                    } else {
+                        IntersectionType::StopSign
+                    },
+                    elevation: Distance::ZERO,
+                },
+            );
+            raw.intersections.insert(
+                OriginalIntersection {
+                    osm_node_id: node + 4,
+                },
+                RawIntersection {
+                    point: Pt2D::new(x3, y3),
+                    intersection_type: IntersectionType::Border,
+                    elevation: Distance::ZERO,
+                },
+            );
+        }
+
+        Map::create_from_raw(raw, &mut Timer::throwaway())
+    }
+
+    #[test]
+    fn test_roundabout_ring_and_priority() {
+        let map = roundabout();
+        let node = |id: i64| {
+            map.all_intersections()
+                .iter()
+                .find(|i| i.orig_id.osm_node_id == id)
+                .unwrap()
+                .id
+        };
+
+        // The signal stays, and the rest of the ring becomes one roundabout. It's still connected
+        // the long way around.
+        assert_eq!(
+            map.get_i(node(3)).intersection_type,
+            IntersectionType::TrafficSignal
+        );
+        let expected: BTreeSet<IntersectionID> =
+            vec![node(1), node(2), node(4)].into_iter().collect();
+        for id in &expected {
+            assert!(map.get_i(*id).is_roundabout());
+            assert_eq!(map.get_roundabout(*id).ring(&map), expected);
+        }
+
+        // Circulating traffic has priority over everybody entering from the road to the border.
+        let control = map.get_roundabout(node(1));
+        assert_eq!(control.circulating.len(), 2);
+        let mut vehicle_turns = 0;
+        for turn in map.get_turns_in_intersection(node(1)) {
+            let priority = control.get_priority(turn.id, &map);
+            if turn.between_sidewalks() {
+                assert_eq!(priority, TurnPriority::Protected);
+                continue;
+            }
+            vehicle_turns += 1;
+            let from_ring = map.get_parent(turn.id.src).is_roundabout();
+            assert_eq!(
+                priority,
+                if from_ring {
+                    TurnPriority::Protected
+                } else {
+                    TurnPriority::Yield
+                }
+            );
+        }
+        assert!(vehicle_turns > 0);
+    }
+
+    // When part of a batch is skipped, later commands using a lane that the batch added have to be
+    // skipped too.
+    #[test]
+    fn test_skipped_batch_forgets_added_lanes() {
+        let map = four_way(DrivingSide::Right);
+        let r = RoadID(0);
+        let added = LaneID(map.num_original_lanes());
+
+        let old = map.get_r_edit(r);
+        let mut new = old.clone();
+        new.push(EditLane {
+            id: added,
+            lane_type: LaneType::Driving,
+            fwd: true,
+            width: NORMAL_LANE_THICKNESS,
+        });
+        let mut edits = MapEdits::new(map.get_name());
+        edits.commands.push(EditCmd::Batch(vec![
+            EditCmd::ChangeRoadLanes { r, new, old },
+            // Borders can't be changed, so this part doesn't apply
+            EditCmd::ChangeIntersection {
+                i: map.get_r(r).dst_i,
+                new: EditIntersection::Closed,
+                old: EditIntersection::Closed,
+            },
+        ]));
+        edits.commands.push(EditCmd::ChangeLaneType {
+            id: added,
+            lt: LaneType::Bus,
+            orig_lt: LaneType::Driving,
+        });
+
+        let perma = PermanentMapEdits::to_permanent(&edits, &map);
+        let edits = perma.from_permanent(&map, &mut Timer::throwaway()).unwrap();
+        assert!(edits.commands.is_empty(), "{:?}", edits.commands);
+    }
+
+    // Edits saved before the format was versioned refer to IDs directly, and every signal phase
+    // just had a duration.
+    #[test]
+    fn test_legacy_signal_edits() {
+        let map = four_way(DrivingSide::Right);
+        let signal = map.get_traffic_signal(IntersectionID(0));
+        let legacy_signal = |duration: f64| {
+            let phases: Vec<String> = signal
+                .phases
+                .iter()
+                .map(|p| {
+                    format!(
+                        r#"{{"protected_groups": {}, "yield_groups": {}, "duration": {}}}"#,
+                        abstutil::to_json(&p.protected_groups),
+                        abstutil::to_json(&p.yield_groups),
+                        duration
+                    )
+                })
+                .collect();
+            format!(
+                r#"{{"TrafficSignal": {{"id": 0, "phases": [{}], "offset": 5.0}}}}"#,
+                phases.join(", ")
+            )
+        };
+        let json = format!(
+            r#"{{
+                "map_name": "four_way",
+                "edits_name": "legacy",
+                "commands": [{{"ChangeIntersection": {{"i": 0, "new": {}, "old": {}}}}}],
+                "proposal_description": []
+            }}"#,
+            legacy_signal(42.0),
+            legacy_signal(30.0)
+        );
+        let path = std::env::temp_dir()
+            .join("legacy_signal_edits.json")
+            .to_str()
+            .unwrap()
+            .to_string();
+        std::fs::write(&path, json).unwrap();
+
+        let edits = MapEdits::load_from_file(&map, path, &mut Timer::throwaway()).unwrap();
+        assert_eq!(edits.commands.len(), 1);
+        match edits.commands[0] {
+            EditCmd::ChangeIntersection {
+                new: EditIntersection::TrafficSignal(ref ts),
+                ..
+            } => {
+                assert_eq!(ts.offset, Duration::seconds(5.0));
+                assert_eq!(ts.phases.len(), signal.phases.len());
+                for (phase, orig) in ts.phases.iter().zip(signal.phases.iter()) {
+                    assert_eq!(phase.protected_groups, orig.protected_groups);
+                    assert_eq!(phase.yield_groups, orig.yield_groups);
+                    assert_eq!(phase.phase_type, PhaseType::Fixed(Duration::seconds(42.0)));
+                }
+            }
+            ref cmd => panic!("Expected a traffic signal change, not {:?}", cmd),
+        }
+    }
+}
@@ -1,10 +1,56 @@
 use crate::pathfind::node_map::{deserialize_nodemap, NodeMap};
-use crate::{Lane, LaneID, Map, Path, PathConstraints, PathRequest, PathStep, Turn, TurnID};
+use crate::{
+    biking_speed_factor, Lane, LaneID, Map, Path, PathConstraints, PathRequest, PathStep,
+    Traversable, Turn, TurnID,
+};
+use abstutil::{deserialize_btreemap, serialize_btreemap};
 use fast_paths::{deserialize_32, serialize_32, FastGraph, InputGraph, PathCalculator};
+use geom::Duration;
 use serde_derive::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
 use thread_local::ThreadLocal;
 
+// How long cars took to cross lanes and turns in some run of the simulation, including waiting at
+// the end. Anything missing falls back to the time at the speed limit.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TravelTimes {
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub lanes: BTreeMap<LaneID, Duration>,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    pub turns: BTreeMap<TurnID, Duration>,
+}
+
+impl TravelTimes {
+    pub fn new() -> TravelTimes {
+        TravelTimes {
+            lanes: BTreeMap::new(),
+            turns: BTreeMap::new(),
+        }
+    }
+
+    // Move weight (between 0 and 1) of the way towards the other times. Averaging runs like this
+    // keeps iterative assignment from flip-flopping between two sets of routes.
+    pub fn blend(&mut self, other: &TravelTimes, weight: f64) {
+        assert!(weight >= 0.0 && weight <= 1.0);
+        for (l, t) in &other.lanes {
+            let old = self.lanes.get(l).cloned().unwrap_or(*t);
+            self.lanes.insert(*l, old * (1.0 - weight) + *t * weight);
+        }
+        for (turn, t) in &other.turns {
+            let old = self.turns.get(turn).cloned().unwrap_or(*t);
+            self.turns.insert(*turn, old * (1.0 - weight) + *t * weight);
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct VehiclePathfinder {
     #[serde(serialize_with = "serialize_32", deserialize_with = "deserialize_32")]
@@ -121,20 +167,88 @@ fn make_input_graph(
     input_graph
 }
 
+// Plain Dijkstra's, for when the costs change too often to rebuild the contraction hierarchy. The
+// extra cost of every lane and turn is added to the usual cost, in the same units.
+pub fn pathfind_with_extra_costs(
+    req: &PathRequest,
+    map: &Map,
+    extra_cost: &dyn Fn(Traversable) -> usize,
+) -> Option<Path> {
+    let start = req.start.lane();
+    let end = req.end.lane();
+    if start == end {
+        return None;
+    }
+
+    let mut backrefs: HashMap<LaneID, TurnID> = HashMap::new();
+    let mut best_cost: HashMap<LaneID, usize> = HashMap::new();
+    // Tie breaker is lane ID, arbitrary but deterministic.
+    let mut queue: BinaryHeap<(Reverse<usize>, LaneID)> = BinaryHeap::new();
+    best_cost.insert(start, 0);
+    queue.push((Reverse(0), start));
+
+    while let Some((Reverse(cost_so_far), current)) = queue.pop() {
+        if current == end {
+            let mut steps = vec![PathStep::Lane(end)];
+            let mut current = end;
+            while current != start {
+                let turn = backrefs[&current];
+                steps.push(PathStep::Turn(turn));
+                steps.push(PathStep::Lane(turn.src));
+                current = turn.src;
+            }
+            steps.reverse();
+            return Some(Path::new(map, steps, req.end.dist_along()));
+        }
+        if best_cost[&current] < cost_so_far {
+            continue;
+        }
+
+        let lane = map.get_l(current);
+        for turn in map.get_turns_for(current, req.constraints) {
+            let next_cost = cost_so_far
+                + cost(lane, turn, req.constraints, map)
+                + extra_cost(Traversable::Lane(current))
+                + extra_cost(Traversable::Turn(turn.id));
+            if best_cost
+                .get(&turn.id.dst)
+                .map(|c| next_cost < *c)
+                .unwrap_or(true)
+            {
+                best_cost.insert(turn.id.dst, next_cost);
+                backrefs.insert(turn.id.dst, turn.id);
+                queue.push((Reverse(next_cost), turn.id.dst));
+            }
+        }
+    }
+
+    None
+}
+
 pub fn cost(lane: &Lane, turn: &Turn, constraints: PathConstraints, map: &Map) -> usize {
     // TODO Could cost turns differently.
 
-    match constraints {
+    let base = match constraints {
         PathConstraints::Car => {
             // Prefer slightly longer route on faster roads
-            let t1 = lane.length() / map.get_r(lane.parent).get_speed_limit();
-            let t2 = turn.geom.length() / map.get_parent(turn.id.dst).get_speed_limit();
+            let mut t1 = lane.length() / map.get_r(lane.parent).get_speed_limit();
+            let mut t2 = turn.geom.length() / map.get_parent(turn.id.dst).get_speed_limit();
+            // But if a previous run measured how long things really take, trust that instead.
+            if let Some(times) = map.get_observed_travel_times() {
+                if let Some(t) = times.lanes.get(&lane.id) {
+                    t1 = *t;
+                }
+                if let Some(t) = times.turns.get(&turn.id) {
+                    t2 = *t;
+                }
+            }
             (t1 + t2).inner_seconds().round() as usize
         }
         PathConstraints::Bike => {
             // Speed limits don't matter, bikes are usually constrained by their own speed limit.
-            let dist = lane.length() + turn.geom.length();
-            // TODO Elevation gain is bad, loss is good.
+            // Elevation does, though; climbing a hill is like biking much further.
+            let dist =
+                lane.length() / biking_speed_factor(lane.percent_grade(map)) + turn.geom.length();
             // TODO If we're on a driving lane, higher speed limit is worse.
             // TODO Bike lanes next to parking is dangerous.
 
@@ -165,5 +279,24 @@ pub fn cost(lane: &Lane, turn: &Turn, constraints: PathConstraints, map: &Map) -
             (lt_penalty * (t1 + t2)).inner_seconds().round() as usize
         }
         PathConstraints::Pedestrian => unreachable!(),
+    };
+
+    // Entering a road closed to through traffic is expensive, so only trips starting or ending
+    // inside will do it. Moving around inside or leaving is free.
+    if map
+        .get_parent(turn.id.dst)
+        .no_through_traffic
+        .contains(&constraints)
+        && !map
+            .get_r(lane.parent)
+            .no_through_traffic
+            .contains(&constraints)
+    {
+        base + NO_THROUGH_TRAFFIC_PENALTY
+    } else {
+        base
     }
 }
+
+// In seconds for cars and buses, meters for bikes. Either way, a long detour.
+const NO_THROUGH_TRAFFIC_PENALTY: usize = 10_000;
@@ -2,8 +2,9 @@ mod driving;
 mod node_map;
 mod walking;
 
-pub use self::driving::cost;
+pub(crate) use self::driving::pathfind_with_extra_costs;
 use self::driving::VehiclePathfinder;
+pub use self::driving::{cost, TravelTimes};
 use self::walking::SidewalkPathfinder;
 use crate::{
     osm, BusRouteID, BusStopID, Lane, LaneID, LaneType, Map, Position, Traversable, TurnID,
@@ -203,6 +204,40 @@ impl Path {
         }
     }
 
+    // Swap out everything after the current step, keeping track of progress so far. The new path
+    // has to start with the current step or the one after it.
+    pub fn reroute(&mut self, new_path: Path, map: &Map) {
+        let current = self.current_step();
+        let lanes_crossed = self.lanes_crossed_so_far();
+        self.total_length = self.crossed_so_far + new_path.total_length;
+        self.total_lanes = lanes_crossed + new_path.total_lanes;
+        self.end_dist = new_path.end_dist;
+        self.steps = new_path.steps;
+        if self.steps[0] != current {
+            assert_eq!(self.steps[0], PathStep::Lane(current.as_turn().dst));
+            self.total_length += current.as_traversable().length(map);
+            self.steps.push_front(current);
+        }
+    }
+
+    // Give up on everything after the first num_steps, stopping at the end of the last one kept.
+    pub fn truncate(&mut self, num_steps: usize, map: &Map) {
+        assert!(num_steps > 0);
+        while self.steps.len() > num_steps {
+            let step = self.steps.pop_back().unwrap();
+            self.total_length -= step.as_traversable().length(map);
+            match step {
+                PathStep::Lane(_) | PathStep::ContraflowLane(_) => self.total_lanes -= 1,
+                _ => {}
+            };
+        }
+        self.end_dist = self.last_step().as_traversable().length(map);
+    }
+
+    pub fn end_dist(&self) -> Distance {
+        self.end_dist
+    }
+
     pub fn current_step(&self) -> PathStep {
         self.steps[0]
     }
@@ -316,7 +351,7 @@ impl Path {
 
 // Who's asking for a path?
 // TODO This is an awful name.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PathConstraints {
     Pedestrian,
     Car,
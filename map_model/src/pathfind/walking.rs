@@ -245,16 +245,13 @@ fn make_input_graph(
         // Connect each adjacent stop along a route, with the cost based on how long it'll take a
         // bus to drive between the stops. Optimistically assume no waiting time at a stop.
         for route in map.get_all_bus_routes() {
-            for (stop1, stop2) in
-                route
-                    .stops
-                    .iter()
-                    .zip(route.stops.iter().skip(1))
-                    .chain(std::iter::once((
-                        route.stops.last().unwrap(),
-                        &route.stops[0],
-                    )))
-            {
+            let mut pairs: Vec<(&BusStopID, &BusStopID)> =
+                route.stops.iter().zip(route.stops.iter().skip(1)).collect();
+            // Scheduled buses vanish after the last stop instead of looping around.
+            if !route.is_scheduled() {
+                pairs.push((route.stops.last().unwrap(), &route.stops[0]));
+            }
+            for (stop1, stop2) in pairs {
                 if let Some((_, driving_cost)) = bus_graph.pathfind(
                     &PathRequest {
                         start: map.get_bs(*stop1).driving_pos,
@@ -1,5 +1,5 @@
 use crate::make::get_lane_types;
-use crate::{osm, AreaType, IntersectionType, OffstreetParking, RoadSpec};
+use crate::{osm, AreaType, IntersectionType, OffstreetParking, RoadSpec, TurnType};
 use abstutil::{deserialize_btreemap, retain_btreemap, serialize_btreemap, Error, Timer, Warn};
 use geom::{Angle, Distance, GPSBounds, Line, PolyLine, Polygon, Pt2D};
 use gtfs::Route;
@@ -322,7 +322,10 @@ impl RawMap {
         };
         let mut roads = BTreeMap::new();
         for r in &i.roads {
-            roads.insert(*r, initial::Road::new(*r, &self.roads[r]));
+            roads.insert(
+                *r,
+                initial::Road::new(*r, &self.roads[r], self.driving_side),
+            );
         }
 
         let (i_pts, debug) =
@@ -536,8 +539,8 @@ pub struct RawRoad {
 }
 
 impl RawRoad {
-    pub fn get_spec(&self) -> RoadSpec {
-        let (fwd, back) = get_lane_types(&self.osm_tags);
+    pub fn get_spec(&self, driving_side: DrivingSide) -> RoadSpec {
+        let (fwd, back) = get_lane_types(&self.osm_tags, driving_side);
         RoadSpec { fwd, back }
     }
 
@@ -694,4 +697,27 @@ impl DrivingSide {
             DrivingSide::Left => a.opposite(),
         }
     }
+
+    // A right turn here is the one that doesn't cross oncoming traffic, and changing lanes to the
+    // right moves towards the curb.
+    pub fn turn_type(self, tt: TurnType) -> TurnType {
+        match (self, tt) {
+            (DrivingSide::Left, TurnType::Right) => TurnType::Left,
+            (DrivingSide::Left, TurnType::Left) => TurnType::Right,
+            (DrivingSide::Left, TurnType::LaneChangeRight) => TurnType::LaneChangeLeft,
+            (DrivingSide::Left, TurnType::LaneChangeLeft) => TurnType::LaneChangeRight,
+            _ => tt,
+        }
+    }
+
+    // OSM tags like sidewalk=right and cycleway:left are relative to the direction of the way, so
+    // they don't depend on the driving side. This is the side of the way where the forwards (or
+    // backwards) lanes are.
+    pub fn osm_side(self, fwds: bool) -> &'static str {
+        if fwds == (self == DrivingSide::Right) {
+            "right"
+        } else {
+            "left"
+        }
+    }
 }
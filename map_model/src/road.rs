@@ -1,9 +1,9 @@
 use crate::raw::{OriginalRoad, RestrictionType};
-use crate::{osm, BusStopID, IntersectionID, LaneID, LaneType, Map, PathConstraints};
+use crate::{osm, BusStopID, IntersectionID, LaneID, LaneType, Map, ParkingRules, PathConstraints};
 use abstutil::{Error, Warn};
 use geom::{Distance, PolyLine, Polygon, Speed};
 use serde_derive::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
 
 // TODO reconsider pub usize. maybe outside world shouldnt know.
@@ -114,6 +114,13 @@ pub struct Road {
     pub center_pts: PolyLine,
     pub src_i: IntersectionID,
     pub dst_i: IntersectionID,
+
+    // Initially from OSM tags, but map edits can change these.
+    pub speed_limit: Speed,
+    // These vehicles can still start or end trips here, but pathfinding avoids passing through.
+    pub no_through_traffic: BTreeSet<PathConstraints>,
+    // Applies to all parking lanes on the road
+    pub parking_rules: ParkingRules,
 }
 
 impl Road {
@@ -211,8 +218,11 @@ impl Road {
     }
 
     pub fn get_speed_limit(&self) -> Speed {
-        // TODO Should probably cache this
-        if let Some(limit) = self.osm_tags.get(osm::MAXSPEED) {
+        self.speed_limit
+    }
+
+    pub(crate) fn speed_limit_from_osm(osm_tags: &BTreeMap<String, String>) -> Speed {
+        if let Some(limit) = osm_tags.get(osm::MAXSPEED) {
             // TODO handle other units
             if limit.ends_with(" mph") {
                 if let Ok(mph) = limit[0..limit.len() - 4].parse::<f64>() {
@@ -221,8 +231,8 @@ impl Road {
             }
         }
 
-        if self.osm_tags.get(osm::HIGHWAY) == Some(&"primary".to_string())
-            || self.osm_tags.get(osm::HIGHWAY) == Some(&"secondary".to_string())
+        if osm_tags.get(osm::HIGHWAY) == Some(&"primary".to_string())
+            || osm_tags.get(osm::HIGHWAY) == Some(&"secondary".to_string())
         {
             return Speed::miles_per_hour(40.0);
         }
@@ -399,6 +409,11 @@ impl Road {
         "???".to_string()
     }
 
+    // Part of the ring of a roundabout. These're one-way.
+    pub fn is_roundabout(&self) -> bool {
+        self.osm_tags.get("junction") == Some(&"roundabout".to_string())
+    }
+
     // Used to determine which roads have stop signs when roads of different types intersect.
     pub fn get_rank(&self) -> usize {
         if let Some(highway) = self.osm_tags.get(osm::HIGHWAY) {
@@ -0,0 +1,66 @@
+use crate::{IntersectionID, Map, RoadID, TurnID, TurnPriority, TurnType};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// A roundabout is usually mapped as a ring of one-way roads tagged junction=roundabout, with an
+// intersection wherever another road enters or leaves the ring. All of those intersections are one
+// roundabout, and each gets this control. A mini-roundabout, or an intersection converted by an
+// edit, is just one intersection without any ring roads. Nobody has priority there; everybody
+// entering yields and goes in the order they arrived, like at an all-way stop.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ControlRoundabout {
+    pub id: IntersectionID,
+    // The ring roads touching this intersection. Vehicles coming from these are already
+    // circulating and have priority over vehicles entering.
+    pub circulating: BTreeSet<RoadID>,
+}
+
+impl ControlRoundabout {
+    pub fn new(map: &Map, id: IntersectionID) -> ControlRoundabout {
+        ControlRoundabout {
+            id,
+            circulating: map
+                .get_i(id)
+                .roads
+                .iter()
+                .filter(|r| map.get_r(**r).is_roundabout())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn get_priority(&self, turn: TurnID, map: &Map) -> TurnPriority {
+        match map.get_t(turn).turn_type {
+            TurnType::SharedSidewalkCorner => TurnPriority::Protected,
+            // Like at stop signs, pedestrians have the right-of-way at crosswalks.
+            TurnType::Crosswalk => TurnPriority::Protected,
+            _ => {
+                if self.circulating.contains(&map.get_l(turn.src).parent) {
+                    TurnPriority::Protected
+                } else {
+                    TurnPriority::Yield
+                }
+            }
+        }
+    }
+
+    // Every intersection of the same roundabout, including this one. Edits can turn part of a
+    // ring into something else, so this only follows ring roads through roundabout intersections.
+    pub fn ring(&self, map: &Map) -> BTreeSet<IntersectionID> {
+        let mut ring = BTreeSet::new();
+        let mut queue = vec![self.id];
+        while let Some(i) = queue.pop() {
+            if ring.contains(&i) || !map.get_i(i).is_roundabout() {
+                continue;
+            }
+            ring.insert(i);
+            for r in &map.get_i(i).roads {
+                let r = map.get_r(*r);
+                if r.is_roundabout() {
+                    queue.push(if r.src_i == i { r.dst_i } else { r.src_i });
+                }
+            }
+        }
+        ring
+    }
+}
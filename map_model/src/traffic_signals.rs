@@ -5,8 +5,10 @@ use crate::{
 };
 use abstutil::{deserialize_btreemap, retain_btreeset, serialize_btreemap, Timer};
 use geom::{Duration, Time};
+use serde::Deserializer;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ControlTrafficSignal {
@@ -25,7 +27,44 @@ pub struct ControlTrafficSignal {
 pub struct Phase {
     pub protected_groups: BTreeSet<TurnGroupID>,
     pub yield_groups: BTreeSet<TurnGroupID>,
-    pub duration: Duration,
+    // Older JSON files just have a fixed duration here.
+    #[serde(alias = "duration", deserialize_with = "deserialize_phase_type")]
+    pub phase_type: PhaseType,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum PhaseType {
+    Fixed(Duration),
+    // Reacts to agents waiting at the intersection. The phase stays green for at least min_green.
+    // After that, it ends once nobody new has requested one of its turns for gap ("gapping
+    // out"), or once it reaches max_green. If skip_if_no_demand, the phase is skipped entirely
+    // when nobody is waiting for it.
+    Actuated {
+        min_green: Duration,
+        max_green: Duration,
+        gap: Duration,
+        skip_if_no_demand: bool,
+    },
+}
+
+fn deserialize_phase_type<'de, D: Deserializer<'de>>(d: D) -> Result<PhaseType, D::Error> {
+    // Formats like bincode can't guess which variant they're looking at, and binary maps get
+    // regenerated anyway.
+    if !d.is_human_readable() {
+        return serde::Deserialize::deserialize(d);
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Compat {
+        PhaseType(PhaseType),
+        Duration(Duration),
+    }
+    let compat: Compat = serde::Deserialize::deserialize(d)?;
+    match compat {
+        Compat::PhaseType(x) => Ok(x),
+        Compat::Duration(x) => Ok(PhaseType::Fixed(x)),
+    }
 }
 
 impl ControlTrafficSignal {
@@ -85,26 +124,34 @@ impl ControlTrafficSignal {
         results
     }
 
+    // For actuated phases, this assumes they run to max_green.
     pub fn cycle_length(&self) -> Duration {
         let mut cycle_length = Duration::ZERO;
         for p in &self.phases {
-            cycle_length += p.duration;
+            cycle_length += p.phase_type.simple_duration();
         }
         cycle_length
     }
 
+    // This is only the plan for a fixed-time signal, with actuated phases running to max_green.
+    // The simulation decides what the signal actually does.
     pub fn current_phase_and_remaining_time(&self, now: Time) -> (usize, &Phase, Duration) {
         let mut now_offset = ((now + self.offset) - Time::START_OF_DAY) % self.cycle_length();
         for (idx, p) in self.phases.iter().enumerate() {
-            if now_offset < p.duration {
-                return (idx, p, p.duration - now_offset);
+            let duration = p.phase_type.simple_duration();
+            if now_offset < duration {
+                return (idx, p, duration - now_offset);
             } else {
-                now_offset -= p.duration;
+                now_offset -= duration;
             }
         }
         unreachable!()
     }
 
+    pub fn is_actuated(&self) -> bool {
+        self.phases.iter().any(|p| p.phase_type.is_actuated())
+    }
+
     pub fn validate(self) -> Result<ControlTrafficSignal, String> {
         // Does the assignment cover the correct set of groups?
         let expected_groups: BTreeSet<TurnGroupID> = self.turn_groups.keys().cloned().collect();
@@ -146,6 +193,21 @@ impl ControlTrafficSignal {
             for g in phase.yield_groups.iter().map(|g| &self.turn_groups[g]) {
                 assert!(g.turn_type != TurnType::Crosswalk);
             }
+
+            if let PhaseType::Actuated {
+                min_green,
+                max_green,
+                gap,
+                ..
+            } = phase.phase_type
+            {
+                if min_green <= Duration::ZERO || gap <= Duration::ZERO || min_green > max_green {
+                    return Err(format!(
+                        "Traffic signal {} has a bad actuated phase: {}",
+                        self.id, phase.phase_type
+                    ));
+                }
+            }
         }
 
         Ok(self)
@@ -518,7 +580,7 @@ impl Phase {
         Phase {
             protected_groups: BTreeSet::new(),
             yield_groups: BTreeSet::new(),
-            duration: Duration::seconds(30.0),
+            phase_type: PhaseType::Fixed(Duration::seconds(30.0)),
         }
     }
 
@@ -579,6 +641,53 @@ impl Phase {
     }
 }
 
+impl PhaseType {
+    // How long the phase lasts if it's fixed, or the longest it could last if it's actuated.
+    pub fn simple_duration(&self) -> Duration {
+        match self {
+            PhaseType::Fixed(d) => *d,
+            PhaseType::Actuated { max_green, .. } => *max_green,
+        }
+    }
+
+    pub fn is_actuated(&self) -> bool {
+        match self {
+            PhaseType::Fixed(_) => false,
+            PhaseType::Actuated { .. } => true,
+        }
+    }
+
+    // Reasonable defaults for turning a fixed phase into an actuated one
+    pub fn actuated_from(max_green: Duration) -> PhaseType {
+        PhaseType::Actuated {
+            min_green: Duration::seconds(5.0).min(max_green),
+            max_green,
+            gap: Duration::seconds(3.0),
+            skip_if_no_demand: false,
+        }
+    }
+}
+
+impl fmt::Display for PhaseType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PhaseType::Fixed(d) => write!(f, "{}", d),
+            PhaseType::Actuated {
+                min_green,
+                max_green,
+                gap,
+                skip_if_no_demand,
+            } => {
+                write!(f, "actuated, {} to {}, gap {}", min_green, max_green, gap)?;
+                if *skip_if_no_demand {
+                    write!(f, ", skipped without demand")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 // Add all possible protected groups to existing phases.
 fn expand_all_phases(phases: &mut Vec<Phase>, turn_groups: &BTreeMap<TurnGroupID, TurnGroup>) {
     for phase in phases.iter_mut() {
@@ -606,6 +715,9 @@ fn make_phases(
         let mut phase = Phase::new();
 
         for (roads, turn_type, protected) in specs.into_iter() {
+            // The specs are written for driving on the right, where left turns cross oncoming
+            // traffic.
+            let turn_type = map.get_driving_side().turn_type(turn_type);
             for group in turn_groups.values() {
                 if !roads.contains(&group.id.from.id) || turn_type != group.turn_type {
                     continue;
@@ -651,7 +763,8 @@ impl ControlTrafficSignal {
                         .iter()
                         .map(|t| export_turn_group(t, map))
                         .collect(),
-                    duration_seconds: p.duration.inner_seconds() as usize,
+                    // The external format only knows about fixed phases.
+                    duration_seconds: p.phase_type.simple_duration().inner_seconds() as usize,
                 })
                 .collect(),
         };
@@ -683,7 +796,7 @@ impl ControlTrafficSignal {
                         .into_iter()
                         .map(|t| import_turn_group(t, map))
                         .collect(),
-                    duration: Duration::seconds(p.duration_seconds as f64),
+                    phase_type: PhaseType::Fixed(Duration::seconds(p.duration_seconds as f64)),
                 })
                 .collect(),
             offset: Duration::ZERO,
@@ -0,0 +1,314 @@
+use crate::psrc::{Endpoint, Mode};
+use crate::{ExternalPerson, ExternalTrip};
+use abstutil::Timer;
+use geom::{LonLat, Pt2D, Time};
+use map_model::{BuildingID, Map};
+use serde_derive::{Deserialize, Serialize};
+use sim::{DrivingGoal, Scenario, SidewalkPOI, SidewalkSpot, SpawnTrip, TripMode};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Write};
+
+// Scenarios in a form that other tools can read and edit. The same rows can be imported again
+// with external_people_to_scenario.
+
+// One row per trip. When person is missing, the trip is taken by somebody new. When an OSM
+// building ID is present and matches a building in the map, it's used instead of lon/lat.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TripRow {
+    #[serde(default)]
+    pub person: Option<usize>,
+    // HH:MM:SS.S
+    pub departure: String,
+    // walk, bike, transit, or drive
+    pub mode: String,
+    pub origin_lon: f64,
+    pub origin_lat: f64,
+    #[serde(default)]
+    pub origin_osm_way_id: Option<i64>,
+    pub destination_lon: f64,
+    pub destination_lat: f64,
+    #[serde(default)]
+    pub destination_osm_way_id: Option<i64>,
+}
+
+pub fn scenario_to_rows(scenario: &Scenario, map: &Map) -> Vec<TripRow> {
+    let mut rows = Vec::new();
+    for person in &scenario.people {
+        for trip in &person.trips {
+            let (from, to) = endpoints(&trip.trip, map);
+            rows.push(TripRow {
+                person: Some(person.id.0),
+                departure: trip.depart.to_string(),
+                mode: match trip.trip.mode() {
+                    TripMode::Walk => "walk",
+                    TripMode::Bike => "bike",
+                    TripMode::Transit => "transit",
+                    TripMode::Drive => "drive",
+                }
+                .to_string(),
+                origin_lon: from.pos.longitude,
+                origin_lat: from.pos.latitude,
+                origin_osm_way_id: from.osm_building,
+                destination_lon: to.pos.longitude,
+                destination_lat: to.pos.latitude,
+                destination_osm_way_id: to.osm_building,
+            });
+        }
+    }
+    rows
+}
+
+// Groups rows by person, keeping the order of their trips.
+pub fn rows_to_people(rows: Vec<TripRow>) -> Result<Vec<ExternalPerson>, String> {
+    let mut people: BTreeMap<usize, ExternalPerson> = BTreeMap::new();
+    let mut anonymous = Vec::new();
+    for row in rows {
+        let trip = ExternalTrip {
+            from: Endpoint {
+                pos: LonLat::new(row.origin_lon, row.origin_lat),
+                osm_building: row.origin_osm_way_id,
+            },
+            to: Endpoint {
+                pos: LonLat::new(row.destination_lon, row.destination_lat),
+                osm_building: row.destination_osm_way_id,
+            },
+            depart: Time::parse(row.departure.trim())
+                .map_err(|_| format!("Bad departure {}", row.departure))?,
+            mode: parse_mode(&row.mode)?,
+        };
+        if let Some(id) = row.person {
+            people
+                .entry(id)
+                .or_insert_with(|| ExternalPerson { trips: Vec::new() })
+                .trips
+                .push(trip);
+        } else {
+            anonymous.push(ExternalPerson { trips: vec![trip] });
+        }
+    }
+    let mut result: Vec<ExternalPerson> = people.into_iter().map(|(_, p)| p).collect();
+    result.extend(anonymous);
+    Ok(result)
+}
+
+pub fn parse_mode(x: &str) -> Result<Mode, String> {
+    match x.trim().to_lowercase().as_str() {
+        "walk" => Ok(Mode::Walk),
+        "bike" => Ok(Mode::Bike),
+        "transit" => Ok(Mode::Transit),
+        "drive" => Ok(Mode::Drive),
+        _ => Err(format!("Unknown mode {}", x)),
+    }
+}
+
+// Based on the extension, write a CSV file or a GeoJSON file with a straight line for each trip.
+pub fn write_trips(rows: &Vec<TripRow>, path: String) -> Result<(), Error> {
+    if path.ends_with(".geojson") {
+        let features = rows
+            .iter()
+            .map(|row| Feature {
+                feature_type: "Feature".to_string(),
+                geometry: Geometry {
+                    geometry_type: "LineString".to_string(),
+                    coordinates: vec![
+                        vec![row.origin_lon, row.origin_lat],
+                        vec![row.destination_lon, row.destination_lat],
+                    ],
+                },
+                properties: row,
+            })
+            .collect();
+        let mut f = File::create(&path)?;
+        f.write_all(
+            abstutil::to_json(&FeatureCollection {
+                feature_type: "FeatureCollection".to_string(),
+                features,
+            })
+            .as_bytes(),
+        )?;
+        println!("Wrote {}", path);
+        return Ok(());
+    }
+    if !path.ends_with(".csv") {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Trips {} must end with .csv or .geojson", path),
+        ));
+    }
+
+    let mut f = File::create(&path)?;
+    writeln!(
+        f,
+        "person,departure,mode,origin_lon,origin_lat,origin_osm_way_id,destination_lon,\
+         destination_lat,destination_osm_way_id"
+    )?;
+    for row in rows {
+        writeln!(
+            f,
+            "{},{},{},{},{},{},{},{},{}",
+            row.person.map(|p| p.to_string()).unwrap_or_default(),
+            row.departure,
+            row.mode,
+            row.origin_lon,
+            row.origin_lat,
+            row.origin_osm_way_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            row.destination_lon,
+            row.destination_lat,
+            row.destination_osm_way_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+        )?;
+    }
+    println!("Wrote {}", path);
+    Ok(())
+}
+
+pub fn read_geojson_trips(path: String, timer: &mut Timer) -> Vec<TripRow> {
+    let collection: FeatureCollection<TripRow> = abstutil::read_json(path, timer);
+    collection
+        .features
+        .into_iter()
+        .map(|f| f.properties)
+        .collect()
+}
+
+// Just enough of GeoJSON to express trips
+#[derive(Serialize, Deserialize)]
+struct FeatureCollection<T> {
+    #[serde(rename = "type")]
+    feature_type: String,
+    features: Vec<Feature<T>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Feature<T> {
+    #[serde(rename = "type")]
+    feature_type: String,
+    geometry: Geometry,
+    properties: T,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Geometry {
+    #[serde(rename = "type")]
+    geometry_type: String,
+    coordinates: Vec<Vec<f64>>,
+}
+
+fn endpoints(trip: &SpawnTrip, map: &Map) -> (Endpoint, Endpoint) {
+    let bldg = |b: BuildingID| {
+        let b = map.get_b(b);
+        endpoint(b.polygon.center(), Some(b.osm_way_id), map)
+    };
+    let spot = |spot: &SidewalkSpot| match spot.connection {
+        SidewalkPOI::Building(b) => bldg(b),
+        SidewalkPOI::Border(i) => endpoint(map.get_i(i).polygon.center(), None, map),
+        _ => endpoint(spot.sidewalk_pos.pt(map), None, map),
+    };
+    let goal = |goal: &DrivingGoal| match goal {
+        DrivingGoal::ParkNear(b) => bldg(*b),
+        DrivingGoal::Border(i, _) => endpoint(map.get_i(*i).polygon.center(), None, map),
+    };
+
+    match trip {
+        SpawnTrip::CarAppearing { start, goal: g, .. } => {
+            (endpoint(start.pt(map), None, map), goal(g))
+        }
+        SpawnTrip::MaybeUsingParkedCar(b, g) => (bldg(*b), goal(g)),
+        SpawnTrip::UsingBike(s, g) => (spot(s), goal(g)),
+        SpawnTrip::JustWalking(s1, s2) | SpawnTrip::UsingTransit(s1, s2, _, _, _) => {
+            (spot(s1), spot(s2))
+        }
+    }
+}
+
+fn endpoint(pt: Pt2D, osm_building: Option<i64>, map: &Map) -> Endpoint {
+    Endpoint {
+        pos: pt.forcibly_to_gps(map.get_gps_bounds()),
+        osm_building,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows() -> Vec<TripRow> {
+        vec![
+            TripRow {
+                person: Some(3),
+                departure: Time::START_OF_DAY.to_string(),
+                mode: "drive".to_string(),
+                origin_lon: -122.3,
+                origin_lat: 47.6,
+                origin_osm_way_id: Some(123),
+                destination_lon: -122.31,
+                destination_lat: 47.61,
+                destination_osm_way_id: None,
+            },
+            TripRow {
+                person: None,
+                departure: "08:30:00.0".to_string(),
+                mode: "walk".to_string(),
+                origin_lon: -122.31,
+                origin_lat: 47.61,
+                origin_osm_way_id: None,
+                destination_lon: -122.3,
+                destination_lat: 47.6,
+                destination_osm_way_id: Some(456),
+            },
+        ]
+    }
+
+    fn check(reimported: Vec<TripRow>) {
+        let people = rows_to_people(reimported).unwrap();
+        assert_eq!(people.len(), 2);
+        let drive = &people[0].trips[0];
+        assert_eq!(drive.mode, Mode::Drive);
+        assert_eq!(drive.depart, Time::START_OF_DAY);
+        assert_eq!(drive.from.osm_building, Some(123));
+        assert_eq!(drive.to.osm_building, None);
+        assert_eq!(drive.to.pos, LonLat::new(-122.31, 47.61));
+        let walk = &people[1].trips[0];
+        assert_eq!(walk.mode, Mode::Walk);
+        assert_eq!(walk.depart, Time::parse("08:30:00.0").unwrap());
+        assert_eq!(walk.to.osm_building, Some(456));
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(name)
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let path = temp_path("popdat_round_trip.csv");
+        write_trips(&rows(), path.clone()).unwrap();
+        let reimported = csv::Reader::from_path(&path)
+            .unwrap()
+            .deserialize()
+            .collect::<Result<Vec<TripRow>, _>>()
+            .unwrap();
+        check(reimported);
+    }
+
+    #[test]
+    fn test_geojson_round_trip() {
+        let path = temp_path("popdat_round_trip.geojson");
+        write_trips(&rows(), path.clone()).unwrap();
+        check(read_geojson_trips(path, &mut Timer::throwaway()));
+    }
+
+    #[test]
+    fn test_write_errors() {
+        assert!(write_trips(&rows(), temp_path("popdat_round_trip.txt")).is_err());
+        let missing_dir = temp_path("popdat_no_such_dir/trips.csv");
+        assert!(write_trips(&rows(), missing_dir).is_err());
+    }
+}
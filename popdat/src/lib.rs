@@ -1,6 +1,12 @@
+mod export;
+mod od;
 pub mod psrc;
 mod trips;
 
+pub use export::{
+    parse_mode, read_geojson_trips, rows_to_people, scenario_to_rows, write_trips, TripRow,
+};
+pub use od::{external_people_to_scenario, od_to_scenario, ExternalPerson, ExternalTrip, ODBucket};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 pub use trips::{clip_trips, trips_to_scenario, Trip, TripEndpt};
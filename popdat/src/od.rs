@@ -0,0 +1,275 @@
+use crate::psrc::{Endpoint, Mode};
+use crate::trips::{spawn_trip, Borders, TripEndpt};
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Distance, Duration, FindClosest, LonLat, Pt2D, Time};
+use map_model::{BuildingID, Map};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+use sim::{IndividTrip, PersonID, PersonSpec, Scenario};
+use std::collections::{BTreeMap, HashMap};
+
+// Demand for cities without Soundcast output. Either individual trips between two points, or
+// counts of trips between zones.
+
+// One trip between two points, which may be outside the map. If the endpoint has an OSM building
+// ID that's in the map, that's used instead of the point.
+#[derive(Clone, Debug)]
+pub struct ExternalTrip {
+    pub from: Endpoint,
+    pub to: Endpoint,
+    pub depart: Time,
+    pub mode: Mode,
+}
+
+// Somebody taking trips in order. When nothing says who takes which trips, everybody just takes
+// one.
+#[derive(Clone, Debug)]
+pub struct ExternalPerson {
+    pub trips: Vec<ExternalTrip>,
+}
+
+// Some number of trips between two zones of an origin-destination matrix, departing sometime in
+// [start, end)
+#[derive(Clone, Debug)]
+pub struct ODBucket {
+    pub from_zone: String,
+    pub to_zone: String,
+    pub mode: Mode,
+    pub start: Time,
+    pub end: Time,
+    pub num_trips: usize,
+}
+
+// Points this far from any building are assumed to be somewhere without buildings, like a park.
+const MAX_BLDG_SNAP_DIST: Distance = Distance::const_meters(100.0);
+// Points this close to a border are assumed to be entering or leaving the map there, even if
+// they're inside the map's bounds.
+const MAX_BORDER_SNAP_DIST: Distance = Distance::const_meters(20.0);
+
+pub fn external_people_to_scenario(
+    scenario_name: &str,
+    input: Vec<ExternalPerson>,
+    map: &Map,
+    timer: &mut Timer,
+) -> Scenario {
+    let borders = Borders::new(map);
+    let mut osm_id_to_bldg = HashMap::new();
+    let mut closest_bldg: FindClosest<BuildingID> = FindClosest::new(map.get_bounds());
+    for b in map.all_buildings() {
+        osm_id_to_bldg.insert(b.osm_way_id, b.id);
+        closest_bldg.add(b.id, b.polygon.points());
+    }
+    let snap = |endpt: &Endpoint, borders: &Vec<_>| -> Option<TripEndpt> {
+        if let Some(b) = endpt.osm_building.and_then(|id| osm_id_to_bldg.get(&id)) {
+            return Some(TripEndpt::Building(*b));
+        }
+        let border = TripEndpt::closest_border(endpt.pos, map, borders);
+        if let Some(pt) = Pt2D::from_gps(endpt.pos, map.get_gps_bounds()) {
+            if let Some(TripEndpt::Border(i, _)) = border {
+                if map.get_i(i).polygon.center().dist_to(pt) <= MAX_BORDER_SNAP_DIST {
+                    return border;
+                }
+            }
+            // A point inside the map has to be near a building; don't make somebody appear at a
+            // border in the middle of the map.
+            return closest_bldg
+                .closest_pt(pt, MAX_BLDG_SNAP_DIST)
+                .map(|(b, _)| TripEndpt::Building(b));
+        }
+        border
+    };
+
+    let mut total = 0;
+    let mut people = Vec::new();
+    timer.start_iter("snap trip endpoints", input.len());
+    for person in input {
+        timer.next();
+        let mut trips = Vec::new();
+        for trip in person.trips {
+            total += 1;
+            if let (Some(from), Some(to)) = (
+                snap(&trip.from, borders.incoming(trip.mode)),
+                snap(&trip.to, borders.outgoing(trip.mode)),
+            ) {
+                trips.push((from, to, trip.mode, trip.depart));
+            }
+        }
+        people.push(trips);
+    }
+    let snapped: usize = people.iter().map(|trips| trips.len()).sum();
+    timer.note(format!(
+        "{} trips snapped to the map, {} dropped",
+        prettyprint_usize(snapped),
+        prettyprint_usize(total - snapped)
+    ));
+
+    make_scenario(scenario_name, people, map, timer)
+}
+
+// Zones with a centroid inside the map boundary split up the buildings; each building belongs to
+// the one with the closest centroid. Trips to or from any other zone use the closest border.
+pub fn od_to_scenario(
+    scenario_name: &str,
+    zones: &BTreeMap<String, LonLat>,
+    buckets: Vec<ODBucket>,
+    map: &Map,
+    rng: &mut XorShiftRng,
+    timer: &mut Timer,
+) -> Scenario {
+    let borders = Borders::new(map);
+    // Only zones centered inside the map get buildings, so buildings near the edge don't wind up
+    // in a zone that's mostly outside of it.
+    let inside_zones: Vec<&String> = zones
+        .iter()
+        .filter(|(_, pos)| {
+            Pt2D::from_gps(**pos, map.get_gps_bounds())
+                .map(|pt| map.get_boundary_polygon().contains_pt(pt))
+                .unwrap_or(false)
+        })
+        .map(|(z, _)| z)
+        .collect();
+    let mut bldgs_per_zone: BTreeMap<&String, Vec<BuildingID>> = BTreeMap::new();
+    for b in map.all_buildings() {
+        if let Some(pos) = b.label_center.to_gps(map.get_gps_bounds()) {
+            if let Some(zone) = inside_zones
+                .iter()
+                .min_by_key(|z| zones[**z].fast_dist(pos))
+            {
+                bldgs_per_zone
+                    .entry(*zone)
+                    .or_insert_with(Vec::new)
+                    .push(b.id);
+            }
+        }
+    }
+    let pick = |zone: &String, borders: &Vec<_>, rng: &mut XorShiftRng| -> Option<TripEndpt> {
+        if let Some(bldgs) = bldgs_per_zone.get(zone) {
+            return Some(TripEndpt::Building(*bldgs.choose(rng).unwrap()));
+        }
+        TripEndpt::closest_border(*zones.get(zone)?, map, borders)
+    };
+
+    let mut endpts = Vec::new();
+    let mut dropped = 0;
+    timer.start_iter("sample trips from OD buckets", buckets.len());
+    for bucket in buckets {
+        timer.next();
+        if !zones.contains_key(&bucket.from_zone) || !zones.contains_key(&bucket.to_zone) {
+            timer.warn(format!(
+                "OD bucket from {} to {} refers to an unknown zone",
+                bucket.from_zone, bucket.to_zone
+            ));
+            dropped += bucket.num_trips;
+            continue;
+        }
+        // Keep the trips sampled from other buckets stable if this one changes.
+        let mut tmp_rng = abstutil::fork_rng(rng);
+        for _ in 0..bucket.num_trips {
+            let from = pick(
+                &bucket.from_zone,
+                borders.incoming(bucket.mode),
+                &mut tmp_rng,
+            );
+            let to = pick(&bucket.to_zone, borders.outgoing(bucket.mode), &mut tmp_rng);
+            let depart = if bucket.end > bucket.start {
+                Time::START_OF_DAY
+                    + Duration::seconds(
+                        tmp_rng.gen_range(bucket.start.inner_seconds(), bucket.end.inner_seconds()),
+                    )
+            } else {
+                bucket.start
+            };
+            if let (Some(from), Some(to)) = (from, to) {
+                endpts.push(vec![(from, to, bucket.mode, depart)]);
+            } else {
+                dropped += 1;
+            }
+        }
+    }
+    timer.note(format!(
+        "{} trips sampled from the OD matrix, {} dropped",
+        prettyprint_usize(endpts.len()),
+        prettyprint_usize(dropped)
+    ));
+
+    make_scenario(scenario_name, endpts, map, timer)
+}
+
+fn make_scenario(
+    scenario_name: &str,
+    input: Vec<Vec<(TripEndpt, TripEndpt, Mode, Time)>>,
+    map: &Map,
+    timer: &mut Timer,
+) -> Scenario {
+    let mut people = Vec::new();
+    // How many parked cars do we need to spawn near each building? Like count_cars, this assumes
+    // trips are instantaneous.
+    let mut parked_cars_per_bldg = BTreeMap::new();
+    let mut avail_per_bldg = BTreeMap::new();
+    for b in map.all_buildings() {
+        parked_cars_per_bldg.insert(b.id, 0);
+        avail_per_bldg.insert(b.id, 0);
+    }
+    let mut all_trips = Vec::new();
+    let mut total = 0;
+    timer.start_iter("turn trips into people", input.len());
+    for endpts in input {
+        timer.next();
+        let mut trips = Vec::new();
+        for (from, to, mode, depart) in endpts {
+            total += 1;
+            match (&from, &to) {
+                (TripEndpt::Border(_, _), TripEndpt::Border(_, _)) => {
+                    // TODO Detect and handle pass-through trips
+                    continue;
+                }
+                (TripEndpt::Building(b1), TripEndpt::Building(b2)) if b1 == b2 => {
+                    continue;
+                }
+                _ => {}
+            }
+            if let Some(trip) = spawn_trip(&from, &to, mode, map) {
+                all_trips.push((depart, from, to, mode));
+                trips.push(IndividTrip { depart, trip });
+            }
+        }
+        if !trips.is_empty() {
+            trips.sort_by_key(|t| t.depart);
+            people.push(PersonSpec {
+                id: PersonID(people.len()),
+                trips,
+            });
+        }
+    }
+    timer.note(format!(
+        "{} trips became {} people",
+        prettyprint_usize(total),
+        prettyprint_usize(people.len())
+    ));
+
+    all_trips.sort_by_key(|(depart, _, _, _)| *depart);
+    for (_, from, to, mode) in all_trips {
+        if mode != Mode::Drive {
+            continue;
+        }
+        if let TripEndpt::Building(b) = from {
+            if avail_per_bldg[&b] > 0 {
+                *avail_per_bldg.get_mut(&b).unwrap() -= 1;
+            } else {
+                *parked_cars_per_bldg.get_mut(&b).unwrap() += 1;
+            }
+        }
+        if let TripEndpt::Building(b) = to {
+            *avail_per_bldg.get_mut(&b).unwrap() += 1;
+        }
+    }
+
+    Scenario {
+        scenario_name: scenario_name.to_string(),
+        map_name: map.get_name().to_string(),
+        people,
+        parked_cars_per_bldg,
+        only_seed_buses: None,
+    }
+}
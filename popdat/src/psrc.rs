@@ -17,7 +17,7 @@ pub struct Trip {
     pub trip_dist: Distance,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Endpoint {
     pub pos: LonLat,
     pub osm_building: Option<i64>,
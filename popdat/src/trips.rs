@@ -38,73 +38,82 @@ impl Trip {
     }
 
     pub fn to_spawn_trip(&self, map: &Map) -> Option<SpawnTrip> {
-        match self.mode {
-            Mode::Drive => match self.from {
-                TripEndpt::Border(i, _) => {
-                    if let Some(start) = TripSpec::spawn_car_at(
-                        Position::new(
-                            map.get_i(i).get_outgoing_lanes(map, PathConstraints::Car)[0],
-                            Distance::ZERO,
-                        ),
-                        map,
-                    ) {
-                        Some(SpawnTrip::CarAppearing {
-                            start,
-                            goal: self.to.driving_goal(PathConstraints::Car, map),
-                            is_bike: false,
-                        })
-                    } else {
-                        // TODO need to be able to emit warnings from parallelize
-                        //timer.warn(format!("No room for car to appear at {:?}", self.from));
-                        None
-                    }
-                }
-                TripEndpt::Building(b) => Some(SpawnTrip::MaybeUsingParkedCar(
-                    b,
-                    self.to.driving_goal(PathConstraints::Car, map),
-                )),
-            },
-            Mode::Bike => match self.from {
-                TripEndpt::Building(b) => Some(SpawnTrip::UsingBike(
-                    SidewalkSpot::building(b, map),
-                    self.to.driving_goal(PathConstraints::Bike, map),
-                )),
-                TripEndpt::Border(i, _) => {
-                    if let Some(start) = TripSpec::spawn_car_at(
-                        Position::new(
-                            map.get_i(i).get_outgoing_lanes(map, PathConstraints::Bike)[0],
-                            Distance::ZERO,
-                        ),
-                        map,
-                    ) {
-                        Some(SpawnTrip::CarAppearing {
-                            start,
-                            goal: self.to.driving_goal(PathConstraints::Bike, map),
-                            is_bike: true,
-                        })
-                    } else {
-                        //timer.warn(format!("No room for bike to appear at {:?}", self.from));
-                        None
-                    }
+        spawn_trip(&self.from, &self.to, self.mode, map)
+    }
+}
+
+pub(crate) fn spawn_trip(
+    from: &TripEndpt,
+    to: &TripEndpt,
+    mode: Mode,
+    map: &Map,
+) -> Option<SpawnTrip> {
+    match mode {
+        Mode::Drive => match *from {
+            TripEndpt::Border(i, _) => {
+                if let Some(start) = TripSpec::spawn_car_at(
+                    Position::new(
+                        map.get_i(i).get_outgoing_lanes(map, PathConstraints::Car)[0],
+                        Distance::ZERO,
+                    ),
+                    map,
+                ) {
+                    Some(SpawnTrip::CarAppearing {
+                        start,
+                        goal: to.driving_goal(PathConstraints::Car, map),
+                        is_bike: false,
+                    })
+                } else {
+                    // TODO need to be able to emit warnings from parallelize
+                    //timer.warn(format!("No room for car to appear at {:?}", from));
+                    None
                 }
-            },
-            Mode::Walk => Some(SpawnTrip::JustWalking(
-                self.from.start_sidewalk_spot(map),
-                self.to.end_sidewalk_spot(map),
+            }
+            TripEndpt::Building(b) => Some(SpawnTrip::MaybeUsingParkedCar(
+                b,
+                to.driving_goal(PathConstraints::Car, map),
+            )),
+        },
+        Mode::Bike => match *from {
+            TripEndpt::Building(b) => Some(SpawnTrip::UsingBike(
+                SidewalkSpot::building(b, map),
+                to.driving_goal(PathConstraints::Bike, map),
             )),
-            Mode::Transit => {
-                let start = self.from.start_sidewalk_spot(map);
-                let goal = self.to.end_sidewalk_spot(map);
-                if let Some((stop1, stop2, route)) =
-                    map.should_use_transit(start.sidewalk_pos, goal.sidewalk_pos)
-                {
-                    Some(SpawnTrip::UsingTransit(start, goal, route, stop1, stop2))
+            TripEndpt::Border(i, _) => {
+                if let Some(start) = TripSpec::spawn_car_at(
+                    Position::new(
+                        map.get_i(i).get_outgoing_lanes(map, PathConstraints::Bike)[0],
+                        Distance::ZERO,
+                    ),
+                    map,
+                ) {
+                    Some(SpawnTrip::CarAppearing {
+                        start,
+                        goal: to.driving_goal(PathConstraints::Bike, map),
+                        is_bike: true,
+                    })
                 } else {
-                    //timer.warn(format!("{:?} not actually using transit, because pathfinding
-                    // didn't find any useful route", trip));
-                    Some(SpawnTrip::JustWalking(start, goal))
+                    //timer.warn(format!("No room for bike to appear at {:?}", from));
+                    None
                 }
             }
+        },
+        Mode::Walk => Some(SpawnTrip::JustWalking(
+            from.start_sidewalk_spot(map),
+            to.end_sidewalk_spot(map),
+        )),
+        Mode::Transit => {
+            let start = from.start_sidewalk_spot(map);
+            let goal = to.end_sidewalk_spot(map);
+            if let Some((stop1, stop2, route)) =
+                map.should_use_transit(start.sidewalk_pos, goal.sidewalk_pos)
+            {
+                Some(SpawnTrip::UsingTransit(start, goal, route, stop1, stop2))
+            } else {
+                //timer.warn(format!("{:?} not actually using transit, because pathfinding
+                // didn't find any useful route", trip));
+                Some(SpawnTrip::JustWalking(start, goal))
+            }
         }
     }
 }
@@ -119,14 +128,19 @@ impl TripEndpt {
         if let Some(b) = endpt.osm_building.and_then(|id| osm_id_to_bldg.get(&id)) {
             return Some(TripEndpt::Building(*b));
         }
+        TripEndpt::closest_border(endpt.pos, map, borders)
+    }
+
+    pub(crate) fn closest_border(
+        pos: LonLat,
+        map: &Map,
+        borders: &Vec<(IntersectionID, LonLat)>,
+    ) -> Option<TripEndpt> {
         borders
             .iter()
-            .min_by_key(|(_, pt)| pt.fast_dist(endpt.pos))
+            .min_by_key(|(_, pt)| pt.fast_dist(pos))
             .map(|(id, _)| {
-                TripEndpt::Border(
-                    *id,
-                    Pt2D::forcibly_from_gps(endpt.pos, map.get_gps_bounds()),
-                )
+                TripEndpt::Border(*id, Pt2D::forcibly_from_gps(pos, map.get_gps_bounds()))
             })
     }
 
@@ -162,6 +176,61 @@ impl TripEndpt {
     }
 }
 
+// Where trips can enter and leave the map, per mode
+pub(crate) struct Borders {
+    incoming_walking: Vec<(IntersectionID, LonLat)>,
+    incoming_driving: Vec<(IntersectionID, LonLat)>,
+    incoming_biking: Vec<(IntersectionID, LonLat)>,
+    outgoing_walking: Vec<(IntersectionID, LonLat)>,
+    outgoing_driving: Vec<(IntersectionID, LonLat)>,
+    outgoing_biking: Vec<(IntersectionID, LonLat)>,
+}
+
+impl Borders {
+    pub fn new(map: &Map) -> Borders {
+        let bounds = map.get_gps_bounds();
+        // TODO Figure out why some polygon centers are broken
+        let incoming = |constraints: PathConstraints| -> Vec<(IntersectionID, LonLat)> {
+            map.all_incoming_borders()
+                .into_iter()
+                .filter(|i| !i.get_outgoing_lanes(map, constraints).is_empty())
+                .filter_map(|i| i.polygon.center().to_gps(bounds).map(|pt| (i.id, pt)))
+                .collect()
+        };
+        let outgoing = |constraints: PathConstraints| -> Vec<(IntersectionID, LonLat)> {
+            map.all_outgoing_borders()
+                .into_iter()
+                .filter(|i| !i.get_incoming_lanes(map, constraints).is_empty())
+                .filter_map(|i| i.polygon.center().to_gps(bounds).map(|pt| (i.id, pt)))
+                .collect()
+        };
+        Borders {
+            incoming_walking: incoming(PathConstraints::Pedestrian),
+            incoming_driving: incoming(PathConstraints::Car),
+            incoming_biking: incoming(PathConstraints::Bike),
+            outgoing_walking: outgoing(PathConstraints::Pedestrian),
+            outgoing_driving: outgoing(PathConstraints::Car),
+            outgoing_biking: outgoing(PathConstraints::Bike),
+        }
+    }
+
+    pub fn incoming(&self, mode: Mode) -> &Vec<(IntersectionID, LonLat)> {
+        match mode {
+            Mode::Walk | Mode::Transit => &self.incoming_walking,
+            Mode::Drive => &self.incoming_driving,
+            Mode::Bike => &self.incoming_biking,
+        }
+    }
+
+    pub fn outgoing(&self, mode: Mode) -> &Vec<(IntersectionID, LonLat)> {
+        match mode {
+            Mode::Walk | Mode::Transit => &self.outgoing_walking,
+            Mode::Drive => &self.outgoing_driving,
+            Mode::Bike => &self.outgoing_biking,
+        }
+    }
+}
+
 pub fn clip_trips(map: &Map, timer: &mut Timer) -> (Vec<Trip>, HashMap<BuildingID, Parcel>) {
     let popdat: PopDat = abstutil::read_binary(abstutil::path_popdat(), timer);
 
@@ -169,50 +238,7 @@ pub fn clip_trips(map: &Map, timer: &mut Timer) -> (Vec<Trip>, HashMap<BuildingI
     for b in map.all_buildings() {
         osm_id_to_bldg.insert(b.osm_way_id, b.id);
     }
-    let bounds = map.get_gps_bounds();
-    // TODO Figure out why some polygon centers are broken
-    let incoming_borders_walking: Vec<(IntersectionID, LonLat)> = map
-        .all_incoming_borders()
-        .into_iter()
-        .filter(|i| {
-            !i.get_outgoing_lanes(map, PathConstraints::Pedestrian)
-                .is_empty()
-        })
-        .filter_map(|i| i.polygon.center().to_gps(bounds).map(|pt| (i.id, pt)))
-        .collect();
-    let incoming_borders_driving: Vec<(IntersectionID, LonLat)> = map
-        .all_incoming_borders()
-        .into_iter()
-        .filter(|i| !i.get_outgoing_lanes(map, PathConstraints::Car).is_empty())
-        .filter_map(|i| i.polygon.center().to_gps(bounds).map(|pt| (i.id, pt)))
-        .collect();
-    let incoming_borders_biking: Vec<(IntersectionID, LonLat)> = map
-        .all_incoming_borders()
-        .into_iter()
-        .filter(|i| !i.get_outgoing_lanes(map, PathConstraints::Bike).is_empty())
-        .filter_map(|i| i.polygon.center().to_gps(bounds).map(|pt| (i.id, pt)))
-        .collect();
-    let outgoing_borders_walking: Vec<(IntersectionID, LonLat)> = map
-        .all_outgoing_borders()
-        .into_iter()
-        .filter(|i| {
-            !i.get_incoming_lanes(map, PathConstraints::Pedestrian)
-                .is_empty()
-        })
-        .filter_map(|i| i.polygon.center().to_gps(bounds).map(|pt| (i.id, pt)))
-        .collect();
-    let outgoing_borders_driving: Vec<(IntersectionID, LonLat)> = map
-        .all_outgoing_borders()
-        .into_iter()
-        .filter(|i| !i.get_incoming_lanes(map, PathConstraints::Car).is_empty())
-        .filter_map(|i| i.polygon.center().to_gps(bounds).map(|pt| (i.id, pt)))
-        .collect();
-    let outgoing_borders_biking: Vec<(IntersectionID, LonLat)> = map
-        .all_outgoing_borders()
-        .into_iter()
-        .filter(|i| !i.get_incoming_lanes(map, PathConstraints::Bike).is_empty())
-        .filter_map(|i| i.polygon.center().to_gps(bounds).map(|pt| (i.id, pt)))
-        .collect();
+    let borders = Borders::new(map);
 
     let total_trips = popdat.trips.len();
     let maybe_results: Vec<Option<Trip>> = timer.parallelize("clip trips", popdat.trips, |trip| {
@@ -220,22 +246,9 @@ pub fn clip_trips(map: &Map, timer: &mut Timer) -> (Vec<Trip>, HashMap<BuildingI
             &trip.from,
             map,
             &osm_id_to_bldg,
-            match trip.mode {
-                Mode::Walk | Mode::Transit => &incoming_borders_walking,
-                Mode::Drive => &incoming_borders_driving,
-                Mode::Bike => &incoming_borders_biking,
-            },
-        )?;
-        let to = TripEndpt::new(
-            &trip.to,
-            map,
-            &osm_id_to_bldg,
-            match trip.mode {
-                Mode::Walk | Mode::Transit => &outgoing_borders_walking,
-                Mode::Drive => &outgoing_borders_driving,
-                Mode::Bike => &outgoing_borders_biking,
-            },
+            borders.incoming(trip.mode),
         )?;
+        let to = TripEndpt::new(&trip.to, map, &osm_id_to_bldg, borders.outgoing(trip.mode))?;
 
         let trip = Trip {
             from,
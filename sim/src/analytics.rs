@@ -1,12 +1,13 @@
-use crate::{CarID, Event, TripID, TripMode, TripPhaseType};
-use abstutil::Counter;
+use crate::{AgentID, CarID, Event, TripID, TripMode, TripPhaseType, VehicleType};
+use abstutil::{deserialize_btreemap, serialize_btreemap, Counter};
 use derivative::Derivative;
 use geom::{Distance, Duration, Histogram, Time};
 use map_model::{
-    BusRouteID, BusStopID, IntersectionID, Map, Path, PathRequest, RoadID, Traversable, TurnGroupID,
+    BusRoute, BusRouteID, BusStopID, IntersectionID, Map, Path, PathRequest, RoadID, TravelTimes,
+    Traversable, TurnGroupID, TurnID,
 };
 use serde_derive::{Deserialize, Serialize};
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 #[derive(Clone, Serialize, Deserialize, Derivative)]
 pub struct Analytics {
@@ -15,6 +16,10 @@ pub struct Analytics {
     pub(crate) test_expectations: VecDeque<Event>,
     pub bus_arrivals: Vec<(Time, CarID, BusRouteID, BusStopID)>,
     pub bus_passengers_waiting: Vec<(Time, BusStopID, BusRouteID)>,
+    // Which trip in the route's schedule each scheduled bus is running
+    pub bus_scheduled_trips: BTreeMap<CarID, usize>,
+    // Departure time, bus, route, stop, number of passengers aboard when leaving
+    pub bus_loads: Vec<(Time, CarID, BusRouteID, BusStopID, usize)>,
     // TODO Scraping TripMode from TripPhaseStarting is frustrating.
     pub started_trips: BTreeMap<TripID, (Time, TripMode)>,
     // TODO Hack: No TripMode means aborted
@@ -22,7 +27,23 @@ pub struct Analytics {
     pub finished_trips: Vec<(Time, TripID, Option<TripMode>, Duration)>,
     // TODO This subsumes finished_trips
     pub trip_log: Vec<(Time, TripID, Option<PathRequest>, TripPhaseType)>,
-    pub intersection_delays: BTreeMap<IntersectionID, Vec<(Time, Duration)>>,
+    pub intersection_delays: BTreeMap<IntersectionID, Vec<(Time, Duration, AgentID, TurnID)>>,
+    // How far each driving trip went past the end of its original path, looking for parking
+    pub parking_cruising: BTreeMap<TripID, Distance>,
+    // Total time cars took to cross each lane and turn (including waiting at the end), and how
+    // many did, grouped by the hour they finished. Only counts cars that crossed the entire thing.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    traversal_times: BTreeMap<Traversable, BTreeMap<usize, (Duration, usize)>>,
+    // When each car entered its current lane or turn. Cars are forgotten once they're done
+    // driving.
+    #[serde(skip_serializing, skip_deserializing)]
+    entered_traversable: BTreeMap<CarID, (Traversable, Time)>,
+    // Which car is driving for each trip, to forget the car when the trip ends
+    #[serde(skip_serializing, skip_deserializing)]
+    car_per_trip: BTreeMap<TripID, CarID>,
 
     // After we restore from a savestate, don't record anything. This is only going to make sense
     // if savestates are only used for quickly previewing against prebaked results, where we have
@@ -42,6 +63,9 @@ pub struct ThruputStats {
 
     // Unlike everything else in Analytics, this is just for a moment in time.
     pub demand: BTreeMap<TurnGroupID, usize>,
+    // How demand changed over time: +1 when somebody starts a path using the turn group, -1 when
+    // they cross it
+    raw_demand: Vec<(Time, TurnGroupID, isize)>,
 }
 
 impl Analytics {
@@ -53,14 +77,21 @@ impl Analytics {
                 raw_per_road: Vec::new(),
                 raw_per_intersection: Vec::new(),
                 demand: BTreeMap::new(),
+                raw_demand: Vec::new(),
             },
             test_expectations: VecDeque::new(),
             bus_arrivals: Vec::new(),
             bus_passengers_waiting: Vec::new(),
+            bus_scheduled_trips: BTreeMap::new(),
+            bus_loads: Vec::new(),
             started_trips: BTreeMap::new(),
             finished_trips: Vec::new(),
             trip_log: Vec::new(),
             intersection_delays: BTreeMap::new(),
+            parking_cruising: BTreeMap::new(),
+            traversal_times: BTreeMap::new(),
+            entered_traversable: BTreeMap::new(),
+            car_per_trip: BTreeMap::new(),
             record_anything: true,
         }
     }
@@ -74,7 +105,7 @@ impl Analytics {
         let raw_thruput = true;
 
         // Throughput
-        if let Event::AgentEntersTraversable(a, to) = ev {
+        if let Event::AgentEntersTraversable(a, to, _) = ev {
             let mode = TripMode::from_agent(a);
             match to {
                 Traversable::Lane(l) => {
@@ -94,11 +125,42 @@ impl Analytics {
 
                     if let Some(id) = map.get_turn_group(t) {
                         *self.thruput_stats.demand.entry(id).or_insert(0) -= 1;
+                        self.thruput_stats.raw_demand.push((time, id, -1));
                     }
                 }
             };
         }
 
+        // Travel times
+        if let Event::AgentEntersTraversable(AgentID::Car(car), to, trip) = ev {
+            if car.1 == VehicleType::Car {
+                if let Some((from, entered)) = self.entered_traversable.insert(car, (to, time)) {
+                    let entry = self
+                        .traversal_times
+                        .entry(from)
+                        .or_insert_with(BTreeMap::new)
+                        .entry(hour(time))
+                        .or_insert((Duration::ZERO, 0));
+                    entry.0 += time - entered;
+                    entry.1 += 1;
+                }
+                if let Some(trip) = trip {
+                    self.car_per_trip.insert(trip, car);
+                }
+            }
+        }
+        match ev {
+            Event::CarReachedParkingSpot(car, _) | Event::CarOrBikeReachedBorder(car, _) => {
+                self.entered_traversable.remove(&car);
+            }
+            Event::TripFinished { trip, .. } | Event::TripAborted(trip, _) => {
+                if let Some(car) = self.car_per_trip.remove(&trip) {
+                    self.entered_traversable.remove(&car);
+                }
+            }
+            _ => {}
+        }
+
         // Test expectations
         if !self.test_expectations.is_empty() && &ev == self.test_expectations.front().unwrap() {
             println!("At {}, met expectation {:?}", time, ev);
@@ -110,6 +172,13 @@ impl Analytics {
             self.bus_arrivals.push((time, bus, route, stop));
         }
 
+        if let Event::BusStartedScheduledTrip(bus, _, trip) = ev {
+            self.bus_scheduled_trips.insert(bus, trip);
+        }
+        if let Event::BusDepartedFromStop(bus, route, stop, passengers) = ev {
+            self.bus_loads.push((time, bus, route, stop, passengers));
+        }
+
         // Bus passengers
         if let Event::TripPhaseStarting(_, _, _, _, ref tpt) = ev {
             if let TripPhaseType::WaitingForBus(route, stop) = tpt {
@@ -148,11 +217,15 @@ impl Analytics {
         }
 
         // Intersection delays
-        if let Event::IntersectionDelayMeasured(id, delay) = ev {
+        if let Event::IntersectionDelayMeasured(turn, delay, agent) = ev {
             self.intersection_delays
-                .entry(id)
+                .entry(turn.parent)
                 .or_insert_with(Vec::new)
-                .push((time, delay));
+                .push((time, delay, agent, turn));
+        }
+
+        if let Event::CarCruisedForParking(_, trip, dist) = ev {
+            self.parking_cruising.insert(trip, dist);
         }
 
         // TODO Kinda hacky, but these all consume the event, so kinda bundle em.
@@ -168,17 +241,166 @@ impl Analytics {
                     .push((time, trip, None, TripPhaseType::Finished));
             }
             Event::PathAmended(path) => {
-                self.record_demand(&path, map);
+                self.record_demand(&path, time, map);
             }
             _ => {}
         }
     }
 
-    pub fn record_demand(&mut self, path: &Path, map: &Map) {
+    // Just the results from one day of a multi-day simulation, with times shifted back so it
+    // looks like a simulation of just that day. Trips belong to the day they started, even if
+    // they finish after midnight.
+    pub fn for_day(&self, day: usize) -> Analytics {
+        let start = Time::start_of_day(day);
+        let end = Time::start_of_day(day + 1);
+        let offset = start - Time::START_OF_DAY;
+        let this_day = |t: Time| t >= start && t < end;
+
+        let mut result = Analytics::new();
+        result.record_anything = self.record_anything;
+        for (t, mode, r) in &self.thruput_stats.raw_per_road {
+            if this_day(*t) {
+                result.thruput_stats.count_per_road.inc(*r);
+                result
+                    .thruput_stats
+                    .raw_per_road
+                    .push((*t - offset, *mode, *r));
+            }
+        }
+        for (t, mode, i) in &self.thruput_stats.raw_per_intersection {
+            if this_day(*t) {
+                result.thruput_stats.count_per_intersection.inc(*i);
+                result
+                    .thruput_stats
+                    .raw_per_intersection
+                    .push((*t - offset, *mode, *i));
+            }
+        }
+        // Demand is for a moment in time, so carry over whatever was left from earlier days
+        let mut carried_demand: BTreeMap<TurnGroupID, isize> = BTreeMap::new();
+        for (t, id, delta) in &self.thruput_stats.raw_demand {
+            if *t < start {
+                *carried_demand.entry(*id).or_insert(0) += *delta;
+            }
+        }
+        for (id, cnt) in carried_demand {
+            if cnt > 0 {
+                result
+                    .thruput_stats
+                    .raw_demand
+                    .push((Time::START_OF_DAY, id, cnt));
+            }
+        }
+        for (t, id, delta) in &self.thruput_stats.raw_demand {
+            if this_day(*t) {
+                result
+                    .thruput_stats
+                    .raw_demand
+                    .push((*t - offset, *id, *delta));
+            }
+        }
+        for (_, id, delta) in &result.thruput_stats.raw_demand {
+            let cnt = result.thruput_stats.demand.entry(*id).or_insert(0);
+            *cnt = ((*cnt as isize) + *delta).max(0) as usize;
+        }
+
+        for (t, car, route, stop) in &self.bus_arrivals {
+            if this_day(*t) {
+                result.bus_arrivals.push((*t - offset, *car, *route, *stop));
+            }
+        }
+        for (t, stop, route) in &self.bus_passengers_waiting {
+            if this_day(*t) {
+                result
+                    .bus_passengers_waiting
+                    .push((*t - offset, *stop, *route));
+            }
+        }
+        result.bus_scheduled_trips = self.bus_scheduled_trips.clone();
+        for (t, car, route, stop, passengers) in &self.bus_loads {
+            if this_day(*t) {
+                result
+                    .bus_loads
+                    .push((*t - offset, *car, *route, *stop, *passengers));
+            }
+        }
+
+        for (id, (t, mode)) in &self.started_trips {
+            if this_day(*t) {
+                result.started_trips.insert(*id, (*t - offset, *mode));
+            }
+        }
+        for (t, id, mode, dt) in &self.finished_trips {
+            if result.started_trips.contains_key(id) {
+                result.finished_trips.push((*t - offset, *id, *mode, *dt));
+            }
+        }
+        for (id, dist) in &self.parking_cruising {
+            if result.started_trips.contains_key(id) {
+                result.parking_cruising.insert(*id, *dist);
+            }
+        }
+        for (t, id, req, phase_type) in &self.trip_log {
+            if result.started_trips.contains_key(id) {
+                result
+                    .trip_log
+                    .push((*t - offset, *id, req.clone(), *phase_type));
+            }
+        }
+
+        for (i, delays) in &self.intersection_delays {
+            for (t, dt, agent, turn) in delays {
+                if this_day(*t) {
+                    result
+                        .intersection_delays
+                        .entry(*i)
+                        .or_insert_with(Vec::new)
+                        .push((*t - offset, *dt, *agent, *turn));
+                }
+            }
+        }
+        for (on, per_hour) in &self.traversal_times {
+            for (h, totals) in per_hour {
+                if *h >= day * 24 && *h < (day + 1) * 24 {
+                    result
+                        .traversal_times
+                        .entry(*on)
+                        .or_insert_with(BTreeMap::new)
+                        .insert(*h - day * 24, *totals);
+                }
+            }
+        }
+
+        result
+    }
+
+    // The average time cars took to cross each lane and turn so far. Give this to
+    // Map::set_observed_travel_times to make the next run route around congestion seen in this
+    // one.
+    pub fn observed_travel_times(&self) -> TravelTimes {
+        let mut times = TravelTimes::new();
+        for (on, per_hour) in &self.traversal_times {
+            let total: Duration = per_hour.values().map(|(dt, _)| *dt).sum();
+            let count: usize = per_hour.values().map(|(_, cnt)| *cnt).sum();
+            let avg = total / (count as f64);
+            match on {
+                Traversable::Lane(l) => {
+                    times.lanes.insert(*l, avg);
+                }
+                Traversable::Turn(t) => {
+                    times.turns.insert(*t, avg);
+                }
+            }
+        }
+        times
+    }
+
+    pub fn record_demand(&mut self, path: &Path, now: Time, map: &Map) {
         for step in path.get_steps() {
             if let Traversable::Turn(t) = step.as_traversable() {
                 if let Some(id) = map.get_turn_group(t) {
                     *self.thruput_stats.demand.entry(id).or_insert(0) += 1;
+                    self.thruput_stats.raw_demand.push((now, id, 1));
                 }
             }
         }
@@ -242,28 +464,50 @@ impl Analytics {
         now: Time,
         baseline: &Analytics,
     ) -> Vec<(Duration, Duration)> {
-        let mut a = BTreeMap::new();
-        for (t, id, maybe_mode, dt) in &self.finished_trips {
-            if *t > now {
-                break;
-            }
-            if maybe_mode.is_some() {
-                a.insert(*id, *dt);
+        self.both_finished_trips_by_id(now, baseline)
+            .into_iter()
+            .map(|(_, pair)| pair)
+            .collect()
+    }
+
+    // Like both_finished_trips, but keeps track of which trip each pair belongs to.
+    pub fn both_finished_trips_by_id(
+        &self,
+        now: Time,
+        baseline: &Analytics,
+    ) -> BTreeMap<TripID, (Duration, Duration)> {
+        let mut a = self.successful_trip_times(now);
+        let mut results = BTreeMap::new();
+        for (id, dt) in baseline.successful_trip_times(now) {
+            if let Some(dt1) = a.remove(&id) {
+                results.insert(id, (dt1, dt));
             }
         }
+        results
+    }
+
+    // Trips that finished here by some time, but were aborted or still ongoing in the other
+    // world.
+    pub fn trips_only_finished_here(&self, now: Time, other: &Analytics) -> Vec<TripID> {
+        let b = other.successful_trip_times(now);
+        self.successful_trip_times(now)
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|id| !b.contains_key(id))
+            .collect()
+    }
 
-        let mut results = Vec::new();
-        for (t, id, maybe_mode, dt) in &baseline.finished_trips {
+    fn successful_trip_times(&self, now: Time) -> BTreeMap<TripID, Duration> {
+        let mut trips = BTreeMap::new();
+        for (t, id, maybe_mode, dt) in &self.finished_trips {
             if *t > now {
                 break;
             }
             if maybe_mode.is_some() {
-                if let Some(dt1) = a.remove(id) {
-                    results.push((dt1, *dt));
-                }
+                trips.insert(*id, *dt);
             }
         }
-        results
+        trips
     }
 
     // Returns unsorted list of deltas, one for each trip finished or ongoing in both worlds.
@@ -405,6 +649,132 @@ impl Analytics {
             .collect()
     }
 
+    // How late (positive) or early (negative) each scheduled bus arrived at each stop, compared to
+    // the timetable. Empty for routes without a schedule. For each stop, a list of (arrival time,
+    // bus, deviation).
+    pub fn bus_schedule_deviations(
+        &self,
+        now: Time,
+        route: &BusRoute,
+    ) -> BTreeMap<BusStopID, Vec<(Time, CarID, Duration)>> {
+        // A route might visit the same stop twice, so track how far along each bus has gotten.
+        let mut next_idx_per_bus: BTreeMap<CarID, usize> = BTreeMap::new();
+        let mut deviations: BTreeMap<BusStopID, Vec<(Time, CarID, Duration)>> = BTreeMap::new();
+        for (t, car, r, stop) in &self.bus_arrivals {
+            if *t > now {
+                break;
+            }
+            if *r != route.id {
+                continue;
+            }
+            let trip = if let Some(trip) = self.bus_scheduled_trips.get(car) {
+                *trip
+            } else {
+                continue;
+            };
+            let next_idx = next_idx_per_bus.entry(*car).or_insert(0);
+            if let Some(idx) = (*next_idx..route.stops.len()).find(|i| route.stops[*i] == *stop) {
+                deviations.entry(*stop).or_insert_with(Vec::new).push((
+                    *t,
+                    *car,
+                    *t - route.schedule[trip][idx],
+                ));
+                *next_idx = idx + 1;
+            }
+        }
+        deviations
+    }
+
+    pub fn bus_on_time_performance(
+        &self,
+        now: Time,
+        route: &BusRoute,
+    ) -> BTreeMap<BusStopID, OnTimePerformance> {
+        self.bus_schedule_deviations(now, route)
+            .into_iter()
+            .map(|(stop, list)| {
+                let mut perf = OnTimePerformance::default();
+                for (_, _, dt) in list {
+                    perf.add(dt);
+                }
+                (stop, perf)
+            })
+            .collect()
+    }
+
+    // For each stop, the time between consecutive buses of the route arriving there. Each entry
+    // is (arrival of the later bus, earlier bus, later bus, headway).
+    pub fn bus_headways(
+        &self,
+        now: Time,
+        r: BusRouteID,
+    ) -> BTreeMap<BusStopID, Vec<(Time, CarID, CarID, Duration)>> {
+        let mut last_arrival: BTreeMap<BusStopID, (Time, CarID)> = BTreeMap::new();
+        let mut headways: BTreeMap<BusStopID, Vec<(Time, CarID, CarID, Duration)>> =
+            BTreeMap::new();
+        for (t, car, route, stop) in &self.bus_arrivals {
+            if *t > now {
+                break;
+            }
+            if *route != r {
+                continue;
+            }
+            if let Some((t1, car1)) = last_arrival.insert(*stop, (*t, *car)) {
+                headways
+                    .entry(*stop)
+                    .or_insert_with(Vec::new)
+                    .push((*t, car1, *car, *t - t1));
+            }
+        }
+        headways
+    }
+
+    // For each stop with at least two headways measured, the coefficient of variation of the
+    // headways (standard deviation / mean). Perfectly even spacing is 0; anything above
+    // BUS_BUNCHING_THRESHOLD means buses are bunching up.
+    pub fn bus_headway_variation(&self, now: Time, r: BusRouteID) -> BTreeMap<BusStopID, f64> {
+        self.bus_headways(now, r)
+            .into_iter()
+            .filter_map(|(stop, list)| {
+                if list.len() < 2 {
+                    return None;
+                }
+                let secs: Vec<f64> = list
+                    .into_iter()
+                    .map(|(_, _, _, dt)| dt.inner_seconds())
+                    .collect();
+                let mean = secs.iter().sum::<f64>() / (secs.len() as f64);
+                if mean == 0.0 {
+                    return None;
+                }
+                let variance =
+                    secs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (secs.len() as f64);
+                Some((stop, variance.sqrt() / mean))
+            })
+            .collect()
+    }
+
+    // For each stop, how many passengers were aboard buses of the route leaving it
+    pub fn bus_passenger_loads(
+        &self,
+        now: Time,
+        r: BusRouteID,
+    ) -> BTreeMap<BusStopID, Histogram<usize>> {
+        let mut loads: BTreeMap<BusStopID, Histogram<usize>> = BTreeMap::new();
+        for (t, _, route, stop, passengers) in &self.bus_loads {
+            if *t > now {
+                break;
+            }
+            if *route == r {
+                loads
+                    .entry(*stop)
+                    .or_insert_with(Histogram::new)
+                    .add(*passengers);
+            }
+        }
+        loads
+    }
+
     // Slightly misleading -- TripMode::Transit means buses, not pedestrians taking transit
     pub fn throughput_road(
         &self,
@@ -497,9 +867,15 @@ impl Analytics {
         phases
     }
 
-    fn get_all_trip_phases(&self) -> BTreeMap<TripID, Vec<TripPhase>> {
+    // Like get_trip_phases, but for every trip that's started. Paths are only calculated if a map
+    // is passed in, because that's slow.
+    pub fn get_all_trip_phases(
+        &self,
+        include_aborted: bool,
+        map: Option<&Map>,
+    ) -> BTreeMap<TripID, Vec<TripPhase>> {
         let mut trips = BTreeMap::new();
-        for (t, id, _, phase_type) in &self.trip_log {
+        for (t, id, maybe_req, phase_type) in &self.trip_log {
             let phases: &mut Vec<TripPhase> = trips.entry(*id).or_insert_with(Vec::new);
             if let Some(ref mut last) = phases.last_mut() {
                 last.end_time = Some(*t);
@@ -507,16 +883,22 @@ impl Analytics {
             if *phase_type == TripPhaseType::Finished {
                 continue;
             }
-            // Remove aborted trips
             if *phase_type == TripPhaseType::Aborted {
-                trips.remove(id);
+                if !include_aborted {
+                    trips.remove(id);
+                }
                 continue;
             }
             phases.push(TripPhase {
                 start_time: *t,
                 end_time: None,
-                // Don't compute any paths
-                path: None,
+                // The map might've been edited since, so the path could be different or gone.
+                path: match (map, maybe_req) {
+                    (Some(map), Some(req)) => map
+                        .pathfind(req.clone())
+                        .map(|path| (req.start.dist_along(), path)),
+                    _ => None,
+                },
                 phase_type: *phase_type,
             })
         }
@@ -529,7 +911,7 @@ impl Analytics {
         // "overhead" -- not the main driving part of the trip?
         // TODO This is misleading for border trips -- the driving lasts longer.
         let mut distrib: Histogram<u16> = Histogram::new();
-        for (_, phases) in self.get_all_trip_phases() {
+        for (_, phases) in self.get_all_trip_phases(false, None) {
             if phases.last().as_ref().unwrap().end_time.is_none() {
                 continue;
             }
@@ -577,7 +959,7 @@ impl Analytics {
         let mut delays = Histogram::new();
         // TODO Binary search
         if let Some(list) = self.intersection_delays.get(&i) {
-            for (t, dt) in list {
+            for (t, dt, _, _) in list {
                 if *t < t1 {
                     continue;
                 }
@@ -602,7 +984,7 @@ impl Analytics {
             (max_this_bucket, Histogram::new()),
         ];
         if let Some(list) = self.intersection_delays.get(&i) {
-            for (t, dt) in list {
+            for (t, dt, _, _) in list {
                 if *t > now {
                     break;
                 }
@@ -616,6 +998,28 @@ impl Analytics {
         results
     }
 
+    // How many times did each car following the corridor have to wait at one of its signals? Only
+    // counts delays of at least CORRIDOR_STOP_THRESHOLD for these turns, so cross traffic doesn't
+    // count. Returns the number of cars and the total number of stops.
+    pub fn corridor_stops(&self, turns: &BTreeSet<TurnID>) -> (usize, usize) {
+        let mut stops_per_car: BTreeMap<CarID, usize> = BTreeMap::new();
+        let signals: BTreeSet<IntersectionID> = turns.iter().map(|t| t.parent).collect();
+        for i in signals {
+            for (_, dt, agent, turn) in self.intersection_delays.get(&i).unwrap_or(&Vec::new()) {
+                if !turns.contains(turn) {
+                    continue;
+                }
+                if let AgentID::Car(c) = agent {
+                    let count = stops_per_car.entry(*c).or_insert(0);
+                    if *dt >= CORRIDOR_STOP_THRESHOLD {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+        (stops_per_car.len(), stops_per_car.values().sum())
+    }
+
     pub fn active_agents(&self, now: Time) -> Vec<(Time, usize)> {
         let mut starts_stops: Vec<(Time, bool)> = Vec::new();
         for (_, (t, _)) in &self.started_trips {
@@ -667,6 +1071,43 @@ impl Default for Analytics {
     }
 }
 
+// Following common transit agency practice, a bus is on time if it arrives between 1 minute early
+// and 5 minutes late.
+#[derive(Debug, Default)]
+pub struct OnTimePerformance {
+    pub early: usize,
+    pub on_time: usize,
+    pub late: usize,
+}
+
+impl OnTimePerformance {
+    fn add(&mut self, deviation: Duration) {
+        if deviation < Duration::seconds(-60.0) {
+            self.early += 1;
+        } else if deviation > Duration::minutes(5) {
+            self.late += 1;
+        } else {
+            self.on_time += 1;
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.early + self.on_time + self.late
+    }
+
+    // Combine the results from multiple stops
+    pub fn merge(&mut self, other: &OnTimePerformance) {
+        self.early += other.early;
+        self.on_time += other.on_time;
+        self.late += other.late;
+    }
+}
+
+pub const BUS_BUNCHING_THRESHOLD: f64 = 0.5;
+
+// Waiting less than this at a signal is just slowing down for a turn, not really stopping.
+pub const CORRIDOR_STOP_THRESHOLD: Duration = Duration::const_seconds(2.0);
+
 #[derive(Debug)]
 pub struct TripPhase {
     pub start_time: Time,
@@ -703,3 +1144,8 @@ impl Window {
         self.times.len()
     }
 }
+
+// Which hour since the start of the simulation a time falls in
+fn hour(t: Time) -> usize {
+    ((t - Time::START_OF_DAY).inner_seconds() / 3600.0) as usize
+}
@@ -1,7 +1,8 @@
 use crate::{AgentID, CarID, ParkingSpot, PedestrianID, PersonID, TripID, TripMode};
-use geom::Duration;
+use geom::{Distance, Duration};
 use map_model::{
     BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, Map, Path, PathRequest, Traversable,
+    TurnID,
 };
 use serde_derive::{Deserialize, Serialize};
 
@@ -11,10 +12,16 @@ use serde_derive::{Deserialize, Serialize};
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Event {
     CarReachedParkingSpot(CarID, ParkingSpot),
+    // When a car starts parking, how far it drove past the end of its original path looking for
+    // a spot
+    CarCruisedForParking(CarID, TripID, Distance),
     CarOrBikeReachedBorder(CarID, IntersectionID),
 
     BusArrivedAtStop(CarID, BusRouteID, BusStopID),
-    BusDepartedFromStop(CarID, BusRouteID, BusStopID),
+    // The last field is the number of passengers aboard when leaving.
+    BusDepartedFromStop(CarID, BusRouteID, BusStopID, usize),
+    // The last field indexes into the route's schedule.
+    BusStartedScheduledTrip(CarID, BusRouteID, usize),
 
     PersonEntersBuilding(PersonID, BuildingID),
     PersonLeavesBuilding(PersonID, BuildingID),
@@ -24,8 +31,9 @@ pub enum Event {
 
     BikeStoppedAtSidewalk(CarID, LaneID),
 
-    AgentEntersTraversable(AgentID, Traversable),
-    IntersectionDelayMeasured(IntersectionID, Duration),
+    // The trip is None for buses.
+    AgentEntersTraversable(AgentID, Traversable, Option<TripID>),
+    IntersectionDelayMeasured(TurnID, Duration, AgentID),
 
     TripFinished {
         trip: TripID,
@@ -10,18 +10,22 @@ mod sim;
 mod transit;
 mod trips;
 
-pub use self::analytics::{Analytics, TripPhase};
+pub use self::analytics::{
+    Analytics, OnTimePerformance, TripPhase, BUS_BUNCHING_THRESHOLD, CORRIDOR_STOP_THRESHOLD,
+};
 pub(crate) use self::events::Event;
 pub use self::events::TripPhaseType;
 pub use self::make::{
-    ABTest, BorderSpawnOverTime, IndividTrip, OriginDestination, PersonSpec, Scenario,
-    ScenarioGenerator, SeedParkedCars, SimFlags, SpawnOverTime, SpawnTrip, TripSpawner, TripSpec,
+    ABTest, BorderSpawnOverTime, IndividTrip, LogitModeChoice, ModeChoice, ModeOption,
+    OriginDestination, PersonSpec, Scenario, ScenarioGenerator, SeedParkedCars, SimFlags,
+    SpawnOverTime, SpawnTrip, TripSpawner, TripSpec,
 };
 pub(crate) use self::mechanics::{
     DrivingSimState, IntersectionSimState, ParkingSimState, WalkingSimState,
 };
 pub(crate) use self::pandemic::PandemicModel;
-pub(crate) use self::router::{ActionAtEnd, Router};
+pub use self::pandemic::{Intervention, PandemicConfig};
+pub(crate) use self::router::{ActionAtEnd, LiveEditOutcome, Router};
 pub(crate) use self::scheduler::{Command, Scheduler};
 pub use self::sim::{AgentProperties, Sim, SimOptions};
 pub(crate) use self::transit::TransitSimState;
@@ -1,5 +1,5 @@
-use crate::{Scenario, Sim, SimOptions};
-use abstutil::CmdArgs;
+use crate::{PandemicConfig, Scenario, Sim, SimOptions};
+use abstutil::{CmdArgs, Timer};
 use geom::Duration;
 use map_model::{Map, MapEdits};
 use rand::SeedableRng;
@@ -16,6 +16,10 @@ pub struct SimFlags {
 impl SimFlags {
     pub fn from_args(args: &mut CmdArgs) -> SimFlags {
         let rng_seed = args.optional_parse("--rng_seed", |s| s.parse());
+        // A JSON file with the disease parameters and interventions. Implies --pandemic.
+        let pandemic_config: Option<PandemicConfig> = args
+            .optional("--pandemic_config")
+            .map(|path| abstutil::read_json(path, &mut Timer::throwaway()));
 
         SimFlags {
             load: args
@@ -32,7 +36,7 @@ impl SimFlags {
                 disable_block_the_box: args.enabled("--disable_block_the_box"),
                 recalc_lanechanging: !args.enabled("--dont_recalc_lc"),
                 clear_laggy_head_early: args.enabled("--clear_laggy_head_early"),
-                enable_pandemic_model: if args.enabled("--pandemic") {
+                enable_pandemic_model: if args.enabled("--pandemic") || pandemic_config.is_some() {
                     if let Some(seed) = rng_seed {
                         Some(XorShiftRng::from_seed([seed; 16]))
                     } else {
@@ -41,6 +45,8 @@ impl SimFlags {
                 } else {
                     None
                 },
+                pandemic_config: pandemic_config.unwrap_or_else(PandemicConfig::default),
+                reroute_every: args.optional_parse("--reroute_every", Duration::parse),
             },
         }
     }
@@ -80,10 +86,9 @@ impl SimFlags {
 
             let mut map = Map::new(abstutil::path_map(&sim.map_name), false, timer);
             if sim.edits_name != "untitled edits" {
-                map.apply_edits(
-                    MapEdits::load(map.get_name(), &sim.edits_name, timer),
-                    timer,
-                );
+                let edits = MapEdits::load(&map, &sim.edits_name, timer)
+                    .unwrap_or_else(|err| panic!("{}", err));
+                map.apply_edits(edits, timer);
                 map.recalculate_pathfinding_after_edits(timer);
             }
             sim.restore_paths(&map, timer);
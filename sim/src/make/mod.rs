@@ -1,6 +1,7 @@
 mod a_b_test;
 mod generator;
 mod load;
+mod mode_choice;
 mod scenario;
 mod spawner;
 
@@ -9,5 +10,6 @@ pub use self::generator::{
     BorderSpawnOverTime, OriginDestination, ScenarioGenerator, SeedParkedCars, SpawnOverTime,
 };
 pub use self::load::SimFlags;
+pub use self::mode_choice::{LogitModeChoice, ModeChoice, ModeOption};
 pub use self::scenario::{IndividTrip, PersonSpec, Scenario, SpawnTrip};
 pub use self::spawner::{TripSpawner, TripSpec};
@@ -0,0 +1,391 @@
+use crate::{DrivingGoal, Scenario, SidewalkPOI, SidewalkSpot, SpawnTrip, TripMode};
+use abstutil::Timer;
+use geom::{Distance, Duration, Speed};
+use map_model::{BuildingID, Map, Path, PathConstraints, PathRequest, Traversable};
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Somebody could do this for a trip between two buildings. The duration is estimated using the
+// current map, including edits.
+#[derive(Clone, Debug)]
+pub struct ModeOption {
+    pub mode: TripMode,
+    pub duration: Duration,
+    pub distance: Distance,
+}
+
+// Decides how people get around. Implement this to try different models.
+pub trait ModeChoice {
+    // There's always at least one option. Returns the index of the chosen one.
+    fn choose(&self, options: &Vec<ModeOption>, rng: &mut XorShiftRng) -> usize;
+}
+
+// The multinomial logit model. The utility of each option is a constant for the mode, minus the
+// time and money it takes. The chance of choosing an option is exp(utility) divided by the sum of
+// that over all options.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct LogitModeChoice {
+    pub walk_constant: f64,
+    pub bike_constant: f64,
+    pub transit_constant: f64,
+    pub drive_constant: f64,
+    // Utility lost per hour spent traveling
+    pub per_hour: f64,
+    // Utility lost per dollar spent
+    pub per_dollar: f64,
+    pub driving_cost_per_mile: f64,
+    pub transit_fare: f64,
+}
+
+impl LogitModeChoice {
+    // Rough guesses, with time worth $15/hour. Calibrate against real mode shares before trusting
+    // the results.
+    pub fn new() -> LogitModeChoice {
+        LogitModeChoice {
+            walk_constant: 0.0,
+            bike_constant: -1.5,
+            transit_constant: -0.5,
+            drive_constant: 0.0,
+            per_hour: 3.0,
+            per_dollar: 0.2,
+            driving_cost_per_mile: 0.6,
+            transit_fare: 2.75,
+        }
+    }
+
+    fn utility(&self, option: &ModeOption) -> f64 {
+        let (constant, cost) = match option.mode {
+            TripMode::Walk => (self.walk_constant, 0.0),
+            TripMode::Bike => (self.bike_constant, 0.0),
+            TripMode::Transit => (self.transit_constant, self.transit_fare),
+            TripMode::Drive => (
+                self.drive_constant,
+                self.driving_cost_per_mile * option.distance.inner_meters() / 1609.34,
+            ),
+        };
+        constant - self.per_hour * option.duration.inner_seconds() / 3600.0 - self.per_dollar * cost
+    }
+}
+
+impl ModeChoice for LogitModeChoice {
+    fn choose(&self, options: &Vec<ModeOption>, rng: &mut XorShiftRng) -> usize {
+        let utilities: Vec<f64> = options.iter().map(|o| self.utility(o)).collect();
+        // Subtract the best utility first, so exp() doesn't overflow.
+        let best = utilities
+            .iter()
+            .cloned()
+            .fold(std::f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = utilities.iter().map(|u| (u - best).exp()).collect();
+        let total: f64 = weights.iter().sum();
+
+        let mut x = rng.gen_range(0.0, total);
+        for (idx, w) in weights.iter().enumerate() {
+            if x < *w {
+                return idx;
+            }
+            x -= w;
+        }
+        options.len() - 1
+    }
+}
+
+impl Scenario {
+    // Decide again how everybody travels between buildings, using the current map and edits.
+    // Everybody uses the same mode for their whole chain of trips, so nobody leaves their car
+    // somewhere and walks home. People with a trip starting or ending at a border keep their
+    // modes.
+    pub fn choose_modes(
+        &mut self,
+        model: &dyn ModeChoice,
+        map: &Map,
+        rng: &mut XorShiftRng,
+        timer: &mut Timer,
+    ) {
+        let mut counts = vec![0; TripMode::all().len()];
+        timer.start_iter("choose modes", self.people.len());
+        for person in &mut self.people {
+            timer.next();
+            // Map edits change the number of options, so fork the RNG to keep other people
+            // stable.
+            let mut tmp_rng = abstutil::fork_rng(rng);
+            let endpoints: Option<Vec<(BuildingID, BuildingID)>> = person
+                .trips
+                .iter()
+                .map(|trip| trip_endpoints(&trip.trip))
+                .collect();
+            let endpoints = match endpoints {
+                Some(list) if !list.is_empty() => list,
+                _ => {
+                    continue;
+                }
+            };
+            let per_trip: Vec<Vec<(ModeOption, SpawnTrip)>> = endpoints
+                .into_iter()
+                .map(|(from, to)| trip_options(from, to, map))
+                .collect();
+
+            // Only modes that work for every trip in the chain, with the time and distance
+            // added up
+            let mut options = Vec::new();
+            for mode in TripMode::all() {
+                let mut total = ModeOption {
+                    mode,
+                    duration: Duration::ZERO,
+                    distance: Distance::ZERO,
+                };
+                let mut possible = true;
+                for choices in &per_trip {
+                    if let Some((o, _)) = choices.iter().find(|(o, _)| o.mode == mode) {
+                        total.duration += o.duration;
+                        total.distance += o.distance;
+                    } else {
+                        possible = false;
+                        break;
+                    }
+                }
+                if possible {
+                    options.push(total);
+                }
+            }
+            if options.is_empty() {
+                continue;
+            }
+
+            let mode = options[model.choose(&options, &mut tmp_rng)].mode;
+            counts[TripMode::all().into_iter().position(|m| m == mode).unwrap()] +=
+                person.trips.len();
+            for (trip, choices) in person.trips.iter_mut().zip(per_trip) {
+                trip.trip = choices.into_iter().find(|(o, _)| o.mode == mode).unwrap().1;
+            }
+        }
+        for (mode, cnt) in TripMode::all().into_iter().zip(counts) {
+            timer.note(format!("{} trips chose {:?}", cnt, mode));
+        }
+
+        self.seed_needed_parked_cars(map);
+    }
+
+    // Make sure everybody who drives now has a car waiting for them. People who stopped driving
+    // still own their car, so never remove any.
+    // TODO Like popdat, this assumes trips are instantaneous.
+    fn seed_needed_parked_cars(&mut self, map: &Map) {
+        let mut drive_trips = Vec::new();
+        for person in &self.people {
+            for trip in &person.trips {
+                if let SpawnTrip::MaybeUsingParkedCar(from, ref goal) = trip.trip {
+                    let to = match goal {
+                        DrivingGoal::ParkNear(b) => Some(*b),
+                        DrivingGoal::Border(_, _) => None,
+                    };
+                    drive_trips.push((trip.depart, from, to));
+                }
+            }
+        }
+        drive_trips.sort_by_key(|(depart, _, _)| *depart);
+
+        let mut needed_per_bldg: BTreeMap<BuildingID, usize> = BTreeMap::new();
+        let mut avail_per_bldg: BTreeMap<BuildingID, usize> = BTreeMap::new();
+        for b in map.all_buildings() {
+            needed_per_bldg.insert(b.id, 0);
+            avail_per_bldg.insert(b.id, 0);
+        }
+        for (_, from, to) in drive_trips {
+            if avail_per_bldg[&from] > 0 {
+                *avail_per_bldg.get_mut(&from).unwrap() -= 1;
+            } else {
+                *needed_per_bldg.get_mut(&from).unwrap() += 1;
+            }
+            if let Some(b) = to {
+                *avail_per_bldg.get_mut(&b).unwrap() += 1;
+            }
+        }
+
+        for (b, needed) in needed_per_bldg {
+            let cnt = self.parked_cars_per_bldg.entry(b).or_insert(0);
+            *cnt = (*cnt).max(needed);
+        }
+    }
+}
+
+fn trip_endpoints(trip: &SpawnTrip) -> Option<(BuildingID, BuildingID)> {
+    let bldg = |spot: &SidewalkSpot| match spot.connection {
+        SidewalkPOI::Building(b) => Some(b),
+        _ => None,
+    };
+    match trip {
+        SpawnTrip::CarAppearing { .. } => None,
+        SpawnTrip::MaybeUsingParkedCar(from, DrivingGoal::ParkNear(to)) => Some((*from, *to)),
+        SpawnTrip::UsingBike(from, DrivingGoal::ParkNear(to)) => Some((bldg(from)?, *to)),
+        SpawnTrip::JustWalking(from, to) | SpawnTrip::UsingTransit(from, to, _, _, _) => {
+            Some((bldg(from)?, bldg(to)?))
+        }
+        SpawnTrip::MaybeUsingParkedCar(_, DrivingGoal::Border(_, _))
+        | SpawnTrip::UsingBike(_, DrivingGoal::Border(_, _)) => None,
+    }
+}
+
+// The average of what Scenario::rand_ped_speed and Scenario::rand_bike pick
+const WALKING_SPEED: Speed = Speed::const_meters_per_second(1.117);
+const BIKING_SPEED: Speed = Speed::const_meters_per_second(4.02);
+// The simulation makes cars take this long to unpark and park
+const PARKING_OVERHEAD: Duration = Duration::const_seconds(25.0);
+// Nobody knows the bus schedule
+const TRANSIT_WAIT: Duration = Duration::const_seconds(300.0);
+
+// Every way somebody could get from one building to another, using the existing pathfinders.
+fn trip_options(from: BuildingID, to: BuildingID, map: &Map) -> Vec<(ModeOption, SpawnTrip)> {
+    let start = SidewalkSpot::building(from, map);
+    let goal = SidewalkSpot::building(to, map);
+    let mut options = Vec::new();
+
+    if let Some(path) = map.pathfind(PathRequest {
+        start: start.sidewalk_pos,
+        end: goal.sidewalk_pos,
+        constraints: PathConstraints::Pedestrian,
+    }) {
+        options.push((
+            ModeOption {
+                mode: TripMode::Walk,
+                duration: path.total_length() / WALKING_SPEED,
+                distance: path.total_length(),
+            },
+            SpawnTrip::JustWalking(start.clone(), goal.clone()),
+        ));
+    }
+
+    let driving_goal = DrivingGoal::ParkNear(to);
+    if let Some(path) = map.pathfind(PathRequest {
+        start: DrivingGoal::ParkNear(from).goal_pos(PathConstraints::Bike, map),
+        end: driving_goal.goal_pos(PathConstraints::Bike, map),
+        constraints: PathConstraints::Bike,
+    }) {
+        options.push((
+            ModeOption {
+                mode: TripMode::Bike,
+                duration: path.total_length() / BIKING_SPEED,
+                distance: path.total_length(),
+            },
+            SpawnTrip::UsingBike(start.clone(), driving_goal.clone()),
+        ));
+    }
+
+    if let Some(path) = map.pathfind(PathRequest {
+        start: DrivingGoal::ParkNear(from).goal_pos(PathConstraints::Car, map),
+        end: driving_goal.goal_pos(PathConstraints::Car, map),
+        constraints: PathConstraints::Car,
+    }) {
+        options.push((
+            ModeOption {
+                mode: TripMode::Drive,
+                duration: driving_time(&path, map) + PARKING_OVERHEAD,
+                distance: path.total_length(),
+            },
+            SpawnTrip::MaybeUsingParkedCar(from, driving_goal),
+        ));
+    }
+
+    if let Some((stop1, stop2, route)) =
+        map.should_use_transit(start.sidewalk_pos, goal.sidewalk_pos)
+    {
+        let walk1 = map.pathfind(PathRequest {
+            start: start.sidewalk_pos,
+            end: map.get_bs(stop1).sidewalk_pos,
+            constraints: PathConstraints::Pedestrian,
+        });
+        let ride = map.pathfind(PathRequest {
+            start: map.get_bs(stop1).driving_pos,
+            end: map.get_bs(stop2).driving_pos,
+            constraints: PathConstraints::Bus,
+        });
+        let walk2 = map.pathfind(PathRequest {
+            start: map.get_bs(stop2).sidewalk_pos,
+            end: goal.sidewalk_pos,
+            constraints: PathConstraints::Pedestrian,
+        });
+        if let (Some(walk1), Some(ride), Some(walk2)) = (walk1, ride, walk2) {
+            let walking = walk1.total_length() + walk2.total_length();
+            options.push((
+                ModeOption {
+                    mode: TripMode::Transit,
+                    duration: walking / WALKING_SPEED + TRANSIT_WAIT + driving_time(&ride, map),
+                    distance: walking + ride.total_length(),
+                },
+                SpawnTrip::UsingTransit(start, goal, route, stop1, stop2),
+            ));
+        }
+    }
+
+    options
+}
+
+// At the speed limit, unless the map has travel times observed in a previous run
+fn driving_time(path: &Path, map: &Map) -> Duration {
+    let observed = map.get_observed_travel_times();
+    let mut total = Duration::ZERO;
+    for step in path.get_steps() {
+        let on = step.as_traversable();
+        let measured = match (observed, on) {
+            (Some(times), Traversable::Lane(l)) => times.lanes.get(&l).cloned(),
+            (Some(times), Traversable::Turn(t)) => times.turns.get(&t).cloned(),
+            (None, _) => None,
+        };
+        total += measured.unwrap_or_else(|| on.length(map) / on.speed_limit(map));
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn option(mode: TripMode, minutes: usize) -> ModeOption {
+        ModeOption {
+            mode,
+            duration: Duration::minutes(minutes),
+            distance: Distance::meters(1000.0),
+        }
+    }
+
+    #[test]
+    fn test_logit_mode_choice() {
+        let mut rng = XorShiftRng::from_seed([42; 16]);
+        let options = vec![
+            option(TripMode::Walk, 15),
+            option(TripMode::Bike, 5),
+            option(TripMode::Drive, 5),
+        ];
+
+        // An option with a much higher utility always wins, wherever it's listed.
+        let model = LogitModeChoice {
+            bike_constant: 50.0,
+            ..LogitModeChoice::new()
+        };
+        for _ in 0..100 {
+            assert_eq!(model.choose(&options, &mut rng), 1);
+        }
+        let model = LogitModeChoice {
+            walk_constant: 50.0,
+            ..LogitModeChoice::new()
+        };
+        for _ in 0..100 {
+            assert_eq!(model.choose(&options, &mut rng), 0);
+        }
+
+        // Taking hours longer is just as bad.
+        let model = LogitModeChoice::new();
+        let options = vec![option(TripMode::Drive, 10), option(TripMode::Walk, 1200)];
+        for _ in 0..100 {
+            assert_eq!(model.choose(&options, &mut rng), 0);
+        }
+
+        // With equal utilities, the options are picked about equally often.
+        let options = vec![option(TripMode::Walk, 10), option(TripMode::Walk, 10)];
+        let first = (0..1000)
+            .filter(|_| model.choose(&options, &mut rng) == 0)
+            .count();
+        assert!(first > 400 && first < 600);
+    }
+}
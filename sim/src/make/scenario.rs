@@ -1,6 +1,6 @@
 use crate::{
-    DrivingGoal, ParkingSpot, PersonID, SidewalkSpot, Sim, TripSpec, VehicleSpec, VehicleType,
-    BIKE_LENGTH, MAX_CAR_LENGTH, MIN_CAR_LENGTH,
+    DrivingGoal, ParkingSpot, PersonID, SidewalkPOI, SidewalkSpot, Sim, TripMode, TripSpec,
+    VehicleSpec, VehicleType, BIKE_LENGTH, MAX_CAR_LENGTH, MIN_CAR_LENGTH,
 };
 use abstutil::Timer;
 use geom::{Distance, Duration, Speed, Time};
@@ -326,4 +326,39 @@ impl SpawnTrip {
             },
         }
     }
+
+    pub fn mode(&self) -> TripMode {
+        match self {
+            SpawnTrip::CarAppearing { is_bike, .. } => {
+                if *is_bike {
+                    TripMode::Bike
+                } else {
+                    TripMode::Drive
+                }
+            }
+            SpawnTrip::MaybeUsingParkedCar(_, _) => TripMode::Drive,
+            SpawnTrip::UsingBike(_, _) => TripMode::Bike,
+            SpawnTrip::JustWalking(_, _) => TripMode::Walk,
+            SpawnTrip::UsingTransit(_, _, _, _, _) => TripMode::Transit,
+        }
+    }
+
+    // None for trips starting at a border or somewhere in the middle of a road
+    pub fn start_building(&self) -> Option<BuildingID> {
+        let spot = match self {
+            SpawnTrip::CarAppearing { .. } => {
+                return None;
+            }
+            SpawnTrip::MaybeUsingParkedCar(b, _) => {
+                return Some(*b);
+            }
+            SpawnTrip::UsingBike(spot, _)
+            | SpawnTrip::JustWalking(spot, _)
+            | SpawnTrip::UsingTransit(spot, _, _, _, _) => spot,
+        };
+        match spot.connection {
+            SidewalkPOI::Building(b) => Some(b),
+            _ => None,
+        }
+    }
 }
@@ -3,7 +3,7 @@ use crate::{
     TransitSimState, TripID, Vehicle, VehicleType,
 };
 use geom::{Distance, Duration, PolyLine, Time};
-use map_model::{Map, Traversable};
+use map_model::{biking_speed_factor, Map, Traversable};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
@@ -16,6 +16,8 @@ pub struct Car {
     pub trip_and_person: Option<(TripID, PersonID)>,
     pub started_at: Time,
     pub total_blocked_time: Duration,
+    // Only used when rerouting around traffic is enabled
+    pub last_rerouted: Time,
 
     // In reverse order -- most recently left is first. The sum length of these must be >=
     // vehicle.length.
@@ -44,7 +46,10 @@ impl Car {
     ) -> CarState {
         let on = self.router.head();
         let mut speed = on.speed_limit(map);
-        if let Some(s) = self.vehicle.max_speed {
+        if let Some(mut s) = self.vehicle.max_speed {
+            if self.vehicle.vehicle_type == VehicleType::Bike {
+                s = s * biking_speed_factor(on.percent_grade(map));
+            }
             speed = speed.min(s);
         }
         let dt = (dist_int.end - dist_int.start) / speed;
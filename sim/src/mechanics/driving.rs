@@ -2,11 +2,11 @@ use crate::mechanics::car::{Car, CarState};
 use crate::mechanics::Queue;
 use crate::{
     ActionAtEnd, AgentID, AgentProperties, CarID, Command, CreateCar, DistanceInterval,
-    DrawCarInput, Event, IntersectionSimState, ParkedCar, ParkingSimState, Scheduler, TimeInterval,
-    TransitSimState, TripManager, TripPositions, UnzoomedAgent, WalkingSimState,
-    FOLLOWING_DISTANCE,
+    DrawCarInput, Event, IntersectionSimState, LiveEditOutcome, ParkedCar, ParkingSimState,
+    Scheduler, TimeInterval, TransitSimState, TripManager, TripPositions, UnzoomedAgent,
+    WalkingSimState, FOLLOWING_DISTANCE,
 };
-use abstutil::{deserialize_btreemap, serialize_btreemap};
+use abstutil::{deserialize_btreemap, serialize_btreemap, Timer};
 use geom::{Distance, Duration, PolyLine, Time};
 use map_model::{BuildingID, LaneID, Map, Path, PathStep, Traversable};
 use serde_derive::{Deserialize, Serialize};
@@ -36,6 +36,7 @@ pub struct DrivingSimState {
 
     recalc_lanechanging: bool,
     clear_laggy_head_early: bool,
+    reroute_every: Option<Duration>,
 }
 
 impl DrivingSimState {
@@ -43,6 +44,7 @@ impl DrivingSimState {
         map: &Map,
         recalc_lanechanging: bool,
         clear_laggy_head_early: bool,
+        reroute_every: Option<Duration>,
     ) -> DrivingSimState {
         let mut sim = DrivingSimState {
             cars: BTreeMap::new(),
@@ -50,22 +52,27 @@ impl DrivingSimState {
             events: Vec::new(),
             recalc_lanechanging,
             clear_laggy_head_early,
+            reroute_every,
         };
+        sim.add_missing_queues(map);
+        sim
+    }
 
+    fn add_missing_queues(&mut self, map: &Map) {
         for l in map.all_lanes() {
-            if l.lane_type.is_for_moving_vehicles() {
+            if l.lane_type.is_for_moving_vehicles()
+                && !self.queues.contains_key(&Traversable::Lane(l.id))
+            {
                 let q = Queue::new(Traversable::Lane(l.id), map);
-                sim.queues.insert(q.id, q);
+                self.queues.insert(q.id, q);
             }
         }
         for t in map.all_turns().values() {
-            if !t.between_sidewalks() {
+            if !t.between_sidewalks() && !self.queues.contains_key(&Traversable::Turn(t.id)) {
                 let q = Queue::new(Traversable::Turn(t.id), map);
-                sim.queues.insert(q.id, q);
+                self.queues.insert(q.id, q);
             }
         }
-
-        sim
     }
 
     // True if it worked
@@ -98,6 +105,7 @@ impl DrivingSimState {
                 last_steps: VecDeque::new(),
                 started_at: now,
                 total_blocked_time: Duration::ZERO,
+                last_rerouted: now,
                 trip_and_person: params.trip_and_person,
             };
             if let Some(p) = params.maybe_parked_car {
@@ -258,6 +266,20 @@ impl DrivingSimState {
                 if queue.cars[0] == car.vehicle.id && queue.laggy_head.is_none() {
                     // Want to re-run, but no urgency about it happening immediately.
                     car.state = CarState::WaitingToAdvance { blocked_since: now };
+                    if let Some(period) = self.reroute_every {
+                        if now - car.last_rerouted >= period {
+                            car.last_rerouted = now;
+                            let extra_cost =
+                                |t: Traversable| self.live_delay(t, now).inner_seconds() as usize;
+                            if car
+                                .router
+                                .reroute_around_traffic(&car.vehicle, map, &extra_cost)
+                            {
+                                self.events
+                                    .push(Event::PathAmended(car.router.get_path().clone()));
+                            }
+                        }
+                    }
                     if self.recalc_lanechanging {
                         car.router.opportunistically_lanechange(&self.queues, map);
                     }
@@ -360,6 +382,7 @@ impl DrivingSimState {
                     let mut queue = self.queues.get_mut(&from).unwrap();
                     assert_eq!(queue.cars.pop_front().unwrap(), car.vehicle.id);
                     queue.laggy_head = Some(car.vehicle.id);
+                    queue.recent_delay = now - blocked_since;
                 }
 
                 // We do NOT need to update the follower. If they were Queued, they'll remain that
@@ -378,6 +401,7 @@ impl DrivingSimState {
                 self.events.push(Event::AgentEntersTraversable(
                     AgentID::Car(car.vehicle.id),
                     goto,
+                    car.trip_and_person.map(|(trip, _)| trip),
                 ));
 
                 car.last_steps.push_front(last_step);
@@ -475,6 +499,13 @@ impl DrivingSimState {
                         // behind, see the spot free, and start parking too. This can
                         // happen with multiple lanes and certain vehicle lengths.
                         parking.reserve_spot(spot);
+                        if let Some((trip, _)) = car.trip_and_person {
+                            self.events.push(Event::CarCruisedForParking(
+                                car.vehicle.id,
+                                trip,
+                                car.router.get_distance_cruised_for_parking(),
+                            ));
+                        }
                         scheduler
                             .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                         true
@@ -499,14 +530,17 @@ impl DrivingSimState {
                         false
                     }
                     Some(ActionAtEnd::BusAtStop) => {
-                        transit.bus_arrived_at_stop(
+                        if !transit.bus_arrived_at_stop(
                             now,
                             car.vehicle.id,
                             trips,
                             walking,
                             scheduler,
                             map,
-                        );
+                        ) {
+                            // A scheduled bus reached the end of its route.
+                            return false;
+                        }
                         car.total_blocked_time += now - blocked_since;
                         car.state = CarState::Idling(
                             our_dist,
@@ -1016,6 +1050,80 @@ impl DrivingSimState {
         }
     }
 
+    // How long a car entering this lane or turn right now would probably wait at the end of it,
+    // judging by the last car to leave and whoever's stuck at the front right now.
+    fn live_delay(&self, on: Traversable, now: Time) -> Duration {
+        let queue = match self.queues.get(&on) {
+            Some(q) => q,
+            None => {
+                return Duration::ZERO;
+            }
+        };
+        let mut delay = queue.recent_delay;
+        if let Some(car) = queue.cars.front().and_then(|id| self.cars.get(id)) {
+            match car.state {
+                CarState::Queued { blocked_since }
+                | CarState::WaitingToAdvance { blocked_since } => {
+                    if now - blocked_since > delay {
+                        delay = now - blocked_since;
+                    }
+                }
+                _ => {}
+            }
+        }
+        delay
+    }
+
+    // After the map is edited mid-simulation, make sure every vehicle can still reach its goal,
+    // or at least stop somewhere that makes sense. Changes to parking aren't handled here.
+    pub fn handle_live_edits(
+        &mut self,
+        now: Time,
+        map: &Map,
+        intersections: &mut IntersectionSimState,
+        scheduler: &mut Scheduler,
+        timer: &mut Timer,
+    ) {
+        self.add_missing_queues(map);
+
+        let mut num_rerouted = 0;
+        let mut num_stranded = 0;
+        for car in self.cars.values_mut() {
+            let old_next = car.router.maybe_next();
+            match car.router.handle_live_edits(&car.vehicle, map) {
+                LiveEditOutcome::Unchanged => {
+                    continue;
+                }
+                LiveEditOutcome::Rerouted => {
+                    num_rerouted += 1;
+                }
+                LiveEditOutcome::Stranded => {
+                    num_stranded += 1;
+                }
+            }
+            self.events
+                .push(Event::PathAmended(car.router.get_path().clone()));
+
+            // If the car was waiting to start a turn that isn't part of the path anymore, the
+            // intersection won't wake it up.
+            if let Some(Traversable::Turn(t)) = old_next {
+                if car.router.maybe_next() != old_next {
+                    intersections.cancel_request(AgentID::Car(car.vehicle.id), t);
+                    if let CarState::WaitingToAdvance { blocked_since } = car.state {
+                        if car.router.last_step() {
+                            car.state = CarState::Queued { blocked_since };
+                        }
+                        scheduler.update(now, Command::UpdateCar(car.vehicle.id));
+                    }
+                }
+            }
+        }
+        timer.note(format!(
+            "After live edits, {} vehicles found a new path and {} can't reach their goal",
+            num_rerouted, num_stranded
+        ));
+    }
+
     pub fn collect_events(&mut self) -> Vec<Event> {
         std::mem::replace(&mut self.events, Vec::new())
     }
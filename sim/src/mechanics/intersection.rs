@@ -5,18 +5,22 @@ use abstutil::{deserialize_btreemap, serialize_btreemap};
 use derivative::Derivative;
 use geom::{Duration, Time};
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, IntersectionID, LaneID, Map, RoadID, TurnID,
-    TurnPriority, TurnType,
+    ControlRoundabout, ControlStopSign, ControlTrafficSignal, IntersectionID, LaneID, Map, Phase,
+    PhaseType, RoadID, TurnID, TurnPriority, TurnType,
 };
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 const WAIT_AT_STOP_SIGN: Duration = Duration::const_seconds(0.5);
 const WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL: Duration = Duration::const_seconds(0.2);
+const WAIT_BEFORE_ENTERING_ROUNDABOUT: Duration = Duration::const_seconds(0.2);
 
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub struct IntersectionSimState {
     state: BTreeMap<IntersectionID, State>,
+    // The plan each traffic signal's state was started from, to tell which ones a live edit
+    // changes
+    signal_plans: BTreeMap<IntersectionID, ControlTrafficSignal>,
     use_freeform_policy_everywhere: bool,
     force_queue_entry: bool,
     events: Vec<Event>,
@@ -33,6 +37,22 @@ struct State {
         deserialize_with = "deserialize_btreemap"
     )]
     waiting: BTreeMap<Request, Time>,
+    // When did somebody last start waiting for each turn? Only tracked for traffic signals, to
+    // detect gaps in demand.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    last_arrival: BTreeMap<TurnID, Time>,
+    // Only for traffic signals
+    signal: Option<SignalState>,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+struct SignalState {
+    current_phase: usize,
+    // When a fixed phase ends, or the latest an actuated phase can end
+    latest_end: Time,
 }
 
 impl IntersectionSimState {
@@ -44,22 +64,35 @@ impl IntersectionSimState {
     ) -> IntersectionSimState {
         let mut sim = IntersectionSimState {
             state: BTreeMap::new(),
+            signal_plans: BTreeMap::new(),
             use_freeform_policy_everywhere,
             force_queue_entry: disable_block_the_box,
             events: Vec::new(),
         };
         for i in map.all_intersections() {
-            sim.state.insert(
-                i.id,
-                State {
-                    id: i.id,
-                    accepted: BTreeSet::new(),
-                    waiting: BTreeMap::new(),
-                },
-            );
+            let mut state = State {
+                id: i.id,
+                accepted: BTreeSet::new(),
+                waiting: BTreeMap::new(),
+                last_arrival: BTreeMap::new(),
+                signal: None,
+            };
             if i.is_traffic_signal() && !use_freeform_policy_everywhere {
-                sim.update_intersection(Time::START_OF_DAY, i.id, map, scheduler);
+                // Start following the fixed-time plan, which takes the offset into account.
+                let signal = map.get_traffic_signal(i.id);
+                let (idx, _, remaining) =
+                    signal.current_phase_and_remaining_time(Time::START_OF_DAY);
+                sim.signal_plans.insert(i.id, signal.clone());
+                state.signal = Some(SignalState {
+                    current_phase: idx,
+                    latest_end: Time::START_OF_DAY + remaining,
+                });
+                scheduler.push(
+                    Time::START_OF_DAY + remaining,
+                    Command::UpdateIntersection(i.id),
+                );
             }
+            sim.state.insert(i.id, state);
         }
         sim
     }
@@ -125,7 +158,7 @@ impl IntersectionSimState {
                 protected.push(req);
             }
         } else if let Some(ref signal) = map.maybe_get_traffic_signal(i) {
-            let (_, phase, _) = signal.current_phase_and_remaining_time(now);
+            let (_, phase, _) = self.state[&i].current_phase(signal, now);
             for (req, _) in all {
                 match phase.get_priority_of_turn(req.turn, signal) {
                     TurnPriority::Protected => {
@@ -147,6 +180,15 @@ impl IntersectionSimState {
                     yielding.push(req);
                 }
             }
+        } else if let Some(ref roundabout) = map.maybe_get_roundabout(i) {
+            for (req, _) in all {
+                // Circulating traffic gets the first chance to go.
+                if roundabout.get_priority(req.turn, map) == TurnPriority::Protected {
+                    protected.push(req);
+                } else {
+                    yielding.push(req);
+                }
+            }
         } else {
             assert!(map.get_i(i).is_border());
         };
@@ -166,19 +208,125 @@ impl IntersectionSimState {
         }
     }
 
-    // This is only triggered for traffic signals.
+    // This is only triggered for traffic signals. Either extends an actuated phase or moves to
+    // the next phase.
     pub fn update_intersection(
-        &self,
+        &mut self,
         now: Time,
         id: IntersectionID,
         map: &Map,
         scheduler: &mut Scheduler,
     ) {
+        let signal = map.get_traffic_signal(id);
+        let state = self.state.get_mut(&id).unwrap();
+        let (current_idx, phase, _) = state.current_phase(signal, now);
+
+        if let PhaseType::Actuated { gap, .. } = phase.phase_type {
+            let latest_end = state.signal.as_ref().unwrap().latest_end;
+            if now < latest_end {
+                // Keep the phase going while somebody's still waiting for it, or until nobody
+                // new has shown up for a while.
+                let next_check = if state.has_demand(phase, signal) {
+                    Some(now + gap)
+                } else {
+                    state
+                        .last_arrival_for(phase, signal)
+                        .map(|t| t + gap)
+                        .filter(|t| *t > now)
+                };
+                if let Some(t) = next_check {
+                    scheduler.push(t.min(latest_end), Command::UpdateIntersection(id));
+                    return;
+                }
+            }
+        }
+
+        // Move to the next phase, maybe skipping some without demand.
+        let mut next_idx = (current_idx + 1) % signal.phases.len();
+        for _ in 0..signal.phases.len() {
+            let next = &signal.phases[next_idx];
+            let skippable = match next.phase_type {
+                PhaseType::Actuated {
+                    skip_if_no_demand, ..
+                } => skip_if_no_demand && !state.has_demand(next, signal),
+                PhaseType::Fixed(_) => false,
+            };
+            if !skippable {
+                break;
+            }
+            next_idx = (next_idx + 1) % signal.phases.len();
+        }
+        let next = &signal.phases[next_idx];
+        let (first_check, latest_end) = match next.phase_type {
+            PhaseType::Fixed(d) => (now + d, now + d),
+            PhaseType::Actuated {
+                min_green,
+                max_green,
+                ..
+            } => (now + min_green, now + max_green),
+        };
+        state.signal = Some(SignalState {
+            current_phase: next_idx,
+            latest_end,
+        });
+        scheduler.push(first_check, Command::UpdateIntersection(id));
+
         self.wakeup_waiting(now, id, scheduler, map);
-        let (_, _, remaining) = map
-            .get_traffic_signal(id)
-            .current_phase_and_remaining_time(now);
-        scheduler.push(now + remaining, Command::UpdateIntersection(id));
+    }
+
+    // Call after editing the map mid-simulation. Traffic signals whose plan changed start over
+    // following their new fixed-time plan, and intersections that stopped being signals drop their
+    // signal state. Signals that weren't touched keep going undisturbed.
+    pub fn handle_live_edits(&mut self, now: Time, map: &Map, scheduler: &mut Scheduler) {
+        let mut changed: BTreeSet<IntersectionID> = map
+            .get_edits()
+            .original_intersections
+            .keys()
+            .cloned()
+            .collect();
+        for state in self.state.values_mut() {
+            let is_signal =
+                map.get_i(state.id).is_traffic_signal() && !self.use_freeform_policy_everywhere;
+            if !is_signal {
+                if state.signal.is_some() {
+                    state.signal = None;
+                    self.signal_plans.remove(&state.id);
+                    scheduler.cancel(Command::UpdateIntersection(state.id));
+                    changed.insert(state.id);
+                }
+                continue;
+            }
+            let signal = map.get_traffic_signal(state.id);
+            if state.signal.is_some() && self.signal_plans.get(&state.id) == Some(signal) {
+                continue;
+            }
+
+            let (idx, _, remaining) = signal.current_phase_and_remaining_time(now);
+            state.signal = Some(SignalState {
+                current_phase: idx,
+                latest_end: now + remaining,
+            });
+            self.signal_plans.insert(state.id, signal.clone());
+            scheduler.update(now + remaining, Command::UpdateIntersection(state.id));
+            changed.insert(state.id);
+        }
+        // The new control might let somebody go.
+        for i in changed {
+            self.wakeup_waiting(now, i, scheduler, map);
+        }
+    }
+
+    // Returns the current phase and the most time it could last. If the intersection isn't
+    // controlled by the simulation's signal state, falls back to the fixed-time plan.
+    pub fn current_phase_and_remaining_time(
+        &self,
+        now: Time,
+        id: IntersectionID,
+        map: &Map,
+    ) -> (usize, Duration) {
+        let signal = map.get_traffic_signal(id);
+        let (idx, _, remaining) = self.state[&id].current_phase(signal, now);
+        (idx, remaining)
     }
 
     // For cars: The head car calls this when they're at the end of the lane WaitingToAdvance. If
@@ -201,7 +349,13 @@ impl IntersectionSimState {
         //let debug = turn.parent == IntersectionID(64);
         let req = Request { agent, turn };
         let state = self.state.get_mut(&turn.parent).unwrap();
-        state.waiting.entry(req.clone()).or_insert(now);
+        if !state.waiting.contains_key(&req) {
+            state.waiting.insert(req.clone(), now);
+            if state.signal.is_some() && map.get_t(turn).turn_type != TurnType::SharedSidewalkCorner
+            {
+                state.last_arrival.insert(turn, now);
+            }
+        }
 
         let allowed = if self.use_freeform_policy_everywhere {
             state.freeform_policy(&req, map)
@@ -209,6 +363,8 @@ impl IntersectionSimState {
             state.traffic_signal_policy(signal, &req, speed, now, map, scheduler)
         } else if let Some(ref sign) = map.maybe_get_stop_sign(state.id) {
             state.stop_sign_policy(sign, &req, now, map, scheduler)
+        } else if let Some(ref roundabout) = map.maybe_get_roundabout(state.id) {
+            state.roundabout_policy(roundabout, &req, now, map, scheduler)
         } else {
             unreachable!()
         };
@@ -231,12 +387,14 @@ impl IntersectionSimState {
 
         assert!(!state.any_accepted_conflict_with(turn, map));
 
-        // TODO For now, we're only interested in signals, and there's too much raw data to store
-        // for stop signs too.
+        // TODO For now, we're only interested in signals and the roundabouts that might replace
+        // them, and there's too much raw data to store for stop signs too.
         let delay = now - state.waiting.remove(&req).unwrap();
-        if map.maybe_get_traffic_signal(state.id).is_some() {
+        if map.maybe_get_traffic_signal(state.id).is_some()
+            || map.maybe_get_roundabout(state.id).is_some()
+        {
             self.events
-                .push(Event::IntersectionDelayMeasured(turn.parent, delay));
+                .push(Event::IntersectionDelayMeasured(turn, delay, req.agent));
         }
         state.accepted.insert(req);
         /*if debug {
@@ -251,6 +409,8 @@ impl IntersectionSimState {
             println!("{}", abstutil::to_json(sign));
         } else if let Some(ref signal) = map.maybe_get_traffic_signal(id) {
             println!("{}", abstutil::to_json(signal));
+        } else if let Some(ref roundabout) = map.maybe_get_roundabout(id) {
+            println!("{}", abstutil::to_json(roundabout));
         } else {
             println!("Border");
         }
@@ -318,6 +478,37 @@ impl IntersectionSimState {
 }
 
 impl State {
+    // (index, phase, remaining time) of the current phase. For actuated phases, the remaining
+    // time is the most the phase could last.
+    fn current_phase<'a>(
+        &self,
+        signal: &'a ControlTrafficSignal,
+        now: Time,
+    ) -> (usize, &'a Phase, Duration) {
+        if let Some(ref s) = self.signal {
+            // The signal might've been edited live to have fewer phases.
+            let idx = s.current_phase.min(signal.phases.len() - 1);
+            (idx, &signal.phases[idx], s.latest_end - now)
+        } else {
+            signal.current_phase_and_remaining_time(now)
+        }
+    }
+
+    // Is anybody waiting for a turn that this phase allows?
+    fn has_demand(&self, phase: &Phase, signal: &ControlTrafficSignal) -> bool {
+        self.waiting
+            .keys()
+            .any(|req| phase.get_priority_of_turn(req.turn, signal) != TurnPriority::Banned)
+    }
+
+    fn last_arrival_for(&self, phase: &Phase, signal: &ControlTrafficSignal) -> Option<Time> {
+        self.last_arrival
+            .iter()
+            .filter(|(t, _)| phase.get_priority_of_turn(**t, signal) != TurnPriority::Banned)
+            .map(|(_, time)| *time)
+            .max()
+    }
+
     fn any_accepted_conflict_with(&self, t: TurnID, map: &Map) -> bool {
         let turn = map.get_t(t);
         self.accepted
@@ -379,6 +570,41 @@ impl State {
         true
     }
 
+    fn roundabout_policy(
+        &self,
+        roundabout: &ControlRoundabout,
+        req: &Request,
+        now: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        if self.any_accepted_conflict_with(req.turn, map) {
+            return false;
+        }
+
+        // Circulating traffic and pedestrians go as soon as nothing conflicts.
+        if roundabout.get_priority(req.turn, map) == TurnPriority::Protected {
+            return true;
+        }
+
+        // Everybody entering has to look for a gap first.
+        let our_time = self.waiting[req];
+        if now < our_time + WAIT_BEFORE_ENTERING_ROUNDABOUT {
+            // Since we have "ownership" of scheduling for req.agent, don't need to use
+            // scheduler.update.
+            scheduler.push(
+                our_time + WAIT_BEFORE_ENTERING_ROUNDABOUT,
+                Command::update_agent(req.agent),
+            );
+            return false;
+        }
+
+        // Like at stop signs, don't check for waiting circulating traffic. It goes as soon as
+        // nothing conflicts, so if it's still waiting, it must be blocked.
+
+        true
+    }
+
     fn traffic_signal_policy(
         &self,
         signal: &ControlTrafficSignal,
@@ -395,7 +621,7 @@ impl State {
             return true;
         }
 
-        let (_, phase, remaining_phase_time) = signal.current_phase_and_remaining_time(now);
+        let (_, phase, remaining_phase_time) = self.current_phase(signal, now);
 
         // Can't go at all this phase.
         let our_priority = phase.get_priority_of_turn(req.turn, signal);
@@ -435,11 +661,12 @@ impl State {
         let time_to_cross = turn.geom.length() / speed;
         if time_to_cross > remaining_phase_time {
             // Actually, we might have bigger problems...
-            if time_to_cross > phase.duration {
+            if time_to_cross > phase.phase_type.simple_duration() {
                 println!(
                     "OYYY! {:?} is impossible to fit into phase duration of {}. Allowing, but fix \
                      the policy!",
-                    req, phase.duration
+                    req,
+                    phase.phase_type.simple_duration()
                 );
             } else {
                 return false;
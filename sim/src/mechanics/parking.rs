@@ -3,12 +3,20 @@ use abstutil::{
     deserialize_btreemap, deserialize_multimap, serialize_btreemap, serialize_multimap, MultiMap,
     Timer,
 };
-use geom::{Distance, Pt2D};
+use geom::{Distance, Duration, Pt2D};
 use map_model;
-use map_model::{BuildingID, Lane, LaneID, LaneType, Map, Position, Traversable};
+use map_model::{BuildingID, Lane, LaneID, LaneType, Map, ParkingRules, Position, Traversable};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 
+// Drivers don't know how long they'll stay, so they all assume this when comparing prices and time
+// limits.
+const TYPICAL_STAY: Duration = Duration::const_seconds(7200.0);
+// How much further somebody would walk to save a dollar
+const WALK_PER_DOLLAR: Distance = Distance::const_meters(100.0);
+// Spots with a time limit shorter than TYPICAL_STAY are as bad as walking this much further.
+const TIME_LIMIT_PENALTY: Distance = Distance::const_meters(1000.0);
+
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub struct ParkingSimState {
     #[serde(
@@ -222,30 +230,55 @@ impl ParkingSimState {
         Some(&self.parked_cars[&car])
     }
 
-    // And the driving position
-    // TODO This one is trickier!
-    pub fn get_first_free_spot(
+    pub fn get_rules(&self, spot: ParkingSpot, map: &Map) -> ParkingRules {
+        match spot {
+            ParkingSpot::Onstreet(l, _) => map.get_parent(l).parking_rules,
+            ParkingSpot::Offstreet(b, _) => map.get_b(b).parking.as_ref().unwrap().rules,
+        }
+    }
+
+    // How bad is parking here for somebody going to the target building, expressed as a walking
+    // distance? Walking further is worse, and so is paying or risking a ticket.
+    pub fn spot_cost(&self, spot: ParkingSpot, target: BuildingID, map: &Map) -> Distance {
+        let bldg = map.get_b(target);
+        let walk = match spot {
+            // Their own spot, or at least the place they're going
+            ParkingSpot::Offstreet(b, _) if b == target => Distance::ZERO,
+            _ => self
+                .spot_to_sidewalk_pos(spot, map)
+                .pt(map)
+                .dist_to(bldg.front_path.sidewalk.pt(map)),
+        };
+        let rules = self.get_rules(spot, map);
+        let mut cost =
+            walk + WALK_PER_DOLLAR * (rules.price_per_hour * TYPICAL_STAY.inner_seconds() / 3600.0);
+        if rules.time_limit.map(|t| t < TYPICAL_STAY).unwrap_or(false) {
+            cost += TIME_LIMIT_PENALTY;
+        }
+        cost
+    }
+
+    // The cheapest free spot reachable from this driving position without leaving the lane, and
+    // the driving position for it.
+    pub fn get_best_free_spot(
         &self,
         driving_pos: Position,
         vehicle: &Vehicle,
+        target: BuildingID,
         map: &Map,
-    ) -> Option<(ParkingSpot, Position)> {
-        let mut maybe_spot = None;
-        // TODO Ideally don't fill in one side first before considering the other.
+    ) -> Option<(ParkingSpot, Position, Distance)> {
+        let mut candidates = Vec::new();
         for l in self.driving_to_parking_lanes.get(driving_pos.lane()) {
             let parking_dist = driving_pos
                 .equiv_pos(*l, driving_pos.dist_along(), map)
                 .dist_along();
             let lane = &self.onstreet_lanes[l];
-            // Bit hacky to enumerate here to conveniently get idx.
             for (idx, spot) in lane.spots().into_iter().enumerate() {
                 if self.is_free(spot) && parking_dist <= lane.dist_along_for_car(idx, vehicle) {
-                    maybe_spot = Some(spot);
-                    break;
+                    candidates.push(spot);
                 }
             }
         }
-
         for b in self.driving_to_offstreet.get(driving_pos.lane()) {
             let bldg_dist = map
                 .get_b(*b)
@@ -257,25 +290,20 @@ impl ParkingSimState {
             if driving_pos.dist_along() > bldg_dist {
                 continue;
             }
-            // Is this potential spot closer than the current best spot?
-            if maybe_spot
-                .map(|spot| bldg_dist > self.spot_to_driving_pos(spot, vehicle, map).dist_along())
-                .unwrap_or(false)
+            // All of the spots in one building cost the same, so just take the first free one.
+            if let Some(spot) = (0..self.num_spots_per_offstreet[&b])
+                .map(|idx| ParkingSpot::offstreet(*b, idx))
+                .find(|spot| self.is_free(*spot))
             {
-                continue;
-            }
-
-            for idx in 0..self.num_spots_per_offstreet[&b] {
-                let spot = ParkingSpot::offstreet(*b, idx);
-                if self.is_free(spot) {
-                    maybe_spot = Some(spot);
-                    break;
-                }
+                candidates.push(spot);
             }
         }
 
-        let spot = maybe_spot?;
-        Some((spot, self.spot_to_driving_pos(spot, vehicle, map)))
+        let (spot, cost) = candidates
+            .into_iter()
+            .map(|spot| (spot, self.spot_cost(spot, target, map)))
+            .min_by_key(|(_, cost)| *cost)?;
+        Some((spot, self.spot_to_driving_pos(spot, vehicle, map), cost))
     }
 
     pub fn spot_to_driving_pos(&self, spot: ParkingSpot, vehicle: &Vehicle, map: &Map) -> Position {
@@ -1,6 +1,6 @@
 use crate::mechanics::car::{Car, CarState};
 use crate::{CarID, FOLLOWING_DISTANCE};
-use geom::{Distance, Time};
+use geom::{Distance, Duration, Time};
 use map_model::{Map, Traversable};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{BTreeMap, VecDeque};
@@ -19,6 +19,8 @@ pub struct Queue {
     // length first. This is unused for turns themselves. This value can exceed geom_len (for the
     // edge case of ONE long car on a short queue).
     pub reserved_length: Distance,
+    // How long the last car to leave this queue waited at the front of it.
+    pub recent_delay: Duration,
 }
 
 impl Queue {
@@ -29,6 +31,7 @@ impl Queue {
             laggy_head: None,
             geom_len: id.length(map),
             reserved_length: Distance::ZERO,
+            recent_delay: Duration::ZERO,
         }
     }
 
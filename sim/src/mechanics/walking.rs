@@ -6,7 +6,10 @@ use crate::{
 };
 use abstutil::{deserialize_multimap, serialize_multimap, MultiMap};
 use geom::{Distance, Duration, Line, PolyLine, Speed, Time};
-use map_model::{BuildingID, BusRouteID, Map, Path, PathStep, Traversable, SIDEWALK_THICKNESS};
+use map_model::{
+    walking_speed_factor, BuildingID, BusRouteID, Map, Path, PathStep, Traversable,
+    SIDEWALK_THICKNESS,
+};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
@@ -468,7 +471,12 @@ impl Pedestrian {
             }
         };
         let dist_int = DistanceInterval::new_walking(start_dist, end_dist);
-        let time_int = TimeInterval::new(start_time, start_time + dist_int.length() / self.speed);
+        let mut grade = self.path.current_step().as_traversable().percent_grade(map);
+        if let PathStep::ContraflowLane(_) = self.path.current_step() {
+            grade *= -1.0;
+        }
+        let speed = self.speed * walking_speed_factor(grade);
+        let time_int = TimeInterval::new(start_time, start_time + dist_int.length() / speed);
         PedState::Crossing(dist_int, time_int)
     }
 
@@ -610,6 +618,7 @@ impl Pedestrian {
         events.push(Event::AgentEntersTraversable(
             AgentID::Pedestrian(self.id),
             self.path.current_step().as_traversable(),
+            Some(self.trip),
         ));
         true
     }
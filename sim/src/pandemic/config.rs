@@ -0,0 +1,134 @@
+use geom::{Duration, Time};
+use map_model::BuildingID;
+use serde_derive::{Deserialize, Serialize};
+
+// Everything the pandemic model assumes about the disease and how people respond to it. Load
+// this from JSON to experiment without recompiling; times and durations are in seconds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PandemicConfig {
+    // How long people stay exposed before becoming infectious, and then infectious before
+    // recovering, being hospitalized, or dying. Both are normally distributed, with the standard
+    // deviation being half of the mean.
+    pub t_inc: Duration,
+    pub t_inf: Duration,
+    // Basic reproduction number. Together with t_inf, this determines how long a susceptible
+    // person has to share a space with an infectious one to be exposed.
+    pub r_0: f64,
+    // At the start of the simulation, this fraction of people are exposed, and this fraction of
+    // those are already infectious.
+    pub initial_exposed_ratio: f64,
+    pub initial_infectious_ratio: f64,
+    pub p_hosp: f64,
+    pub p_death: f64,
+
+    #[serde(default)]
+    pub interventions: Vec<Intervention>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Intervention {
+    // Between start and end, nobody is exposed inside these buildings, even if they went inside
+    // before the closure started. The trips to and from them still happen; they're part of the
+    // scenario.
+    CloseBuildings {
+        bldgs: Vec<BuildingID>,
+        start: Time,
+        end: Time,
+    },
+    // Buses never carry more than this many people; everybody else waits for the next one.
+    LimitBusOccupancy {
+        max_passengers: usize,
+    },
+    // Between start and end, the time somebody spends near an infectious person is scaled by
+    // this factor, in [0, 1]. Overlapping mask mandates multiply.
+    Masks {
+        factor: f64,
+        start: Time,
+        end: Time,
+    },
+}
+
+impl PandemicConfig {
+    // TODO These are dummy values
+    pub fn default() -> PandemicConfig {
+        PandemicConfig {
+            t_inc: Duration::hours(1),
+            t_inf: Duration::hours(1),
+            r_0: 2.5,
+            initial_exposed_ratio: 0.01,
+            initial_infectious_ratio: 0.05,
+            p_hosp: 0.5,
+            p_death: 0.5,
+            interventions: Vec::new(),
+        }
+    }
+
+    // How much of the time two people spent together between start and end counts towards
+    // exposure. Time inside a closed building doesn't count, and masks scale the rest.
+    pub fn exposure(&self, bldg: Option<BuildingID>, start: Time, end: Time) -> Duration {
+        // Interventions only change at their start and end, so split up the window there.
+        let mut cuts = vec![start, end];
+        for i in &self.interventions {
+            match i {
+                Intervention::CloseBuildings {
+                    start: t1, end: t2, ..
+                }
+                | Intervention::Masks {
+                    start: t1, end: t2, ..
+                } => {
+                    cuts.push(*t1);
+                    cuts.push(*t2);
+                }
+                Intervention::LimitBusOccupancy { .. } => {}
+            }
+        }
+        cuts.retain(|t| *t >= start && *t <= end);
+        cuts.sort();
+        cuts.dedup();
+
+        let mut total = Duration::ZERO;
+        for pair in cuts.windows(2) {
+            if bldg.map(|b| self.is_closed(b, pair[0])).unwrap_or(false) {
+                continue;
+            }
+            total += (pair[1] - pair[0]) * self.mask_factor(pair[0]);
+        }
+        total
+    }
+
+    fn is_closed(&self, b: BuildingID, now: Time) -> bool {
+        self.interventions.iter().any(|i| match i {
+            Intervention::CloseBuildings { bldgs, start, end } => {
+                now >= *start && now < *end && bldgs.contains(&b)
+            }
+            _ => false,
+        })
+    }
+
+    fn mask_factor(&self, now: Time) -> f64 {
+        let mut factor = 1.0;
+        for i in &self.interventions {
+            if let Intervention::Masks {
+                factor: f,
+                start,
+                end,
+            } = i
+            {
+                if now >= *start && now < *end {
+                    factor *= f;
+                }
+            }
+        }
+        factor
+    }
+
+    pub fn max_bus_passengers(&self) -> Option<usize> {
+        self.interventions
+            .iter()
+            .filter_map(|i| match i {
+                Intervention::LimitBusOccupancy { max_passengers } => Some(*max_passengers),
+                _ => None,
+            })
+            .min()
+    }
+}
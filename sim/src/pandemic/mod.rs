@@ -1,5 +1,7 @@
+mod config;
 mod pandemic;
 
+pub use config::{Intervention, PandemicConfig};
 use geom::{Duration, Time};
 pub use pandemic::{Cmd, PandemicModel};
 use rand::Rng;
@@ -86,14 +88,16 @@ pub struct Event {
 }
 
 impl Event {
-    fn next(&self, now: AnyTime, rng: &mut XorShiftRng) -> State {
+    fn next(&self, now: AnyTime, cfg: &PandemicConfig, rng: &mut XorShiftRng) -> State {
+        let t_inc = cfg.t_inc.inner_seconds();
+        let t_inf = cfg.t_inf.inner_seconds();
         match self.s {
             StateEvent::Exposition => State::Exposed((
                 Event {
                     s: StateEvent::Incubation,
                     p_hosp: self.p_hosp,
                     p_death: self.p_death,
-                    t: now + State::get_time_normal(State::T_INC, State::T_INC / 2.0, rng),
+                    t: now + State::get_time_normal(t_inc, t_inc / 2.0, rng),
                 },
                 now.into(),
             )),
@@ -104,7 +108,7 @@ impl Event {
                             s: StateEvent::Recovery,
                             p_hosp: self.p_hosp,
                             p_death: self.p_death,
-                            t: now + State::get_time_normal(State::T_INF, State::T_INF / 2.0, rng),
+                            t: now + State::get_time_normal(t_inf, t_inf / 2.0, rng),
                         },
                         now.into(),
                     ))
@@ -114,7 +118,7 @@ impl Event {
                             s: StateEvent::Hospitalization,
                             p_hosp: self.p_hosp,
                             p_death: self.p_death,
-                            t: now + State::get_time_normal(State::T_INF, State::T_INF / 2.0, rng),
+                            t: now + State::get_time_normal(t_inf, t_inf / 2.0, rng),
                         },
                         now.into(),
                     ))
@@ -127,7 +131,7 @@ impl Event {
                             s: StateEvent::Recovery,
                             p_hosp: self.p_hosp,
                             p_death: self.p_death,
-                            t: now + State::get_time_normal(State::T_INF, State::T_INF / 2.0, rng),
+                            t: now + State::get_time_normal(t_inf, t_inf / 2.0, rng),
                         },
                         now.into(),
                     ))
@@ -137,7 +141,7 @@ impl Event {
                             s: StateEvent::Death,
                             p_hosp: self.p_hosp,
                             p_death: self.p_death,
-                            t: now + State::get_time_normal(State::T_INF, State::T_INF / 2.0, rng),
+                            t: now + State::get_time_normal(t_inf, t_inf / 2.0, rng),
                         },
                         now.into(),
                     ))
@@ -160,22 +164,6 @@ pub enum State {
 }
 
 impl State {
-    const T_INF: f64 = 360.0 * 10.0; // TODO dummy values
-    const T_INC: f64 = 3600.0; // TODO dummy values
-    const R_0: f64 = 2.5;
-    // const S_RATIO: f64 = 0.985;
-    const E_RATIO: f64 = 0.01;
-    const I_RATIO: f64 = 0.05;
-    // const R_RATIO: f64 = 0.0;
-
-    pub fn ini_infectious_ratio() -> f64 {
-        Self::I_RATIO
-    }
-
-    pub fn ini_exposed_ratio() -> f64 {
-        Self::E_RATIO
-    }
-
     fn new(p_hosp: f64, p_death: f64) -> Self {
         Self::Sane((
             Event {
@@ -272,42 +260,47 @@ impl State {
     // }
 
     // TODO: not sure if we want an option here...
-    pub fn next_default(self, default: AnyTime, rng: &mut XorShiftRng) -> Option<Self> {
+    pub fn next_default(
+        self,
+        default: AnyTime,
+        cfg: &PandemicConfig,
+        rng: &mut XorShiftRng,
+    ) -> Option<Self> {
         // TODO: when #![feature(bindings_after_at)] reaches stable
         // rewrite this part with it
         match self {
             Self::Sane((ev, _)) => Some(Self::Sane((ev, default.into()))),
-            Self::Exposed((ev, _)) => Some(ev.next(default, rng)),
-            Self::Infectious((ev, _)) => Some(ev.next(default, rng)),
-            Self::Hospitalized((ev, _)) => Some(ev.next(default, rng)),
+            Self::Exposed((ev, _)) => Some(ev.next(default, cfg, rng)),
+            Self::Infectious((ev, _)) => Some(ev.next(default, cfg, rng)),
+            Self::Hospitalized((ev, _)) => Some(ev.next(default, cfg, rng)),
             Self::Recovered(_) => Some(Self::Recovered(default.into())),
             Self::Dead(_) => Some(Self::Dead(default.into())),
         }
     }
 
     // TODO: not sure if we want an option here...
-    pub fn next(self, now: AnyTime, rng: &mut XorShiftRng) -> Option<Self> {
+    pub fn next(self, now: AnyTime, cfg: &PandemicConfig, rng: &mut XorShiftRng) -> Option<Self> {
         // TODO: when #![feature(bindings_after_at)] reaches stable
         // rewrite this part with it
         match self {
             Self::Sane((ev, t)) => Some(Self::Sane((ev, t))),
             Self::Exposed((ev, t)) => {
                 if ev.t <= now {
-                    Some(ev.next(now, rng))
+                    Some(ev.next(now, cfg, rng))
                 } else {
                     Some(Self::Exposed((ev, t)))
                 }
             }
             Self::Infectious((ev, t)) => {
                 if ev.t <= now {
-                    Some(ev.next(now, rng))
+                    Some(ev.next(now, cfg, rng))
                 } else {
                     Some(Self::Infectious((ev, t)))
                 }
             }
             Self::Hospitalized((ev, t)) => {
                 if ev.t <= now {
-                    Some(ev.next(now, rng))
+                    Some(ev.next(now, cfg, rng))
                 } else {
                     Some(Self::Hospitalized((ev, t)))
                 }
@@ -322,13 +315,14 @@ impl State {
         self,
         now: AnyTime,
         overlap: Duration,
+        cfg: &PandemicConfig,
         rng: &mut XorShiftRng,
     ) -> Result<Self, String> {
         // rewrite this part with it
         match self {
             Self::Sane((ev, t)) => {
-                if overlap >= Self::get_time_exp(State::R_0 / State::T_INF, rng) {
-                    Ok(ev.next(now, rng))
+                if overlap >= Self::get_time_exp(cfg.r_0 / cfg.t_inf.inner_seconds(), rng) {
+                    Ok(ev.next(now, cfg, rng))
                 } else {
                     Ok(Self::Sane((ev, t)))
                 }
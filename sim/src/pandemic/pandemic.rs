@@ -1,5 +1,5 @@
-use crate::pandemic::{AnyTime, State};
-use crate::{CarID, Event, Person, PersonID, Scheduler, TripPhaseType};
+use crate::pandemic::{AnyTime, PandemicConfig, State};
+use crate::{CarID, Command, Event, Person, PersonID, Scheduler, TripPhaseType};
 use geom::{Duration, Time};
 use map_model::{BuildingID, BusStopID};
 use rand::Rng;
@@ -20,6 +20,7 @@ pub struct PandemicModel {
     buses: SharedSpace<CarID>,
     person_to_bus: BTreeMap<PersonID, CarID>,
 
+    config: PandemicConfig,
     rng: XorShiftRng,
     initialized: bool,
 }
@@ -41,7 +42,7 @@ pub enum Cmd {
 // from there.
 
 impl PandemicModel {
-    pub fn new(rng: XorShiftRng) -> PandemicModel {
+    pub fn new(config: PandemicConfig, rng: XorShiftRng) -> PandemicModel {
         PandemicModel {
             pop: BTreeMap::new(),
 
@@ -50,6 +51,7 @@ impl PandemicModel {
             buses: SharedSpace::new(),
             person_to_bus: BTreeMap::new(),
 
+            config,
             rng,
             initialized: false,
         }
@@ -65,18 +67,23 @@ impl PandemicModel {
         // TODO the intial time is not well set. it should start "before"
         // the beginning of the day. Also
         for p in population {
-            let state = State::new(0.5, 0.5);
-            let state = if self.rng.gen_bool(State::ini_exposed_ratio()) {
+            let state = State::new(self.config.p_hosp, self.config.p_death);
+            let state = if self.rng.gen_bool(self.config.initial_exposed_ratio) {
                 let next_state = state
                     .start(
                         AnyTime::from(Time::START_OF_DAY),
                         Duration::seconds(std::f64::MAX),
+                        &self.config,
                         &mut self.rng,
                     )
                     .unwrap();
-                let next_state = if self.rng.gen_bool(State::ini_infectious_ratio()) {
+                let next_state = if self.rng.gen_bool(self.config.initial_infectious_ratio) {
                     next_state
-                        .next_default(AnyTime::from(Time::START_OF_DAY), &mut self.rng)
+                        .next_default(
+                            AnyTime::from(Time::START_OF_DAY),
+                            &self.config,
+                            &mut self.rng,
+                        )
                         .unwrap()
                 } else {
                     next_state
@@ -161,7 +168,7 @@ impl PandemicModel {
             }
             Event::PersonLeavesBuilding(person, bldg) => {
                 if let Some(others) = self.bldgs.person_leaves_space(now, *person, *bldg) {
-                    self.transmission(now, *person, others, scheduler);
+                    self.transmission(now, *person, others, Some(*bldg), scheduler);
                 } else {
                     // TODO A person left a building, but they weren't inside of it? Not sure
                     // what's happening here yet.
@@ -178,7 +185,7 @@ impl PandemicModel {
                             .bus_stops
                             .person_leaves_space(now, person, *stop)
                             .unwrap();
-                        self.transmission(now, person, others, scheduler);
+                        self.transmission(now, person, others, None, scheduler);
 
                         self.buses.person_enters_space(now, person, *bus);
                         self.person_to_bus.insert(person, *bus);
@@ -189,7 +196,7 @@ impl PandemicModel {
                         // of a bus ride.
                         if let Some(car) = self.person_to_bus.remove(&person) {
                             let others = self.buses.person_leaves_space(now, person, car).unwrap();
-                            self.transmission(now, person, others, scheduler);
+                            self.transmission(now, person, others, None, scheduler);
                         }
                     }
                     _ => {
@@ -219,6 +226,10 @@ impl PandemicModel {
         }
     }
 
+    pub fn get_config(&self) -> &PandemicConfig {
+        &self.config
+    }
+
     pub fn get_time(&self, person: PersonID) -> Option<Time> {
         match self.pop.get(&person) {
             Some(state) => state.get_time(),
@@ -275,21 +286,30 @@ impl PandemicModel {
         now: Time,
         person: PersonID,
         other_occupants: Vec<(PersonID, Duration)>,
+        bldg: Option<BuildingID>,
         scheduler: &mut Scheduler,
     ) {
         // person has spent some duration in the same space as other people. Does transmission
-        // occur?
+        // occur? Interventions might've started or ended while they were together.
         for (other, overlap) in other_occupants {
             if let Some(pid) = self.infectious_contact(person, other) {
-                self.become_exposed(now, overlap, pid, scheduler);
+                let exposure = self.config.exposure(bldg, now - overlap, now);
+                self.become_exposed(now, exposure, pid, scheduler);
             }
         }
     }
 
     // transition from a state to another without interaction with others
-    fn transition(&mut self, now: Time, person: PersonID, _scheduler: &mut Scheduler) {
+    fn transition(&mut self, now: Time, person: PersonID, scheduler: &mut Scheduler) {
         let state = self.pop.remove(&person).unwrap();
-        let state = state.next(AnyTime::from(now), &mut self.rng).unwrap();
+        let was_dead = state.is_dead();
+        let state = state
+            .next(AnyTime::from(now), &self.config, &mut self.rng)
+            .unwrap();
+        // In a multi-day simulation, the dead shouldn't keep commuting.
+        if state.is_dead() && !was_dead {
+            scheduler.push(now, Command::Pandemic(Cmd::CancelFutureTrips(person)));
+        }
         self.pop.insert(person, state);
 
         // if self.rng.gen_bool(0.1) {
@@ -314,7 +334,7 @@ impl PandemicModel {
             std::f64::INFINITY
         );
         let state = state
-            .start(AnyTime::from(now), overlap, &mut self.rng)
+            .start(AnyTime::from(now), overlap, &self.config, &mut self.rng)
             .unwrap();
         self.pop.insert(person, state);
 
@@ -385,6 +405,7 @@ impl<T: Ord> SharedSpace<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::pandemic::Intervention;
 
     fn time(x: usize) -> Time {
         Time::START_OF_DAY + Duration::hours(x)
@@ -444,4 +465,42 @@ mod tests {
             Some(vec![(person3, Duration::hours(5))])
         );
     }
+
+    #[test]
+    fn test_interventions_during_overlap() {
+        let bldg1 = BuildingID(1);
+        let bldg2 = BuildingID(2);
+        let mut config = PandemicConfig::default();
+        config.interventions = vec![
+            Intervention::CloseBuildings {
+                bldgs: vec![bldg1],
+                start: time(2),
+                end: time(4),
+            },
+            Intervention::Masks {
+                factor: 0.5,
+                start: time(3),
+                end: time(10),
+            },
+        ];
+
+        // People who went inside before the closure aren't exposed once it starts
+        assert_eq!(
+            config.exposure(Some(bldg1), time(0), time(5)),
+            Duration::hours(2) + Duration::hours(1) * 0.5
+        );
+        // Masks only count for the part of the overlap when they were required
+        assert_eq!(
+            config.exposure(Some(bldg2), time(0), time(5)),
+            Duration::hours(3) + Duration::hours(2) * 0.5
+        );
+        assert_eq!(
+            config.exposure(None, time(11), time(12)),
+            Duration::hours(1)
+        );
+        assert_eq!(
+            config.exposure(Some(bldg1), time(2), time(4)),
+            Duration::ZERO
+        );
+    }
 }
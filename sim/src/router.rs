@@ -1,7 +1,7 @@
 use crate::mechanics::Queue;
 use crate::{
     Event, ParkingSimState, ParkingSpot, PersonID, SidewalkSpot, TripID, TripMode, TripPhaseType,
-    Vehicle,
+    Vehicle, VehicleType,
 };
 use geom::Distance;
 use map_model::{
@@ -11,6 +11,11 @@ use map_model::{
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BinaryHeap, HashMap};
 
+// Drivers who've seen a free spot only look this much further for a better one.
+const MAX_PARKING_SEARCH: Distance = Distance::const_meters(400.0);
+// Every meter of driving to reach a spot is as bad as walking this fraction of a meter.
+const CRUISING_COST: f64 = 0.1;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Router {
     // Front is always the current step
@@ -31,12 +36,14 @@ pub enum ActionAtEnd {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 enum Goal {
     // Spot and cached distance along the last driving lane
-    // TODO Right now, the building is ignored when choosing the best spot.
     ParkNearBuilding {
         target: BuildingID,
         spot: Option<(ParkingSpot, Distance)>,
         // No parking available at all!
         stuck_end_dist: Option<Distance>,
+        // How far the driver has gone past the end of their original path, looking for parking.
+        // Only counts whole lanes and turns.
+        cruised: Option<Distance>,
     },
     EndAtBorder {
         end_dist: Distance,
@@ -48,6 +55,17 @@ enum Goal {
     FollowBusRoute {
         end_dist: Distance,
     },
+    // The map was edited, and there's no way to reach the original goal anymore.
+    Stranded {
+        end_dist: Distance,
+    },
+}
+
+pub enum LiveEditOutcome {
+    Unchanged,
+    Rerouted,
+    // The vehicle will abort its trip at the end of its current lane.
+    Stranded,
 }
 
 impl Router {
@@ -65,6 +83,7 @@ impl Router {
                 target: bldg,
                 spot: None,
                 stuck_end_dist: None,
+                cruised: None,
             },
         }
     }
@@ -115,6 +134,7 @@ impl Router {
             } => stuck_end_dist.unwrap_or_else(|| spot.unwrap().1),
             Goal::BikeThenStop { end_dist } => end_dist,
             Goal::FollowBusRoute { end_dist } => end_dist,
+            Goal::Stranded { end_dist } => end_dist,
         }
     }
 
@@ -122,6 +142,13 @@ impl Router {
         &self.path
     }
 
+    pub fn get_distance_cruised_for_parking(&self) -> Distance {
+        match self.goal {
+            Goal::ParkNearBuilding { cruised, .. } => cruised.unwrap_or(Distance::ZERO),
+            _ => Distance::ZERO,
+        }
+    }
+
     // Returns the step just finished
     pub fn advance(
         &mut self,
@@ -132,6 +159,13 @@ impl Router {
         events: &mut Vec<Event>,
     ) -> Traversable {
         let prev = self.path.shift(map).as_traversable();
+        if let Goal::ParkNearBuilding {
+            cruised: Some(ref mut dist),
+            ..
+        } = self.goal
+        {
+            *dist += prev.length(map);
+        }
         if self.last_step() {
             // Do this to trigger the side-effect of looking for parking.
             self.maybe_handle_end(
@@ -179,9 +213,10 @@ impl Router {
                 }
             }
             Goal::ParkNearBuilding {
+                target,
                 ref mut spot,
                 ref mut stuck_end_dist,
-                ..
+                ref mut cruised,
             } => {
                 if let Some(d) = stuck_end_dist {
                     if *d == front {
@@ -197,56 +232,44 @@ impl Router {
                 };
                 if need_new_spot {
                     let current_lane = self.path.current_step().as_lane();
-                    if let Some((new_spot, new_pos)) = parking.get_first_free_spot(
-                        Position::new(current_lane, front),
-                        vehicle,
-                        map,
-                    ) {
+                    let start = Position::new(current_lane, front);
+                    if let Some((new_path_steps, new_spot, new_pos)) =
+                        path_to_best_parking_spot(start, vehicle, target, map, parking)
+                    {
+                        *spot = Some((new_spot, new_pos.dist_along()));
+                        // TODO This path might not be the same as the one found here...
                         if let Some((t, p)) = trip_and_person {
                             events.push(Event::TripPhaseStarting(
                                 t,
                                 p,
                                 TripMode::Drive,
                                 Some(PathRequest {
-                                    start: Position::new(current_lane, front),
+                                    start,
                                     end: new_pos,
                                     constraints: PathConstraints::Car,
                                 }),
                                 TripPhaseType::Parking,
                             ));
                         }
-                        *spot = Some((new_spot, new_pos.dist_along()));
-                    } else {
-                        if let Some((new_path_steps, new_spot, new_pos)) =
-                            path_to_free_parking_spot(current_lane, vehicle, map, parking)
-                        {
-                            *spot = Some((new_spot, new_pos.dist_along()));
+                        if !new_path_steps.is_empty() {
                             for step in new_path_steps {
                                 self.path.add(step, map);
                             }
                             events.push(Event::PathAmended(self.path.clone()));
-                            // TODO This path might not be the same as the one found here...
-                            if let Some((t, p)) = trip_and_person {
-                                events.push(Event::TripPhaseStarting(
-                                    t,
-                                    p,
-                                    TripMode::Drive,
-                                    Some(PathRequest {
-                                        start: Position::new(current_lane, front),
-                                        end: new_pos,
-                                        constraints: PathConstraints::Car,
-                                    }),
-                                    TripPhaseType::Parking,
-                                ));
+                            if cruised.is_none() {
+                                // The whole current lane gets counted once we leave it, but the
+                                // search only started here.
+                                *cruised = Some(Distance::ZERO - front);
                             }
-                        } else {
-                            println!(
-                                "WARNING: {} can't find parking on {} or anywhere reachable from \
-                                 it. Possibly we're just totally out of parking space!",
-                                vehicle.id, current_lane
-                            );
-                            *stuck_end_dist = Some(map.get_l(current_lane).length());
+                            return Some(ActionAtEnd::GotoLaneEnd);
                         }
+                    } else {
+                        println!(
+                            "WARNING: {} can't find parking on {} or anywhere reachable from \
+                             it. Possibly we're just totally out of parking space!",
+                            vehicle.id, current_lane
+                        );
+                        *stuck_end_dist = Some(map.get_l(current_lane).length());
                         return Some(ActionAtEnd::GotoLaneEnd);
                     }
                 }
@@ -282,6 +305,13 @@ impl Router {
                     None
                 }
             }
+            Goal::Stranded { end_dist } => {
+                if end_dist == front {
+                    Some(ActionAtEnd::AbortTrip)
+                } else {
+                    None
+                }
+            }
         }
     }
 
@@ -354,61 +384,153 @@ impl Router {
         self.path.modify_step(3, PathStep::Turn(turn2), map);
     }
 
+    // Only called when the vehicle is at the end of a lane, before starting the next turn. Cars
+    // might find a faster way to their goal, given the extra cost of crossing each lane and turn
+    // right now. Bikes and buses don't bother. Returns true if the path changed.
+    pub fn reroute_around_traffic(
+        &mut self,
+        vehicle: &Vehicle,
+        map: &Map,
+        extra_cost: &dyn Fn(Traversable) -> usize,
+    ) -> bool {
+        if vehicle.vehicle_type != VehicleType::Car || self.last_step() {
+            return false;
+        }
+        match self.goal {
+            Goal::ParkNearBuilding {
+                stuck_end_dist: None,
+                ..
+            }
+            | Goal::EndAtBorder { .. } => {}
+            _ => {
+                return false;
+            }
+        }
+
+        let current_lane = self.head().as_lane();
+        let req = PathRequest {
+            start: Position::new(current_lane, map.get_l(current_lane).length()),
+            end: Position::new(self.path.last_step().as_lane(), self.path.end_dist()),
+            constraints: PathConstraints::Car,
+        };
+        if let Some(path) = map.pathfind_with_extra_costs(req, extra_cost) {
+            if path.get_steps() != self.path.get_steps() {
+                self.path.reroute(path, map);
+                return true;
+            }
+        }
+        false
+    }
+
+    // After the map is edited mid-simulation, anything after the current step might not exist
+    // anymore. The pathfinder must already be updated.
+    pub fn handle_live_edits(&mut self, vehicle: &Vehicle, map: &Map) -> LiveEditOutcome {
+        let constraints = vehicle.vehicle_type.to_constraints();
+        let still_valid = self.path.get_steps().iter().skip(1).all(|step| match step {
+            PathStep::Lane(l) | PathStep::ContraflowLane(l) => {
+                constraints.can_use(map.get_l(*l), map)
+            }
+            PathStep::Turn(t) => map.maybe_get_t(*t).is_some(),
+        });
+        if still_valid {
+            return LiveEditOutcome::Unchanged;
+        }
+
+        // Finish the current step no matter what, and if it's a turn, the lane after it.
+        let (start, steps_to_keep) = match self.head() {
+            Traversable::Lane(l) => (l, 1),
+            Traversable::Turn(t) => (t.dst, 2),
+        };
+        if let Goal::ParkNearBuilding { ref mut spot, .. } = self.goal {
+            // The path might've been amended to reach this spot, so just look again later.
+            *spot = None;
+        }
+        if constraints.can_use(map.get_l(start), map) {
+            if let Some(path) = map.pathfind(PathRequest {
+                start: Position::new(start, map.get_l(start).length()),
+                end: Position::new(self.path.last_step().as_lane(), self.path.end_dist()),
+                constraints,
+            }) {
+                self.path.reroute(path, map);
+                return LiveEditOutcome::Rerouted;
+            }
+        }
+
+        self.path.truncate(steps_to_keep, map);
+        self.goal = Goal::Stranded {
+            end_dist: self.path.end_dist(),
+        };
+        LiveEditOutcome::Stranded
+    }
+
     pub fn replace_path_for_serialization(&mut self, path: Path) -> Path {
         std::mem::replace(&mut self.path, path)
     }
 }
 
-// Unrealistically assumes the driver has knowledge of currently free parking spots, even if
-// they're far away. Since they don't reserve the spot in advance, somebody else can still beat
-// them there, producing some nice, realistic churn if there's too much contention.
-// The first PathStep is the turn after start, NOT PathStep::Lane(start).
-fn path_to_free_parking_spot(
-    start: LaneID,
+// Unrealistically assumes the driver has knowledge of currently free parking spots and their
+// prices, even if they're far away. Since they don't reserve the spot in advance, somebody else can
+// still beat them there, producing some nice, realistic churn if there's too much contention.
+// Among all spots within MAX_PARKING_SEARCH of the start, picks the cheapest one for getting to the
+// target building. If there are none, settles for the closest one.
+// The first PathStep is the turn after start, NOT PathStep::Lane(start). No steps means the spot is
+// ahead on the start lane.
+fn path_to_best_parking_spot(
+    start: Position,
     vehicle: &Vehicle,
+    target: BuildingID,
     map: &Map,
     parking: &ParkingSimState,
 ) -> Option<(Vec<PathStep>, ParkingSpot, Position)> {
     let mut backrefs: HashMap<LaneID, TurnID> = HashMap::new();
-    // Don't travel far.
     // This is a max-heap, so negate all distances. Tie breaker is lane ID, arbitrary but
     // deterministic.
     let mut queue: BinaryHeap<(Distance, LaneID)> = BinaryHeap::new();
-    queue.push((Distance::ZERO, start));
-
-    while !queue.is_empty() {
-        let (dist_so_far, current) = queue.pop().unwrap();
-        // If the current lane has a spot open, we wouldn't be asking. This can happen if a spot
-        // opens up on the 'start' lane, but behind the car.
-        if current != start {
-            if let Some((spot, pos)) =
-                parking.get_first_free_spot(Position::new(current, Distance::ZERO), vehicle, map)
-            {
-                let mut steps = vec![PathStep::Lane(current)];
-                let mut current = current;
-                loop {
-                    if current == start {
-                        // Don't include PathStep::Lane(start)
-                        steps.pop();
-                        steps.reverse();
-                        return Some((steps, spot, pos));
-                    }
-                    let turn = backrefs[&current];
-                    steps.push(PathStep::Turn(turn));
-                    steps.push(PathStep::Lane(turn.src));
-                    current = turn.src;
-                }
+    queue.push((Distance::ZERO, start.lane()));
+    // Cost, lane, spot, driving position
+    let mut best: Option<(Distance, LaneID, ParkingSpot, Position)> = None;
+
+    while let Some((neg_dist_so_far, current)) = queue.pop() {
+        let dist_so_far = Distance::ZERO - neg_dist_so_far;
+        if dist_so_far > MAX_PARKING_SEARCH && best.is_some() {
+            break;
+        }
+        let pos = if current == start.lane() {
+            start
+        } else {
+            Position::new(current, Distance::ZERO)
+        };
+        if let Some((spot, spot_pos, cost)) = parking.get_best_free_spot(pos, vehicle, target, map)
+        {
+            // Driving around isn't free either.
+            let cost = cost + dist_so_far * CRUISING_COST;
+            if best.as_ref().map(|(c, _, _, _)| cost < *c).unwrap_or(true) {
+                best = Some((cost, current, spot, spot_pos));
             }
         }
         for turn in map.get_turns_for(current, PathConstraints::Car) {
-            if !backrefs.contains_key(&turn.id.dst) {
+            if turn.id.dst != start.lane() && !backrefs.contains_key(&turn.id.dst) {
                 let dist_this_step = turn.geom.length() + map.get_l(current).length();
                 backrefs.insert(turn.id.dst, turn.id);
                 // Remember, keep things negative
-                queue.push((dist_so_far - dist_this_step, turn.id.dst));
+                queue.push((neg_dist_so_far - dist_this_step, turn.id.dst));
             }
         }
     }
 
-    None
+    let (_, lane, spot, pos) = best?;
+    let mut steps = vec![PathStep::Lane(lane)];
+    let mut current = lane;
+    loop {
+        if current == start.lane() {
+            // Don't include PathStep::Lane(start)
+            steps.pop();
+            steps.reverse();
+            return Some((steps, spot, pos));
+        }
+        let turn = backrefs[&current];
+        steps.push(PathStep::Turn(turn));
+        steps.push(PathStep::Lane(turn.src));
+        current = turn.src;
+    }
 }
@@ -1,7 +1,7 @@
 use crate::{pandemic, AgentID, CarID, CreateCar, CreatePedestrian, PedestrianID};
 use derivative::Derivative;
 use geom::{Duration, Histogram, Time};
-use map_model::{IntersectionID, Path, PathRequest};
+use map_model::{BusRouteID, IntersectionID, Path, PathRequest};
 use serde_derive::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BinaryHeap};
@@ -18,6 +18,8 @@ pub enum Command {
     UpdateIntersection(IntersectionID),
     Savestate(Duration),
     Pandemic(pandemic::Cmd),
+    // The index of the trip in the route's schedule
+    StartBus(BusRouteID, usize),
 }
 
 impl Command {
@@ -38,6 +40,7 @@ impl Command {
             Command::UpdateIntersection(id) => CommandType::Intersection(*id),
             Command::Savestate(_) => CommandType::Savestate,
             Command::Pandemic(ref p) => CommandType::Pandemic(p.clone()),
+            Command::StartBus(route, trip) => CommandType::StartBus(*route, *trip),
         }
     }
 }
@@ -52,6 +55,7 @@ pub enum CommandType {
     Intersection(IntersectionID),
     Savestate,
     Pandemic(pandemic::Cmd),
+    StartBus(BusRouteID, usize),
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -1,10 +1,11 @@
 use crate::{
     AgentID, Analytics, CarID, Command, CreateCar, DrawCarInput, DrawPedCrowdInput,
     DrawPedestrianInput, DrivingGoal, DrivingSimState, Event, GetDrawAgents, IntersectionSimState,
-    PandemicModel, ParkedCar, ParkingSimState, ParkingSpot, PedestrianID, Person, PersonID,
-    PersonState, Router, Scheduler, SidewalkPOI, SidewalkSpot, TransitSimState, TripCount,
-    TripEndpoint, TripID, TripLeg, TripManager, TripMode, TripPhaseType, TripPositions, TripResult,
-    TripSpawner, TripSpec, UnzoomedAgent, VehicleSpec, VehicleType, WalkingSimState, BUS_LENGTH,
+    PandemicConfig, PandemicModel, ParkedCar, ParkingSimState, ParkingSpot, PedestrianID, Person,
+    PersonID, PersonState, Router, Scheduler, SidewalkPOI, SidewalkSpot, TransitSimState,
+    TripCount, TripEndpoint, TripID, TripLeg, TripManager, TripMode, TripPhaseType, TripPositions,
+    TripResult, TripSpawner, TripSpec, UnzoomedAgent, VehicleSpec, VehicleType, WalkingSimState,
+    BUS_LENGTH,
 };
 use abstutil::Timer;
 use derivative::Derivative;
@@ -73,6 +74,10 @@ pub struct SimOptions {
     pub recalc_lanechanging: bool,
     pub clear_laggy_head_early: bool,
     pub enable_pandemic_model: Option<XorShiftRng>,
+    // Only used when the pandemic model is enabled
+    pub pandemic_config: PandemicConfig,
+    // Drivers look for a faster path around congestion this often
+    pub reroute_every: Option<Duration>,
 }
 
 impl SimOptions {
@@ -85,6 +90,8 @@ impl SimOptions {
             recalc_lanechanging: true,
             clear_laggy_head_early: false,
             enable_pandemic_model: None,
+            pandemic_config: PandemicConfig::default(),
+            reroute_every: None,
         }
     }
 }
@@ -101,6 +108,7 @@ impl Sim {
                 map,
                 opts.recalc_lanechanging,
                 opts.clear_laggy_head_early,
+                opts.reroute_every,
             ),
             parking: ParkingSimState::new(map, timer),
             walking: WalkingSimState::new(),
@@ -110,10 +118,14 @@ impl Sim {
                 opts.use_freeform_policy_everywhere,
                 opts.disable_block_the_box,
             ),
-            transit: TransitSimState::new(),
+            transit: TransitSimState::new(if opts.enable_pandemic_model.is_some() {
+                opts.pandemic_config.max_bus_passengers()
+            } else {
+                None
+            }),
             trips: TripManager::new(),
             pandemic: if let Some(rng) = opts.enable_pandemic_model {
-                Some(PandemicModel::new(rng))
+                Some(PandemicModel::new(opts.pandemic_config, rng))
             } else {
                 None
             },
@@ -219,7 +231,18 @@ impl Sim {
         self.parking.get_offstreet_parked_cars(bldg)
     }
 
+    // For routes with a schedule, buses appear later, so this returns nothing.
     pub fn seed_bus_route(&mut self, route: &BusRoute, map: &Map, timer: &mut Timer) -> Vec<CarID> {
+        if route.is_scheduled() {
+            self.transit.create_empty_route(route, map);
+            for (idx, times) in route.schedule.iter().enumerate() {
+                // Trips that already departed are squeezed in right away.
+                self.scheduler
+                    .push(times[0].max(self.time), Command::StartBus(route.id, idx));
+            }
+            return Vec::new();
+        }
+
         let mut results: Vec<CarID> = Vec::new();
 
         // Try to spawn just ONE bus anywhere.
@@ -276,8 +299,8 @@ impl Sim {
                     &self.parking,
                     &mut self.scheduler,
                 ) {
-                    self.transit.bus_created(id, route.id, next_stop_idx);
-                    self.analytics.record_demand(&path, map);
+                    self.transit.bus_created(id, route.id, next_stop_idx, None);
+                    self.analytics.record_demand(&path, self.time, map);
                     results.push(id);
                     return results;
                 } else {
@@ -292,6 +315,48 @@ impl Sim {
         results
     }
 
+    // Returns false if there's no room to start the bus yet and it should be retried.
+    fn start_scheduled_bus(&mut self, route: BusRouteID, trip: usize, map: &Map) -> bool {
+        let (next_stop_idx, req, path, end_dist) =
+            self.transit.scheduled_bus_start(route, BUS_LENGTH, map);
+        if map.get_l(req.start.lane()).length() < BUS_LENGTH {
+            println!(
+                "WARNING: The first stop of {} is on a lane too short for a bus; not starting it",
+                route
+            );
+            return true;
+        }
+        let id = CarID(self.car_id_counter, VehicleType::Bus);
+        let vehicle = VehicleSpec {
+            vehicle_type: VehicleType::Bus,
+            length: BUS_LENGTH,
+            max_speed: None,
+        }
+        .make(id, None);
+        if !self.driving.start_car_on_lane(
+            self.time,
+            CreateCar {
+                start_dist: BUS_LENGTH,
+                vehicle,
+                req,
+                router: Router::follow_bus_route(path.clone(), end_dist),
+                maybe_parked_car: None,
+                trip_and_person: None,
+            },
+            map,
+            &self.intersections,
+            &self.parking,
+            &mut self.scheduler,
+        ) {
+            return false;
+        }
+        self.car_id_counter += 1;
+        self.transit
+            .bus_created(id, route, next_stop_idx, Some(trip));
+        self.analytics.record_demand(&path, self.time, map);
+        true
+    }
+
     pub fn set_name(&mut self, name: String) {
         self.run_name = name;
     }
@@ -400,7 +465,18 @@ impl Sim {
         let mut savestate = false;
         match cmd {
             Command::SpawnCar(create_car, retry_if_no_room) => {
-                if self.driving.start_car_on_lane(
+                if create_car
+                    .trip_and_person
+                    .map(|(trip, person)| self.trips.person_busy_with_other_trip(person, trip))
+                    .unwrap_or(false)
+                {
+                    // Wait for them to finish their current trip. The time spent waiting counts
+                    // towards this trip.
+                    self.scheduler.push(
+                        self.time + BLIND_RETRY_TO_SPAWN,
+                        Command::SpawnCar(create_car, retry_if_no_room),
+                    );
+                } else if self.driving.start_car_on_lane(
                     self.time,
                     create_car.clone(),
                     map,
@@ -441,7 +517,7 @@ impl Sim {
                         ));
                     }
                     self.analytics
-                        .record_demand(create_car.router.get_path(), map);
+                        .record_demand(create_car.router.get_path(), self.time, map);
                 } else if retry_if_no_room {
                     // TODO Record this in the trip log
                     self.scheduler.push(
@@ -457,6 +533,16 @@ impl Sim {
                     }
                 }
             }
+            Command::SpawnPed(create_ped)
+                if self
+                    .trips
+                    .person_busy_with_other_trip(create_ped.person, create_ped.trip) =>
+            {
+                self.scheduler.push(
+                    self.time + BLIND_RETRY_TO_SPAWN,
+                    Command::SpawnPed(create_ped),
+                );
+            }
             Command::SpawnPed(mut create_ped) => {
                 let ok = if let SidewalkPOI::DeferredParkingSpot(b, driving_goal) =
                     create_ped.goal.connection.clone()
@@ -527,7 +613,8 @@ impl Sim {
                         Some(create_ped.req.clone()),
                         TripPhaseType::Walking,
                     ));
-                    self.analytics.record_demand(&create_ped.path, map);
+                    self.analytics
+                        .record_demand(&create_ped.path, self.time, map);
 
                     // Maybe there's actually no work to do!
                     match (&create_ped.start.connection, &create_ped.goal.connection) {
@@ -601,6 +688,14 @@ impl Sim {
                     .push(self.time + frequency, Command::Savestate(frequency));
                 savestate = true;
             }
+            Command::StartBus(route, trip) => {
+                if !self.start_scheduled_bus(route, trip, map) {
+                    self.scheduler.push(
+                        self.time + BLIND_RETRY_TO_SPAWN,
+                        Command::StartBus(route, trip),
+                    );
+                }
+            }
             Command::Pandemic(cmd) => {
                 if let crate::pandemic::Cmd::CancelFutureTrips(person) = cmd {
                     self.trips
@@ -704,6 +799,22 @@ impl Sim {
             println!("Debug from {}", path);
         }
     }
+
+    // Call after editing the map mid-simulation and recalculating pathfinding. Vehicles whose path
+    // doesn't exist anymore are rerouted, or abort their trip at the end of their current lane if
+    // there's no way to reach their goal now. Edited traffic signals restart their new plan.
+    pub fn handle_live_edits(&mut self, map: &Map, timer: &mut Timer) {
+        self.edits_name = map.get_edits().edits_name.clone();
+        self.intersections
+            .handle_live_edits(self.time, map, &mut self.scheduler);
+        self.driving.handle_live_edits(
+            self.time,
+            map,
+            &mut self.intersections,
+            &mut self.scheduler,
+            timer,
+        );
+    }
 }
 
 // Helpers to run the sim
@@ -1109,6 +1220,16 @@ impl Sim {
         results
     }
 
+    // The current phase of a traffic signal, and the most time it could last
+    pub fn current_phase_and_remaining_time(
+        &self,
+        i: IntersectionID,
+        map: &Map,
+    ) -> (usize, Duration) {
+        self.intersections
+            .current_phase_and_remaining_time(self.time, i, map)
+    }
+
     pub fn get_analytics(&self) -> &Analytics {
         &self.analytics
     }
@@ -1179,3 +1300,537 @@ pub struct AgentProperties {
     pub lanes_crossed: usize,
     pub total_lanes: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        IndividTrip, LogitModeChoice, PersonSpec, Scenario, SimOptions, SpawnTrip, MAX_CAR_LENGTH,
+    };
+    use geom::{LonLat, Polygon};
+    use map_model::raw::{
+        OriginalBuilding, OriginalIntersection, OriginalRoad, RawBuilding, RawIntersection, RawMap,
+        RawRoad,
+    };
+    use map_model::{
+        osm, EditCmd, EditIntersection, IntersectionType, LaneType, ParkingRules, Phase, PhaseType,
+        Position, Road, TurnType,
+    };
+    use rand::SeedableRng;
+    use std::collections::BTreeSet;
+
+    // A square block with a road coming in from the west and leaving to the east, both at the
+    // northern corners. The direct way across is the northern side of the block; the detour goes
+    // around the other three sides.
+    fn block() -> Map {
+        Map::create_from_raw(raw_block(), &mut Timer::throwaway())
+    }
+
+    fn raw_block() -> RawMap {
+        let mut raw = RawMap::blank("block".to_string());
+        raw.gps_bounds.update(LonLat::new(-122.0, 47.0));
+        raw.gps_bounds.update(LonLat::new(-121.99, 47.01));
+        raw.boundary_polygon = raw.gps_bounds.to_bounds().get_rectangle();
+
+        for (id, x, y, intersection_type) in vec![
+            (1, 50.0, 100.0, IntersectionType::Border),
+            (2, 150.0, 100.0, IntersectionType::StopSign),
+            (3, 350.0, 100.0, IntersectionType::StopSign),
+            (4, 450.0, 100.0, IntersectionType::Border),
+            (5, 150.0, 300.0, IntersectionType::StopSign),
+            (6, 350.0, 300.0, IntersectionType::StopSign),
+        ] {
+            raw.intersections.insert(
+                OriginalIntersection { osm_node_id: id },
+                RawIntersection {
+                    point: Pt2D::new(x, y),
+                    intersection_type,
+                    elevation: Distance::ZERO,
+                },
+            );
+        }
+        for (id, i1, i2) in vec![
+            (1, 1, 2),
+            (2, 2, 3),
+            (3, 3, 4),
+            (4, 2, 5),
+            (5, 5, 6),
+            (6, 6, 3),
+        ] {
+            let i1 = OriginalIntersection { osm_node_id: i1 };
+            let i2 = OriginalIntersection { osm_node_id: i2 };
+            let mut osm_tags = BTreeMap::new();
+            osm_tags.insert(osm::OSM_WAY_ID.to_string(), id.to_string());
+            osm_tags.insert(osm::NAME.to_string(), format!("Road {}", id));
+            osm_tags.insert(osm::HIGHWAY.to_string(), "residential".to_string());
+            osm_tags.insert("lanes".to_string(), "2".to_string());
+            raw.roads.insert(
+                OriginalRoad {
+                    osm_way_id: id,
+                    i1,
+                    i2,
+                },
+                RawRoad {
+                    center_points: vec![raw.intersections[&i1].point, raw.intersections[&i2].point],
+                    osm_tags,
+                    turn_restrictions: Vec::new(),
+                },
+            );
+        }
+        raw
+    }
+
+    // The same block with sidewalks and parking on every road, plus buildings centered at these
+    // points
+    fn block_with_buildings(centers: Vec<Pt2D>) -> Map {
+        let mut raw = raw_block();
+        for r in raw.roads.values_mut() {
+            r.osm_tags
+                .insert(osm::PARKING_BOTH.to_string(), "parallel".to_string());
+            r.osm_tags
+                .insert(osm::SIDEWALK.to_string(), "both".to_string());
+        }
+        for (idx, center) in centers.into_iter().enumerate() {
+            raw.buildings.insert(
+                OriginalBuilding {
+                    osm_way_id: 100 + idx as i64,
+                },
+                RawBuilding {
+                    polygon: Polygon::rectangle_centered(
+                        center,
+                        Distance::meters(30.0),
+                        Distance::meters(20.0),
+                    ),
+                    osm_tags: BTreeMap::new(),
+                    parking: None,
+                    amenities: BTreeSet::new(),
+                },
+            );
+        }
+        Map::create_from_raw(raw, &mut Timer::throwaway())
+    }
+
+    // A traffic signal with four roads leading to borders, two lanes each way. This is map_model's
+    // four_way test map, without the parking and bike lanes.
+    fn four_way() -> Map {
+        let mut raw = RawMap::blank("four_way".to_string());
+        raw.gps_bounds.update(LonLat::new(-122.0, 47.0));
+        raw.gps_bounds.update(LonLat::new(-121.99, 47.01));
+        raw.boundary_polygon = raw.gps_bounds.to_bounds().get_rectangle();
+
+        let center = OriginalIntersection { osm_node_id: 0 };
+        raw.intersections.insert(
+            center,
+            RawIntersection {
+                point: Pt2D::new(200.0, 200.0),
+                intersection_type: IntersectionType::TrafficSignal,
+                elevation: Distance::ZERO,
+            },
+        );
+        for (id, (x, y)) in vec![(200.0, 50.0), (50.0, 200.0), (200.0, 350.0), (350.0, 200.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let id = (id + 1) as i64;
+            let i2 = OriginalIntersection { osm_node_id: id };
+            raw.intersections.insert(
+                i2,
+                RawIntersection {
+                    point: Pt2D::new(x, y),
+                    intersection_type: IntersectionType::Border,
+                    elevation: Distance::ZERO,
+                },
+            );
+
+            let mut osm_tags = BTreeMap::new();
+            osm_tags.insert(osm::OSM_WAY_ID.to_string(), id.to_string());
+            osm_tags.insert(osm::NAME.to_string(), format!("Road {}", id));
+            osm_tags.insert(osm::HIGHWAY.to_string(), "primary".to_string());
+            osm_tags.insert("lanes".to_string(), "4".to_string());
+            raw.roads.insert(
+                OriginalRoad {
+                    osm_way_id: id,
+                    i1: center,
+                    i2,
+                },
+                RawRoad {
+                    center_points: vec![Pt2D::new(200.0, 200.0), Pt2D::new(x, y)],
+                    osm_tags,
+                    turn_restrictions: Vec::new(),
+                },
+            );
+        }
+
+        Map::create_from_raw(raw, &mut Timer::throwaway())
+    }
+
+    // With a phase per road and cars only coming from one of them, an actuated signal should go
+    // straight to that road's phase, skipping the others. Once the cars are gone, every phase gaps
+    // out right after its minimum green.
+    #[test]
+    fn test_actuated_signal() {
+        let mut timer = Timer::throwaway();
+        let mut map = four_way();
+        let min_green = Duration::seconds(10.0);
+        let max_green = Duration::seconds(30.0);
+
+        let road = |osm_way_id: i64| {
+            map.all_roads()
+                .iter()
+                .find(|r| r.orig_id.osm_way_id == osm_way_id)
+                .unwrap()
+        };
+        let driving_lane = |lanes: &Vec<(LaneID, LaneType)>| {
+            lanes
+                .iter()
+                .find(|(_, lt)| *lt == LaneType::Driving)
+                .unwrap()
+                .0
+        };
+        // Every road starts at the signal, so cars head towards it on the backwards lanes.
+        let start = driving_lane(&road(1).children_backwards);
+        let end = driving_lane(&road(3).children_forwards);
+        let border = road(3).dst_i;
+        let center = road(1).src_i;
+
+        // The phase for road 1 comes last, so reaching it means skipping the two in between.
+        let mut signal = map.get_traffic_signal(center).clone();
+        signal.offset = Duration::ZERO;
+        signal.phases.clear();
+        for osm_way_id in vec![2, 3, 4, 1] {
+            let r = road(osm_way_id).id;
+            let mut phase = Phase::new();
+            for g in signal.turn_groups.values() {
+                if g.turn_type != TurnType::Crosswalk && g.id.from.id == r {
+                    phase.yield_groups.insert(g.id);
+                }
+            }
+            phase.phase_type = PhaseType::Actuated {
+                min_green,
+                max_green,
+                gap: Duration::seconds(5.0),
+                skip_if_no_demand: true,
+            };
+            signal.phases.push(phase);
+        }
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(EditCmd::ChangeIntersection {
+            i: center,
+            new: EditIntersection::TrafficSignal(signal),
+            old: map.get_i_edit(center),
+        });
+        map.apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+
+        // A car every 3 seconds for the first minute
+        let mut scenario = Scenario::empty(&map, "actuated");
+        for idx in 0..20 {
+            scenario.people.push(PersonSpec {
+                id: PersonID(idx),
+                trips: vec![IndividTrip {
+                    depart: Time::START_OF_DAY + Duration::seconds(3.0 * (idx as f64)),
+                    trip: SpawnTrip::CarAppearing {
+                        start: Position::new(start, MAX_CAR_LENGTH),
+                        goal: DrivingGoal::Border(border, end),
+                        is_bike: false,
+                    },
+                }],
+            });
+        }
+        let mut sim = Sim::new(&map, SimOptions::new("test"), &mut timer);
+        scenario.instantiate(
+            &mut sim,
+            &map,
+            &mut XorShiftRng::from_seed([42; 16]),
+            &mut timer,
+        );
+
+        let mut samples = Vec::new();
+        for _ in 0..300 {
+            sim.normal_step(&map, Duration::seconds(1.0));
+            let (idx, remaining) = sim.current_phase_and_remaining_time(center, &map);
+            assert!(remaining <= max_green);
+            samples.push((sim.time() - Time::START_OF_DAY, idx));
+        }
+
+        // While cars keep coming, the phases without demand are skipped.
+        let busy: Vec<usize> = samples
+            .iter()
+            .filter(|(t, _)| *t <= Duration::minutes(1))
+            .map(|(_, idx)| *idx)
+            .collect();
+        assert!(busy.iter().all(|idx| *idx == 0 || *idx == 3));
+        assert!(busy.contains(&3));
+        assert_eq!(sim.get_analytics().finished_trips.len(), 20);
+
+        // Once everybody's gone, the phases take turns, each lasting only the minimum green.
+        let mut switches = Vec::new();
+        for pair in samples.windows(2) {
+            if pair[0].0 >= Duration::minutes(3) && pair[0].1 != pair[1].1 {
+                assert_eq!(pair[1].1, (pair[0].1 + 1) % 4);
+                switches.push(pair[1].0);
+            }
+        }
+        assert!(switches.len() >= 5);
+        for pair in switches.windows(2) {
+            let length = pair[1] - pair[0];
+            assert!(length >= min_green - Duration::seconds(1.0));
+            assert!(length <= min_green + Duration::seconds(1.0));
+        }
+    }
+
+    // Parking right in front of the building costs money, so the driver should keep going to a free
+    // spot a little further away, and count the extra driving as cruising for parking.
+    #[test]
+    fn test_cheaper_parking_beats_closest() {
+        let mut timer = Timer::throwaway();
+        // Inside the block, along the northern side
+        let mut map = block_with_buildings(vec![Pt2D::new(250.0, 150.0)]);
+        let target = BuildingID(0);
+        let road = |osm_way_id: i64| {
+            map.all_roads()
+                .iter()
+                .find(|r| r.orig_id.osm_way_id == osm_way_id)
+                .unwrap()
+        };
+        let paid = road(2).id;
+        let start = road(1)
+            .children_forwards
+            .iter()
+            .find(|(_, lt)| *lt == LaneType::Driving)
+            .unwrap()
+            .0;
+
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(EditCmd::ChangeParkingRules {
+            id: paid,
+            new: ParkingRules {
+                price_per_hour: 3.0,
+                time_limit: None,
+            },
+            old: ParkingRules::free(),
+        });
+        map.apply_edits(edits, &mut timer);
+
+        let mut scenario = Scenario::empty(&map, "parking");
+        scenario.people.push(PersonSpec {
+            id: PersonID(0),
+            trips: vec![IndividTrip {
+                depart: Time::START_OF_DAY,
+                trip: SpawnTrip::CarAppearing {
+                    start: Position::new(start, MAX_CAR_LENGTH),
+                    goal: DrivingGoal::ParkNear(target),
+                    is_bike: false,
+                },
+            }],
+        });
+        let mut sim = Sim::new(&map, SimOptions::new("test"), &mut timer);
+        scenario.instantiate(
+            &mut sim,
+            &map,
+            &mut XorShiftRng::from_seed([42; 16]),
+            &mut timer,
+        );
+        sim.just_run_until_done(&map, Some(Duration::minutes(10)));
+        assert!(sim.is_done());
+
+        let (filled, available) = sim.get_all_parking_spots();
+        assert_eq!(filled.len(), 1);
+        let parked = filled[0];
+        let on_road = |spot: ParkingSpot| match spot {
+            ParkingSpot::Onstreet(l, _) => map.get_parent(l).id,
+            ParkingSpot::Offstreet(_, _) => unreachable!(),
+        };
+        assert_ne!(on_road(parked), paid);
+
+        // The spot with the shortest walk is on the paid road, but costs more overall.
+        let sidewalk_pt = map.get_b(target).front_path.sidewalk.pt(&map);
+        let closest = available
+            .into_iter()
+            .min_by_key(|spot| {
+                sim.parking
+                    .spot_to_sidewalk_pos(*spot, &map)
+                    .pt(&map)
+                    .dist_to(sidewalk_pt)
+            })
+            .unwrap();
+        assert_eq!(on_road(closest), paid);
+        assert!(
+            sim.parking.spot_cost(parked, target, &map)
+                < sim.parking.spot_cost(closest, target, &map)
+        );
+
+        let cruised: Vec<Distance> = sim
+            .get_analytics()
+            .parking_cruising
+            .values()
+            .cloned()
+            .collect();
+        assert_eq!(cruised.len(), 1);
+        assert!(cruised[0] > Distance::ZERO);
+    }
+
+    // Everybody picks one mode for their whole day, so nobody drives to work and walks home.
+    #[test]
+    fn test_choose_modes_for_trip_chains() {
+        let mut timer = Timer::throwaway();
+        // Along the northern and southern sides of the block
+        let map = block_with_buildings(vec![Pt2D::new(250.0, 150.0), Pt2D::new(250.0, 250.0)]);
+        let home = BuildingID(0);
+        let work = BuildingID(1);
+        let walk = |from: BuildingID, to: BuildingID| {
+            SpawnTrip::JustWalking(
+                SidewalkSpot::building(from, &map),
+                SidewalkSpot::building(to, &map),
+            )
+        };
+        let mut scenario = Scenario::empty(&map, "modes");
+        for idx in 0..20 {
+            scenario.people.push(PersonSpec {
+                id: PersonID(idx),
+                trips: vec![
+                    IndividTrip {
+                        depart: Time::START_OF_DAY + Duration::hours(8),
+                        trip: walk(home, work),
+                    },
+                    IndividTrip {
+                        depart: Time::START_OF_DAY + Duration::hours(12),
+                        trip: walk(work, home),
+                    },
+                    IndividTrip {
+                        depart: Time::START_OF_DAY + Duration::hours(13),
+                        trip: walk(home, work),
+                    },
+                    IndividTrip {
+                        depart: Time::START_OF_DAY + Duration::hours(17),
+                        trip: walk(work, home),
+                    },
+                ],
+            });
+        }
+        let mode = |trip: &SpawnTrip| match trip {
+            SpawnTrip::JustWalking(_, _) => TripMode::Walk,
+            SpawnTrip::UsingBike(_, _) => TripMode::Bike,
+            SpawnTrip::MaybeUsingParkedCar(_, _) | SpawnTrip::CarAppearing { .. } => {
+                TripMode::Drive
+            }
+            SpawnTrip::UsingTransit(_, _, _, _, _) => TripMode::Transit,
+        };
+        let modes_per_person = |scenario: &Scenario| -> Vec<TripMode> {
+            scenario
+                .people
+                .iter()
+                .map(|person| {
+                    let first = mode(&person.trips[0].trip);
+                    assert!(person.trips.iter().all(|t| mode(&t.trip) == first));
+                    first
+                })
+                .collect()
+        };
+
+        // Walking and driving such a short way are about as appealing, so people split up.
+        let mut rng = XorShiftRng::from_seed([42; 16]);
+        let mut split = scenario.clone();
+        split.choose_modes(&LogitModeChoice::new(), &map, &mut rng, &mut timer);
+        let modes = modes_per_person(&split);
+        assert!(modes.contains(&TripMode::Walk));
+        assert!(modes.contains(&TripMode::Drive));
+        // Every driver needs a car waiting at home in the morning, and nobody needs one at work.
+        let drivers = modes.iter().filter(|m| **m == TripMode::Drive).count();
+        assert_eq!(
+            split.parked_cars_per_bldg.get(&home).cloned(),
+            Some(drivers)
+        );
+        assert_eq!(
+            split.parked_cars_per_bldg.get(&work).cloned().unwrap_or(0),
+            0
+        );
+
+        // If driving is far better, everybody drives the whole day.
+        let model = LogitModeChoice {
+            drive_constant: 50.0,
+            ..LogitModeChoice::new()
+        };
+        scenario.choose_modes(&model, &map, &mut rng, &mut timer);
+        assert!(modes_per_person(&scenario)
+            .into_iter()
+            .all(|m| m == TripMode::Drive));
+    }
+
+    // Closing the road a car is about to use should send it around the block, not strand it.
+    #[test]
+    fn test_reroute_after_live_edits() {
+        let mut timer = Timer::throwaway();
+        let mut map = block();
+        let road = |osm_way_id: i64| {
+            map.all_roads()
+                .iter()
+                .find(|r| r.orig_id.osm_way_id == osm_way_id)
+                .unwrap()
+        };
+        let driving_lane = |r: &Road| {
+            r.children_forwards
+                .iter()
+                .find(|(_, lt)| *lt == LaneType::Driving)
+                .unwrap()
+                .0
+        };
+        let start = driving_lane(road(1));
+        let end = driving_lane(road(3));
+        let closed = driving_lane(road(2));
+        let detour = driving_lane(road(5));
+        let border = road(3).dst_i;
+
+        let mut scenario = Scenario::empty(&map, "live edits");
+        scenario.people.push(PersonSpec {
+            id: PersonID(0),
+            trips: vec![IndividTrip {
+                depart: Time::START_OF_DAY,
+                trip: SpawnTrip::CarAppearing {
+                    start: Position::new(start, MAX_CAR_LENGTH),
+                    goal: DrivingGoal::Border(border, end),
+                    is_bike: false,
+                },
+            }],
+        });
+        let mut sim = Sim::new(&map, SimOptions::new("test"), &mut timer);
+        scenario.instantiate(
+            &mut sim,
+            &map,
+            &mut XorShiftRng::from_seed([42; 16]),
+            &mut timer,
+        );
+        sim.normal_step(&map, Duration::seconds(1.0));
+        let car = sim.active_agents()[0];
+        assert!(sim
+            .get_path(car)
+            .unwrap()
+            .get_steps()
+            .contains(&PathStep::Lane(closed)));
+
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(EditCmd::ChangeLaneType {
+            id: closed,
+            lt: LaneType::Construction,
+            orig_lt: LaneType::Driving,
+        });
+        map.apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+        sim.handle_live_edits(&map, &mut timer);
+
+        let path = sim.get_path(car).unwrap();
+        assert!(!path.get_steps().contains(&PathStep::Lane(closed)));
+        assert!(path.get_steps().contains(&PathStep::Lane(detour)));
+
+        sim.just_run_until_done(&map, Some(Duration::minutes(10)));
+        assert!(sim.is_done());
+        assert_eq!(
+            sim.get_analytics()
+                .finished_trips
+                .iter()
+                .map(|(_, _, mode, _)| *mode)
+                .collect::<Vec<_>>(),
+            vec![Some(TripMode::Drive)]
+        );
+    }
+}
@@ -17,15 +17,15 @@ type StopIdx = usize;
 struct StopForRoute {
     id: BusStopID,
     driving_pos: Position,
-    req: PathRequest,
-    path_to_next_stop: Path,
-    next_stop_idx: StopIdx,
+    // None for the last stop of a scheduled route; the bus vanishes there.
+    next_stop: Option<(StopIdx, PathRequest, Path)>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 struct Route {
     stops: Vec<StopForRoute>,
     buses: Vec<CarID>,
+    scheduled: bool,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
@@ -62,22 +62,25 @@ pub struct TransitSimState {
         deserialize_with = "deserialize_btreemap"
     )]
     peds_waiting: BTreeMap<BusStopID, Vec<(PedestrianID, BusRouteID, BusStopID, Time)>>,
+    // When set, people have to wait for a later bus once one is this full.
+    max_passengers: Option<usize>,
 
     events: Vec<Event>,
 }
 
 impl TransitSimState {
-    pub fn new() -> TransitSimState {
+    pub fn new(max_passengers: Option<usize>) -> TransitSimState {
         TransitSimState {
             buses: BTreeMap::new(),
             routes: BTreeMap::new(),
             peds_waiting: BTreeMap::new(),
+            max_passengers,
             events: Vec::new(),
         }
     }
 
     // Returns (next stop, first path, end distance for next stop) for all of the stops in the
-    // route.
+    // route that have a next stop.
     pub fn create_empty_route(
         &mut self,
         bus_route: &BusRoute,
@@ -87,16 +90,23 @@ impl TransitSimState {
 
         let route = Route {
             buses: Vec::new(),
+            scheduled: bus_route.is_scheduled(),
             stops: bus_route
                 .stops
                 .iter()
                 .enumerate()
                 .map(|(idx, stop1_id)| {
                     let stop1 = map.get_bs(*stop1_id);
-                    let stop2_idx = if idx + 1 == bus_route.stops.len() {
-                        0
-                    } else {
+                    let stop2_idx = if idx + 1 < bus_route.stops.len() {
                         idx + 1
+                    } else if bus_route.is_scheduled() {
+                        return StopForRoute {
+                            id: *stop1_id,
+                            driving_pos: stop1.driving_pos,
+                            next_stop: None,
+                        };
+                    } else {
+                        0
                     };
                     let req = PathRequest {
                         start: stop1.driving_pos,
@@ -110,9 +120,7 @@ impl TransitSimState {
                     StopForRoute {
                         id: *stop1_id,
                         driving_pos: stop1.driving_pos,
-                        req,
-                        path_to_next_stop: path,
-                        next_stop_idx: stop2_idx,
+                        next_stop: Some((stop2_idx, req, path)),
                     }
                 })
                 .collect(),
@@ -121,21 +129,54 @@ impl TransitSimState {
         let stops = route
             .stops
             .iter()
-            .map(|s| {
-                (
-                    s.next_stop_idx,
-                    s.req.clone(),
-                    s.path_to_next_stop.clone(),
-                    route.stops[s.next_stop_idx].driving_pos.dist_along(),
-                )
+            .filter_map(|s| {
+                let (next_idx, req, path) = s.next_stop.clone()?;
+                let end_dist = route.stops[next_idx].driving_pos.dist_along();
+                Some((next_idx, req, path, end_dist))
             })
             .collect();
         self.routes.insert(bus_route.id, route);
         stops
     }
 
-    pub fn bus_created(&mut self, bus: CarID, route: BusRouteID, next_stop_idx: StopIdx) {
+    // Where a bus on a scheduled route should appear: somewhere before the first stop, on the same
+    // lane. Returns (next stop, request, path, end distance for next stop). If the first stop is
+    // too close to the start of its lane, the bus starts past it and heads for the second stop.
+    pub fn scheduled_bus_start(
+        &self,
+        route: BusRouteID,
+        start_dist: Distance,
+        map: &Map,
+    ) -> (StopIdx, PathRequest, Path, Distance) {
+        let stops = &self.routes[&route].stops;
+        let first = &stops[0];
+        if first.driving_pos.dist_along() > start_dist {
+            let req = PathRequest {
+                start: Position::new(first.driving_pos.lane(), start_dist),
+                end: first.driving_pos,
+                constraints: PathConstraints::Bus,
+            };
+            let path = map.pathfind(req.clone()).unwrap();
+            return (0, req, path, first.driving_pos.dist_along());
+        }
+        let (next_idx, req, path) = first.next_stop.clone().unwrap();
+        let end_dist = stops[next_idx].driving_pos.dist_along();
+        (next_idx, req, path, end_dist)
+    }
+
+    // Buses on a scheduled route also say which trip of the schedule they're running.
+    pub fn bus_created(
+        &mut self,
+        bus: CarID,
+        route: BusRouteID,
+        next_stop_idx: StopIdx,
+        scheduled_trip: Option<usize>,
+    ) {
         self.routes.get_mut(&route).unwrap().buses.push(bus);
+        if let Some(trip) = scheduled_trip {
+            self.events
+                .push(Event::BusStartedScheduledTrip(bus, route, trip));
+        }
         self.buses.insert(
             bus,
             Bus {
@@ -155,12 +196,13 @@ impl TransitSimState {
         walking: &mut WalkingSimState,
         scheduler: &mut Scheduler,
         map: &Map,
-    ) {
+    ) -> bool {
         let mut bus = self.buses.get_mut(&id).unwrap();
         match bus.state {
             BusState::DrivingToStop(stop_idx) => {
                 bus.state = BusState::AtStop(stop_idx);
-                let stop1 = self.routes[&bus.route].stops[stop_idx].id;
+                let route = &self.routes[&bus.route];
+                let stop1 = route.stops[stop_idx].id;
                 self.events
                     .push(Event::BusArrivedAtStop(id, bus.route, stop1));
 
@@ -175,12 +217,34 @@ impl TransitSimState {
                 }
                 bus.passengers = still_riding;
 
+                if route.stops[stop_idx].next_stop.is_none() {
+                    // The end of the line. Nobody should board, since every stop they could want
+                    // is behind us.
+                    if !bus.passengers.is_empty() {
+                        println!(
+                            "WARNING: {} finished {} with passengers still aboard: {:?}",
+                            id, bus.route, bus.passengers
+                        );
+                    }
+                    let route_id = bus.route;
+                    self.buses.remove(&id);
+                    self.routes
+                        .get_mut(&route_id)
+                        .unwrap()
+                        .buses
+                        .retain(|b| *b != id);
+                    return false;
+                }
+
                 // Board new passengers.
                 let mut still_waiting = Vec::new();
                 for (ped, route, stop2, started_waiting) in
                     self.peds_waiting.remove(&stop1).unwrap_or_else(Vec::new)
                 {
-                    if bus.route == route {
+                    if bus.route == route
+                        && self.routes[&route].serves(stop_idx, stop2)
+                        && has_room(&bus.passengers, self.max_passengers)
+                    {
                         bus.passengers.push((ped, stop2));
                         let (trip, person) =
                             trips.ped_boarded_bus(now, ped, now - started_waiting, walking);
@@ -200,9 +264,10 @@ impl TransitSimState {
                     }
                 }
                 self.peds_waiting.insert(stop1, still_waiting);
+                true
             }
             BusState::AtStop(_) => unreachable!(),
-        };
+        }
     }
 
     pub fn bus_departed_from_stop(&mut self, id: CarID) -> Router {
@@ -212,13 +277,18 @@ impl TransitSimState {
             BusState::AtStop(stop_idx) => {
                 let route = &self.routes[&bus.route];
                 let stop = &route.stops[stop_idx];
+                let (next_idx, _, path) = stop.next_stop.as_ref().unwrap();
 
-                bus.state = BusState::DrivingToStop(stop.next_stop_idx);
-                self.events
-                    .push(Event::BusDepartedFromStop(id, bus.route, stop.id));
+                bus.state = BusState::DrivingToStop(*next_idx);
+                self.events.push(Event::BusDepartedFromStop(
+                    id,
+                    bus.route,
+                    stop.id,
+                    bus.passengers.len(),
+                ));
                 Router::follow_bus_route(
-                    stop.path_to_next_stop.clone(),
-                    route.stops[stop.next_stop_idx].driving_pos.dist_along(),
+                    path.clone(),
+                    route.stops[*next_idx].driving_pos.dist_along(),
                 )
             }
         }
@@ -240,7 +310,10 @@ impl TransitSimState {
         if let Some(route) = self.routes.get(&route_id) {
             for bus in &route.buses {
                 if let BusState::AtStop(idx) = self.buses[bus].state {
-                    if route.stops[idx].id == stop1 {
+                    if route.stops[idx].id == stop1
+                        && route.serves(idx, stop2)
+                        && has_room(&self.buses[bus].passengers, self.max_passengers)
+                    {
                         self.buses
                             .get_mut(bus)
                             .unwrap()
@@ -295,8 +368,12 @@ impl TransitSimState {
                 .map(|bus| {
                     let stop = match self.buses[bus].state {
                         BusState::DrivingToStop(idx) => {
-                            if idx == 0 {
+                            // Scheduled buses that haven't reached the first stop yet count as
+                            // being there already.
+                            if idx == 0 && !r.scheduled {
                                 r.stops.len() - 1
+                            } else if idx == 0 {
+                                0
                             } else {
                                 idx - 1
                             }
@@ -311,3 +388,19 @@ impl TransitSimState {
         }
     }
 }
+
+fn has_room(passengers: &Vec<(PedestrianID, BusStopID)>, max_passengers: Option<usize>) -> bool {
+    max_passengers
+        .map(|max| passengers.len() < max)
+        .unwrap_or(true)
+}
+
+impl Route {
+    // Can a bus currently at this stop still reach the destination?
+    fn serves(&self, current: StopIdx, dst: BusStopID) -> bool {
+        if !self.scheduled {
+            return true;
+        }
+        self.stops[current + 1..].iter().any(|s| s.id == dst)
+    }
+}
@@ -715,6 +715,15 @@ impl TripManager {
         self.trips[id.0].person
     }
 
+    // Is this person still in the middle of some other trip? This happens when an earlier trip
+    // runs late, like yesterday's trip in a multi-day simulation.
+    pub fn person_busy_with_other_trip(&self, person: PersonID, trip: TripID) -> bool {
+        match self.people[person.0].state {
+            PersonState::Trip(t) => t != trip,
+            _ => false,
+        }
+    }
+
     pub fn cancel_future_trips(&mut self, now: Time, p: PersonID, scheduler: &mut Scheduler) {
         let person = &self.people[p.0];
         println!("Aborting trips by {} ({:?}) after {}", p, person.state, now);